@@ -0,0 +1,88 @@
+use cgmath::Vector4;
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
+
+use hypervis::physics::{Broadphase, PhysicsWorld};
+use hypervis::shapes::{self, RegularSolid, ShapeBuilder};
+use hypervis::world::World;
+
+// A floor covered in a loose grid of tesseracts, some of which are touching.
+fn tesseract_field(count: usize, broadphase: Broadphase) -> PhysicsWorld {
+    let mut world = World::new();
+    world.physics.settings.broadphase = broadphase;
+    world.insert(shapes::create_floor(100.0, Default::default()));
+
+    let side = (count as f32).sqrt().ceil() as usize;
+    for i in 0..count {
+        let (x, z) = ((i % side) as f32, (i / side) as f32);
+        world.insert(
+            ShapeBuilder::new()
+                .regular_solid(RegularSolid::EightCell)
+                .position(Vector4::new(
+                    1.2 * x,
+                    0.5 + (i % 3) as f32,
+                    1.2 * z,
+                    0.0,
+                ))
+                .build()
+                .unwrap(),
+        );
+    }
+
+    world.physics
+}
+
+fn candidate_pairs(c: &mut Criterion) {
+    let mut group = c.benchmark_group("candidate_pairs");
+    for &count in [50, 200, 800].iter() {
+        for &broadphase in
+            [Broadphase::BruteForce, Broadphase::SweepAndPrune].iter()
+        {
+            let world = tesseract_field(count, broadphase);
+            group.bench_with_input(
+                BenchmarkId::new(format!("{:?}", broadphase), count),
+                &world,
+                |b, world| b.iter(|| broadphase.candidate_pairs(&world.bodies)),
+            );
+        }
+    }
+    group.finish();
+}
+
+// The broadphase and narrowphase together, which is the part of a step that
+// the broadphase is meant to speed up.
+fn find_collisions(c: &mut Criterion) {
+    let mut group = c.benchmark_group("find_collisions");
+    group.sample_size(20);
+    for &count in [50, 200, 800].iter() {
+        for &broadphase in
+            [Broadphase::BruteForce, Broadphase::SweepAndPrune].iter()
+        {
+            let mut world = tesseract_field(count, broadphase);
+            group.bench_function(
+                BenchmarkId::new(format!("{:?}", broadphase), count),
+                |b| b.iter(|| world.find_collisions()),
+            );
+        }
+    }
+    group.finish();
+}
+
+fn step(c: &mut Criterion) {
+    let mut group = c.benchmark_group("step");
+    group.sample_size(10);
+    for &count in [50, 200].iter() {
+        for &broadphase in
+            [Broadphase::BruteForce, Broadphase::SweepAndPrune].iter()
+        {
+            let mut world = tesseract_field(count, broadphase);
+            group.bench_function(
+                BenchmarkId::new(format!("{:?}", broadphase), count),
+                |b| b.iter(|| world.step(1.0 / 60.0)),
+            );
+        }
+    }
+    group.finish();
+}
+
+criterion_group!(benches, candidate_pairs, find_collisions, step);
+criterion_main!(benches);
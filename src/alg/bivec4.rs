@@ -1,7 +1,8 @@
 use super::{Quadvec4, Rotor4, Trivec4, Vec4};
+use serde::{Deserialize, Serialize};
 use std::ops::{Add, Mul};
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
 pub struct Bivec4 {
     pub xy: f32,
     pub xz: f32,
@@ -27,10 +28,32 @@ impl Bivec4 {
         Self::new(0.0, 0.0, 0.0, 0.0, 0.0, 0.0)
     }
 
+    pub fn mag(&self) -> f32 {
+        let b = self;
+        (b.xy * b.xy
+            + b.xz * b.xz
+            + b.xw * b.xw
+            + b.yz * b.yz
+            + b.yw * b.yw
+            + b.zw * b.zw)
+            .sqrt()
+    }
+
     pub fn reverse(&self) -> Self {
         Self::new(-self.xy, -self.xz, -self.xw, -self.yz, -self.yw, -self.zw)
     }
 
+    // Sum of the products of the components, which is the work done by a
+    // torque in one of the bivectors turning at a rate in the other.
+    pub fn dot(&self, c: &Bivec4) -> f32 {
+        self.xy * c.xy
+            + self.xz * c.xz
+            + self.xw * c.xw
+            + self.yz * c.yz
+            + self.yw * c.yw
+            + self.zw * c.zw
+    }
+
     pub fn dot_v(&self, v: &Vec4) -> Vec4 {
         let b = self;
         Vec4 {
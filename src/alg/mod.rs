@@ -5,7 +5,7 @@ mod trivec4;
 mod vec4;
 
 pub use bivec4::Bivec4;
-use quadvec4::Quadvec4;
+pub(crate) use quadvec4::Quadvec4;
 pub use rotor4::Rotor4;
 pub use trivec4::Trivec4;
 pub use vec4::Vec4;
@@ -1,7 +1,8 @@
 use super::{Bivec4, Trivec4, Vec4};
+use serde::{Deserialize, Serialize};
 use std::ops::{Add, Mul};
 
-#[derive(Debug, Copy, Clone)]
+#[derive(Debug, Copy, Clone, Serialize, Deserialize)]
 pub struct Quadvec4 {
     pub xyzw: f32,
 }
@@ -1,8 +1,9 @@
 use super::{Bivec4, Quadvec4, Vec4};
 use cgmath::Matrix4;
+use serde::{Deserialize, Serialize};
 use std::ops::{Add, Mul};
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
 pub struct Rotor4 {
     pub s: f32,
     pub b: Bivec4,
@@ -36,12 +37,41 @@ impl Rotor4 {
 
         // p = Q ~R
         let b_rev = self.b.reverse();
-        let p = self.s * q_1
+        self.s * q_1
             + q_1.left_contract_bv(&b_rev)
             + q_3.right_contract_bv(&b_rev)
-            + q_3.mul_qv(&self.q);
+            + q_3.mul_qv(&self.q)
+    }
+
+    pub fn rotate_bv(&self, b: &Bivec4) -> Bivec4 {
+        // R B ~R. Rotating each vector of the plane is the same as conjugating
+        // the plane's antisymmetric matrix by the rotation matrix.
+        let m = self.to_matrix();
+        #[rustfmt::skip]
+        let c = [
+            [0.0, b.xy, b.xz, b.xw],
+            [-b.xy, 0.0, b.yz, b.yw],
+            [-b.xz, -b.yz, 0.0, b.zw],
+            [-b.xw, -b.yw, -b.zw, 0.0],
+        ];
+        let component = |k: usize, l: usize| {
+            let mut sum = 0.0;
+            for (i, row) in c.iter().enumerate() {
+                for (j, c_ij) in row.iter().enumerate() {
+                    sum += m[i][k] * c_ij * m[j][l];
+                }
+            }
+            sum
+        };
 
-        p
+        Bivec4::new(
+            component(0, 1),
+            component(0, 2),
+            component(0, 3),
+            component(1, 2),
+            component(1, 3),
+            component(2, 3),
+        )
     }
 
     pub fn mul_bv(&self, c: &Bivec4) -> Rotor4 {
@@ -242,4 +272,26 @@ mod tests {
             r.to_matrix().determinant()
         );
     }
+
+    #[test]
+    fn rotate_bivector() {
+        let r = Bivec4::new(0.3, -0.2, 0.5, 0.1, 0.7, -0.4).exp();
+        let u = Vec4 {
+            x: 1.0,
+            y: 2.0,
+            z: -1.0,
+            w: 0.5,
+        };
+        let v = Vec4 {
+            x: -0.5,
+            y: 0.0,
+            z: 3.0,
+            w: 1.0,
+        };
+
+        let rotated = r.rotate_bv(&u.wedge_v(&v));
+        let expected = r.rotate(&u).wedge_v(&r.rotate(&v));
+        let error = rotated + expected.reverse();
+        assert!(error.mag() < 1e-4, "{:?} != {:?}", rotated, expected);
+    }
 }
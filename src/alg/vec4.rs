@@ -84,13 +84,13 @@ impl Add<Vec4> for Vec4 {
     }
 }
 
-impl Into<Vector4<f32>> for Vec4 {
-    fn into(self) -> Vector4<f32> {
+impl From<Vec4> for Vector4<f32> {
+    fn from(v: Vec4) -> Self {
         Vector4 {
-            x: self.x,
-            y: self.y,
-            z: self.z,
-            w: self.w,
+            x: v.x,
+            y: v.y,
+            z: v.z,
+            w: v.w,
         }
     }
 }
@@ -106,9 +106,9 @@ impl From<Vector4<f32>> for Vec4 {
     }
 }
 
-impl Into<cgmath::Vector4<f32>> for Vec4 {
-    fn into(self) -> cgmath::Vector4<f32> {
-        cgmath::Vector4::new(self.x, self.y, self.z, self.w)
+impl From<Vec4> for cgmath::Vector4<f32> {
+    fn from(v: Vec4) -> Self {
+        cgmath::Vector4::new(v.x, v.y, v.z, v.w)
     }
 }
 
@@ -0,0 +1,406 @@
+// Writers for dumping 3D slices of the world to common interchange formats.
+// Each object's slice is written out as a separate object/mesh, with the
+// per-vertex colours from the tetrahedral mesh preserved.
+
+use std::fmt::Write as _;
+use std::io::{self, Write};
+use std::path::Path;
+
+use cgmath::Vector3;
+
+use crate::graphics::Vertex3;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SliceFormat {
+    Obj,
+    Ply,
+    Glb,
+}
+
+impl SliceFormat {
+    pub fn from_path<P: AsRef<Path>>(path: P) -> Option<Self> {
+        let extension = path.as_ref().extension()?.to_str()?;
+        match extension.to_ascii_lowercase().as_str() {
+            "obj" => Some(SliceFormat::Obj),
+            "ply" => Some(SliceFormat::Ply),
+            "glb" => Some(SliceFormat::Glb),
+            _ => None,
+        }
+    }
+}
+
+pub fn write_slice<W: Write>(
+    writer: W,
+    format: SliceFormat,
+    objects: &[Vec<Vertex3>],
+) -> io::Result<()> {
+    match format {
+        SliceFormat::Obj => write_obj(writer, objects),
+        SliceFormat::Ply => write_ply(writer, objects),
+        SliceFormat::Glb => write_glb(writer, objects),
+    }
+}
+
+// The slicer can produce degenerate slivers, which have NaN normals. None of
+// the formats can represent those sensibly, so they get dropped.
+fn triangles(vertices: &[Vertex3]) -> impl Iterator<Item = &[Vertex3]> {
+    vertices.chunks_exact(3).filter(|triangle| {
+        let n = triangle[0].normal;
+        n.x.is_finite() && n.y.is_finite() && n.z.is_finite()
+    })
+}
+
+fn color_to_u8(c: f32) -> u8 {
+    (c.clamp(0.0, 1.0) * 255.0).round() as u8
+}
+
+// Wavefront OBJ. Vertex colours are written using the common `v x y z r g b`
+// extension, which Blender and MeshLab both understand.
+pub fn write_obj<W: Write>(
+    mut writer: W,
+    objects: &[Vec<Vertex3>],
+) -> io::Result<()> {
+    writeln!(writer, "# hypervis slice")?;
+
+    let mut vertex_count = 0;
+    for (i, object) in objects.iter().enumerate() {
+        writeln!(writer, "o object_{}", i)?;
+        for triangle in triangles(object) {
+            for v in triangle {
+                let p = v.position;
+                let c = v.color;
+                writeln!(
+                    writer,
+                    "v {} {} {} {} {} {}",
+                    p.x, p.y, p.z, c.x, c.y, c.z
+                )?;
+                let n = v.normal;
+                writeln!(writer, "vn {} {} {}", n.x, n.y, n.z)?;
+            }
+            writeln!(
+                writer,
+                "f {0}//{0} {1}//{1} {2}//{2}",
+                vertex_count + 1,
+                vertex_count + 2,
+                vertex_count + 3,
+            )?;
+            vertex_count += 3;
+        }
+    }
+
+    Ok(())
+}
+
+// ASCII PLY with normals and 8-bit RGBA colours.
+pub fn write_ply<W: Write>(
+    mut writer: W,
+    objects: &[Vec<Vertex3>],
+) -> io::Result<()> {
+    let face_count: usize =
+        objects.iter().map(|object| triangles(object).count()).sum();
+
+    writeln!(writer, "ply")?;
+    writeln!(writer, "format ascii 1.0")?;
+    writeln!(writer, "comment hypervis slice")?;
+    writeln!(writer, "element vertex {}", face_count * 3)?;
+    for property in &["x", "y", "z", "nx", "ny", "nz"] {
+        writeln!(writer, "property float {}", property)?;
+    }
+    for property in &["red", "green", "blue", "alpha"] {
+        writeln!(writer, "property uchar {}", property)?;
+    }
+    writeln!(writer, "element face {}", face_count)?;
+    writeln!(writer, "property list uchar uint vertex_indices")?;
+    writeln!(writer, "end_header")?;
+
+    for object in objects {
+        for v in triangles(object).flatten() {
+            let p = v.position;
+            let n = v.normal;
+            let c = v.color;
+            writeln!(
+                writer,
+                "{} {} {} {} {} {} {} {} {} {}",
+                p.x,
+                p.y,
+                p.z,
+                n.x,
+                n.y,
+                n.z,
+                color_to_u8(c.x),
+                color_to_u8(c.y),
+                color_to_u8(c.z),
+                color_to_u8(c.w),
+            )?;
+        }
+    }
+
+    for i in 0..face_count {
+        writeln!(writer, "3 {} {} {}", 3 * i, 3 * i + 1, 3 * i + 2)?;
+    }
+
+    Ok(())
+}
+
+const GLB_MAGIC: u32 = 0x4654_6c67;
+const GLB_CHUNK_JSON: u32 = 0x4e4f_534a;
+const GLB_CHUNK_BIN: u32 = 0x004e_4942;
+const GL_FLOAT: u32 = 5126;
+const GL_ARRAY_BUFFER: u32 = 34962;
+const GL_TRIANGLES: u32 = 4;
+
+fn push_f32s(bin: &mut Vec<u8>, values: &[f32]) {
+    for value in values {
+        bin.extend_from_slice(&value.to_le_bytes());
+    }
+}
+
+// Binary glTF 2.0. Every object becomes its own mesh and node, with
+// non-indexed POSITION, NORMAL and COLOR_0 attributes.
+pub fn write_glb<W: Write>(
+    mut writer: W,
+    objects: &[Vec<Vertex3>],
+) -> io::Result<()> {
+    let mut bin = Vec::new();
+    let mut accessors = Vec::new();
+    let mut buffer_views = Vec::new();
+    let mut meshes = Vec::new();
+
+    for object in objects {
+        let vertices: Vec<&Vertex3> = triangles(object).flatten().collect();
+        if vertices.is_empty() {
+            continue;
+        }
+
+        let mut min = Vector3::new(f32::INFINITY, f32::INFINITY, f32::INFINITY);
+        let mut max = -min;
+        for v in vertices.iter() {
+            for i in 0..3 {
+                min[i] = min[i].min(v.position[i]);
+                max[i] = max[i].max(v.position[i]);
+            }
+        }
+
+        let first_accessor = accessors.len();
+        let attributes: [(&str, usize); 3] =
+            [("VEC3", 3), ("VEC3", 3), ("VEC4", 4)];
+        for (attribute, &(ty, components)) in attributes.iter().enumerate() {
+            let offset = bin.len();
+            for v in vertices.iter() {
+                match attribute {
+                    0 => push_f32s(
+                        &mut bin,
+                        &[v.position.x, v.position.y, v.position.z],
+                    ),
+                    1 => push_f32s(
+                        &mut bin,
+                        &[v.normal.x, v.normal.y, v.normal.z],
+                    ),
+                    _ => push_f32s(
+                        &mut bin,
+                        &[v.color.x, v.color.y, v.color.z, v.color.w],
+                    ),
+                }
+            }
+
+            buffer_views.push(format!(
+                r#"{{"buffer":0,"byteOffset":{},"byteLength":{},"target":{}}}"#,
+                offset,
+                vertices.len() * components * 4,
+                GL_ARRAY_BUFFER,
+            ));
+
+            let mut accessor = format!(
+                r#"{{"bufferView":{},"componentType":{},"count":{},"type":"{}""#,
+                buffer_views.len() - 1,
+                GL_FLOAT,
+                vertices.len(),
+                ty,
+            );
+            if attribute == 0 {
+                // POSITION accessors are required to have bounds
+                write!(
+                    accessor,
+                    r#","min":[{},{},{}],"max":[{},{},{}]"#,
+                    min.x, min.y, min.z, max.x, max.y, max.z,
+                )
+                .unwrap();
+            }
+            accessor.push('}');
+            accessors.push(accessor);
+        }
+
+        meshes.push(format!(
+            r#"{{"primitives":[{{"attributes":{{"POSITION":{},"NORMAL":{},"COLOR_0":{}}},"mode":{}}}]}}"#,
+            first_accessor,
+            first_accessor + 1,
+            first_accessor + 2,
+            GL_TRIANGLES,
+        ));
+    }
+
+    // glTF doesn't allow empty arrays, so everything gets left out if there
+    // is nothing to write
+    let mut json =
+        String::from(r#"{"asset":{"version":"2.0","generator":"hypervis"}"#);
+    if meshes.is_empty() {
+        json.push_str(r#","scene":0,"scenes":[{}]"#);
+    } else {
+        let nodes: Vec<String> = (0..meshes.len())
+            .map(|i| format!(r#"{{"mesh":{}}}"#, i))
+            .collect();
+        let node_indices: Vec<String> =
+            (0..meshes.len()).map(|i| i.to_string()).collect();
+        write!(
+            json,
+            r#","scene":0,"scenes":[{{"nodes":[{}]}}],"nodes":[{}],"meshes":[{}],"accessors":[{}],"bufferViews":[{}],"buffers":[{{"byteLength":{}}}]"#,
+            node_indices.join(","),
+            nodes.join(","),
+            meshes.join(","),
+            accessors.join(","),
+            buffer_views.join(","),
+            bin.len(),
+        )
+        .unwrap();
+    }
+    json.push('}');
+
+    // chunks have to be 4-byte aligned. The JSON chunk is padded with spaces
+    // and the binary chunk with zeroes.
+    let mut json = json.into_bytes();
+    while json.len() % 4 != 0 {
+        json.push(b' ');
+    }
+    while bin.len() % 4 != 0 {
+        bin.push(0);
+    }
+
+    let mut total_length = 12 + 8 + json.len();
+    if !bin.is_empty() {
+        total_length += 8 + bin.len();
+    }
+
+    writer.write_all(&GLB_MAGIC.to_le_bytes())?;
+    writer.write_all(&2u32.to_le_bytes())?;
+    writer.write_all(&(total_length as u32).to_le_bytes())?;
+
+    writer.write_all(&(json.len() as u32).to_le_bytes())?;
+    writer.write_all(&GLB_CHUNK_JSON.to_le_bytes())?;
+    writer.write_all(&json)?;
+
+    if !bin.is_empty() {
+        writer.write_all(&(bin.len() as u32).to_le_bytes())?;
+        writer.write_all(&GLB_CHUNK_BIN.to_le_bytes())?;
+        writer.write_all(&bin)?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cgmath::Vector4;
+
+    fn triangle() -> Vec<Vertex3> {
+        let color = Vector4::new(1.0, 0.5, 0.0, 1.0);
+        let normal = Vector4::new(0.0, 0.0, 1.0, 1.0);
+        vec![
+            Vertex3 {
+                position: Vector4::new(0.0, 0.0, 0.0, 1.0),
+                color,
+                normal,
+            },
+            Vertex3 {
+                position: Vector4::new(1.0, 0.0, 0.0, 1.0),
+                color,
+                normal,
+            },
+            Vertex3 {
+                position: Vector4::new(0.0, 1.0, 0.0, 1.0),
+                color,
+                normal,
+            },
+        ]
+    }
+
+    fn degenerate() -> Vec<Vertex3> {
+        let mut vertices = triangle();
+        for v in vertices.iter_mut() {
+            v.normal = Vector4::new(f32::NAN, f32::NAN, f32::NAN, 1.0);
+        }
+        vertices
+    }
+
+    #[test]
+    fn format_from_path() {
+        assert_eq!(SliceFormat::from_path("a/b.OBJ"), Some(SliceFormat::Obj));
+        assert_eq!(SliceFormat::from_path("b.ply"), Some(SliceFormat::Ply));
+        assert_eq!(SliceFormat::from_path("b.glb"), Some(SliceFormat::Glb));
+        assert_eq!(SliceFormat::from_path("b.stl"), None);
+        assert_eq!(SliceFormat::from_path("b"), None);
+    }
+
+    #[test]
+    fn obj() {
+        let mut out = Vec::new();
+        write_obj(&mut out, &[triangle(), degenerate(), triangle()]).unwrap();
+        let out = String::from_utf8(out).unwrap();
+
+        assert_eq!(out.lines().filter(|l| l.starts_with("o ")).count(), 3);
+        assert_eq!(out.lines().filter(|l| l.starts_with("v ")).count(), 6);
+        assert!(out.contains("v 1 0 0 1 0.5 0\n"));
+        assert!(out.contains("f 4//4 5//5 6//6\n"));
+    }
+
+    #[test]
+    fn ply() {
+        let mut out = Vec::new();
+        write_ply(&mut out, &[triangle(), degenerate()]).unwrap();
+        let out = String::from_utf8(out).unwrap();
+
+        assert!(out.starts_with("ply\nformat ascii 1.0\n"));
+        assert!(out.contains("element vertex 3\n"));
+        assert!(out.contains("element face 1\n"));
+        assert!(out.contains("0 1 0 0 0 1 255 128 0 255\n"));
+        assert!(out.ends_with("end_header\n0 0 0 0 0 1 255 128 0 255\n1 0 0 0 0 1 255 128 0 255\n0 1 0 0 0 1 255 128 0 255\n3 0 1 2\n"));
+    }
+
+    fn read_u32(bytes: &[u8], offset: usize) -> u32 {
+        let mut word = [0; 4];
+        word.copy_from_slice(&bytes[offset..offset + 4]);
+        u32::from_le_bytes(word)
+    }
+
+    #[test]
+    fn glb() {
+        let mut out = Vec::new();
+        write_glb(&mut out, &[triangle(), triangle()]).unwrap();
+
+        assert_eq!(read_u32(&out, 0), GLB_MAGIC);
+        assert_eq!(read_u32(&out, 4), 2);
+        assert_eq!(read_u32(&out, 8) as usize, out.len());
+
+        let json_length = read_u32(&out, 12) as usize;
+        assert_eq!(read_u32(&out, 16), GLB_CHUNK_JSON);
+        let json = std::str::from_utf8(&out[20..20 + json_length]).unwrap();
+        assert!(json.contains(r#""nodes":[{"mesh":0},{"mesh":1}]"#));
+        assert!(json.contains(r#""min":[0,0,0],"max":[1,1,0]"#));
+
+        // 2 objects * 3 vertices * (3 + 3 + 4) floats
+        let bin_offset = 20 + json_length;
+        assert_eq!(read_u32(&out, bin_offset) as usize, 2 * 3 * 10 * 4);
+        assert_eq!(read_u32(&out, bin_offset + 4), GLB_CHUNK_BIN);
+        assert_eq!(out.len(), bin_offset + 8 + 2 * 3 * 10 * 4);
+    }
+
+    #[test]
+    fn empty_glb() {
+        let mut out = Vec::new();
+        write_glb(&mut out, &[degenerate()]).unwrap();
+
+        assert_eq!(read_u32(&out, 8) as usize, out.len());
+        let json_length = read_u32(&out, 12) as usize;
+        assert_eq!(out.len(), 20 + json_length);
+    }
+}
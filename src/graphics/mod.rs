@@ -0,0 +1,12 @@
+// Plain data types shared with the GPU. These are laid out to match the
+// structs in the shaders, but don't depend on any graphics API themselves.
+
+mod slice_plane;
+mod transform4;
+mod vertex3;
+mod vertex4;
+
+pub use slice_plane::*;
+pub use transform4::*;
+pub use vertex3::*;
+pub use vertex4::*;
@@ -2,9 +2,10 @@ use cgmath::{
     prelude::{One, Zero},
     Matrix4, Vector4,
 };
+use serde::{Deserialize, Serialize};
 
 #[repr(C)]
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
 pub struct SlicePlane {
     pub normal: Vector4<f32>,
     pub base_point: Vector4<f32>,
@@ -0,0 +1,9 @@
+use cgmath::Vector4;
+
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+pub struct Vertex3 {
+    pub position: Vector4<f32>,
+    pub color: Vector4<f32>,
+    pub normal: Vector4<f32>,
+}
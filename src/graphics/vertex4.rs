@@ -0,0 +1,11 @@
+use cgmath::Vector4;
+
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+pub struct Vertex4 {
+    pub position: Vector4<f32>,
+    pub color: Vector4<f32>,
+}
+
+unsafe impl bytemuck::Pod for Vertex4 {}
+unsafe impl bytemuck::Zeroable for Vertex4 {}
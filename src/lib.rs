@@ -0,0 +1,11 @@
+pub mod alg;
+pub mod export;
+pub mod graphics;
+pub mod mesh;
+pub mod physics;
+pub mod scene;
+pub mod shapes;
+pub mod world;
+
+mod mesh4;
+mod util;
@@ -3,13 +3,20 @@
 use super::{Cell, Mesh};
 use cgmath::{InnerSpace, Vector4};
 use smallvec::{smallvec, SmallVec};
+use std::collections::hash_map::DefaultHasher;
 use std::collections::HashMap;
+use std::hash::{Hash, Hasher};
 
 use crate::util::EPSILON;
 
 #[derive(Debug)]
 struct ClipVertex {
     point: Vector4<f32>,
+    // Identifies where this vertex came from: either a vertex of the original
+    // mesh, or the intersection of a clip plane with an edge between two other
+    // vertices. This stays the same between clips of the same features, which
+    // is what lets contact points be matched up from one step to the next.
+    id: u64,
     distance: f32,
     // temporary variable used for getting the open polyline. Can be initialised
     // to anything you want - it'll be reset before it's used
@@ -35,8 +42,10 @@ pub struct ClipMesh {
     vertices: Vec<ClipVertex>,
     edges: Vec<ClipEdge>,
     faces: Vec<ClipFace>,
+    clip_count: u64,
 }
 
+#[allow(clippy::enum_variant_names)]
 enum ProcessVertexResult {
     NoneClipped,
     AllClipped,
@@ -49,6 +58,7 @@ impl ClipMesh {
     }
 
     pub fn clip_by(&mut self, clip_normal: Vector4<f32>, clip_distance: f32) {
+        self.clip_count += 1;
         match self.process_vertices(clip_normal, clip_distance) {
             ProcessVertexResult::NoneClipped => return,
             ProcessVertexResult::AllClipped => {
@@ -70,9 +80,22 @@ impl ClipMesh {
     }
 
     pub fn to_vertices(self) -> Vec<Vector4<f32>> {
+        self.to_vertices_with_ids()
+            .into_iter()
+            .map(|(_, point)| point)
+            .collect()
+    }
+
+    pub fn to_vertices_with_ids(self) -> Vec<(u64, Vector4<f32>)> {
         self.vertices
             .into_iter()
-            .filter_map(|v| if v.visible { Some(v.point) } else { None })
+            .filter_map(|v| {
+                if v.visible {
+                    Some((v.id, v.point))
+                } else {
+                    None
+                }
+            })
             .collect()
     }
 
@@ -102,11 +125,11 @@ impl ClipMesh {
         }
 
         if negative == 0 {
-            return ProcessVertexResult::NoneClipped;
+            ProcessVertexResult::NoneClipped
         } else if positive == 0 {
-            return ProcessVertexResult::AllClipped;
+            ProcessVertexResult::AllClipped
         } else {
-            return ProcessVertexResult::PartiallyClipped;
+            ProcessVertexResult::PartiallyClipped
         }
     }
 
@@ -128,7 +151,7 @@ impl ClipMesh {
                             break;
                         }
                     }
-                    if face.edges.len() == 0 {
+                    if face.edges.is_empty() {
                         face.visible = false;
                     }
                 }
@@ -148,9 +171,21 @@ impl ClipMesh {
                     * self.vertices[edge.hd_vertex].point
                     + t * self.vertices[edge.tl_vertex].point;
 
+                let id = {
+                    let (id0, id1) = (
+                        self.vertices[edge.hd_vertex].id,
+                        self.vertices[edge.tl_vertex].id,
+                    );
+                    let mut hasher = DefaultHasher::new();
+                    (self.clip_count, id0.min(id1), id0.max(id1))
+                        .hash(&mut hasher);
+                    hasher.finish()
+                };
+
                 let fresh_idx = self.vertices.len();
                 self.vertices.push(ClipVertex {
                     point: intersection,
+                    id,
                     distance: 0.0,
                     occurs: 0,
                     visible: true,
@@ -219,16 +254,16 @@ impl ClipMesh {
         for edge_idx in face.edges.iter() {
             let edge = &self.edges[*edge_idx];
             if self.vertices[edge.hd_vertex].occurs == 1 {
-                if start == None {
+                if start.is_none() {
                     start = Some(edge.hd_vertex);
-                } else if end == None {
+                } else if end.is_none() {
                     end = Some(edge.hd_vertex);
                 }
             }
             if self.vertices[edge.tl_vertex].occurs == 1 {
-                if start == None {
+                if start.is_none() {
                     start = Some(edge.tl_vertex);
-                } else if end == None {
+                } else if end.is_none() {
                     end = Some(edge.tl_vertex);
                 }
             }
@@ -279,6 +314,7 @@ impl<'a> ClipMeshBuilder<'a> {
             vertices: self.vertices,
             edges: self.edges,
             faces: self.faces,
+            clip_count: 0,
         }
     }
 
@@ -289,6 +325,7 @@ impl<'a> ClipMeshBuilder<'a> {
                 let fresh_idx = self.vertices.len();
                 self.vertices.push(ClipVertex {
                     point: self.mesh.vertices[vertex_idx],
+                    id: vertex_idx as u64,
                     distance: 0.0,
                     occurs: 0,
                     visible: true,
@@ -0,0 +1,525 @@
+// Reading and writing meshes, so that polytopes can be shared with other
+// tools.
+//
+// Polytopes use the 4OFF text format, as written by e.g. Stella4D:
+//
+//     4OFF
+//     # Vertices, Faces, Edges, Cells
+//     16 24 32 8
+//     -0.5 -0.5 -0.5 -0.5
+//     ...
+//     4 0 1 3 2
+//     ...
+//     6 0 1 2 3 4 5
+//     ...
+//
+// The counts are followed by one line per vertex with its coordinates, one
+// line per face with its number of vertices and then the vertices in order
+// around the face, and one line per cell with its number of faces and then
+// the faces. The edge count is ignored when reading. Anything after the
+// indices on a line (usually a colour) is ignored, as are comments starting
+// with #.
+//
+// Tetrahedral meshes use a little-endian binary format: the magic bytes TET4,
+// a u32 version (currently 1), u32 vertex and tetrahedron counts, then every
+// vertex as 4 f32 position coordinates and 4 f32 colour components, then
+// every tetrahedron as 4 u32 vertex indices.
+
+use super::tetrahedra::get_face_vertex_indices;
+use super::{Mesh, MeshError, TetrahedronMesh};
+use crate::graphics::Vertex4;
+use cgmath::{Vector4, Zero};
+use std::error::Error;
+use std::fmt;
+use std::fmt::Write as _;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+const TETRAHEDRON_MAGIC: &[u8; 4] = b"TET4";
+const TETRAHEDRON_VERSION: u32 = 1;
+
+#[derive(Debug)]
+pub enum MeshFileError {
+    Io(io::Error),
+    // A problem with the contents of a 4OFF file, and the line it's on.
+    Syntax { line: usize, message: String },
+    // A problem with the contents of a binary file.
+    Malformed(&'static str),
+    // The file was read fine, but doesn't describe a valid convex polytope.
+    Invalid(Vec<MeshError>),
+}
+
+impl fmt::Display for MeshFileError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            MeshFileError::Io(e) => {
+                write!(f, "could not access mesh file: {}", e)
+            }
+            MeshFileError::Syntax { line, message } => {
+                write!(f, "invalid mesh on line {}: {}", line, message)
+            }
+            MeshFileError::Malformed(message) => {
+                write!(f, "invalid mesh: {}", message)
+            }
+            MeshFileError::Invalid(errors) => {
+                write!(f, "invalid polytope: ")?;
+                for (i, error) in errors.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, "; ")?;
+                    }
+                    write!(f, "{}", error)?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+impl Error for MeshFileError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            MeshFileError::Io(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for MeshFileError {
+    fn from(e: io::Error) -> Self {
+        MeshFileError::Io(e)
+    }
+}
+
+// Splits a 4OFF file into its non-empty lines, without comments, along with
+// their line numbers.
+struct Lines<'a> {
+    lines: std::iter::Enumerate<std::str::Lines<'a>>,
+    line: usize,
+}
+
+impl<'a> Lines<'a> {
+    fn new(s: &'a str) -> Self {
+        Self {
+            lines: s.lines().enumerate(),
+            line: 0,
+        }
+    }
+
+    fn error(&self, message: impl Into<String>) -> MeshFileError {
+        MeshFileError::Syntax {
+            line: self.line,
+            message: message.into(),
+        }
+    }
+
+    fn next_line(&mut self) -> Result<Vec<&'a str>, MeshFileError> {
+        for (i, line) in &mut self.lines {
+            self.line = i + 1;
+            let line = line.split('#').next().unwrap();
+            let tokens: Vec<&str> = line.split_whitespace().collect();
+            if !tokens.is_empty() {
+                return Ok(tokens);
+            }
+        }
+        Err(self.error("unexpected end of file"))
+    }
+
+    // Parses the first n tokens of the next line, ignoring the rest.
+    fn next_values<T: std::str::FromStr>(
+        &mut self,
+        n: usize,
+    ) -> Result<Vec<T>, MeshFileError> {
+        let tokens = self.next_line()?;
+        if tokens.len() < n {
+            return Err(self.error(format!(
+                "expected {} values but found {}",
+                n,
+                tokens.len()
+            )));
+        }
+        tokens[..n]
+            .iter()
+            .map(|token| {
+                token.parse().map_err(|_| {
+                    self.error(format!("invalid number {}", token))
+                })
+            })
+            .collect()
+    }
+
+    // Parses a line consisting of a count followed by that many indices, all
+    // of which have to be less than the limit.
+    fn next_indices(
+        &mut self,
+        min_count: usize,
+        limit: usize,
+        what: &str,
+    ) -> Result<Vec<usize>, MeshFileError> {
+        let tokens = self.next_line()?;
+        let count: usize = tokens[0]
+            .parse()
+            .map_err(|_| self.error(format!("invalid count {}", tokens[0])))?;
+        if count < min_count {
+            return Err(self.error(format!(
+                "expected at least {} {}s but found {}",
+                min_count, what, count
+            )));
+        }
+        // tokens is never empty, and this can't overflow for huge counts
+        if tokens.len() - 1 < count {
+            return Err(self.error(format!(
+                "expected {} {}s but found {}",
+                count,
+                what,
+                tokens.len() - 1
+            )));
+        }
+        tokens[1..=count]
+            .iter()
+            .map(|token| match token.parse() {
+                Ok(index) if index < limit => Ok(index),
+                _ => Err(self.error(format!("invalid {} {}", what, token))),
+            })
+            .collect()
+    }
+}
+
+impl Mesh {
+    // Reads a polytope from a 4OFF file. The polytope is moved so that the
+    // average of its vertices is at the origin, and has to be convex.
+    pub fn from_4off(s: &str) -> Result<Self, MeshFileError> {
+        let mut lines = Lines::new(s);
+
+        let header = lines.next_line()?;
+        if header != ["4OFF"] {
+            return Err(lines.error("expected 4OFF header"));
+        }
+        let counts: Vec<usize> = lines.next_values(4)?;
+        let (vertex_count, face_count, cell_count) =
+            (counts[0], counts[1], counts[3]);
+        if vertex_count == 0 || cell_count == 0 {
+            return Err(lines.error("a polytope needs vertices and cells"));
+        }
+
+        let mut vertices = Vec::new();
+        for _ in 0..vertex_count {
+            let v: Vec<f32> = lines.next_values(4)?;
+            // Rust parses nan and inf, but they're no use as coordinates.
+            if let Some(x) = v.iter().find(|x| !x.is_finite()) {
+                return Err(lines.error(format!("invalid coordinate {}", x)));
+            }
+            vertices.push(Vector4::new(v[0], v[1], v[2], v[3]));
+        }
+        let mut faces = Vec::new();
+        for _ in 0..face_count {
+            faces.push(lines.next_indices(3, vertex_count, "vertex")?);
+        }
+        let mut cell_faces = Vec::new();
+        for _ in 0..cell_count {
+            let cell = lines.next_indices(4, face_count, "face")?;
+            cell_faces.push(
+                cell.into_iter().map(|face| faces[face].clone()).collect(),
+            );
+        }
+
+        let centre = vertices
+            .iter()
+            .fold(Vector4::zero(), |acc: Vector4<f32>, v| acc + v)
+            / vertex_count as f32;
+        for v in vertices.iter_mut() {
+            *v -= centre;
+        }
+        Mesh::try_from_cells(vertices, &cell_faces)
+            .map_err(MeshFileError::Invalid)
+    }
+
+    pub fn to_4off(&self) -> String {
+        let mut s = String::new();
+        writeln!(s, "4OFF").unwrap();
+        writeln!(s, "# Vertices, Faces, Edges, Cells").unwrap();
+        writeln!(
+            s,
+            "{} {} {} {}",
+            self.vertices.len(),
+            self.faces.len(),
+            self.edges.len(),
+            self.cells.len()
+        )
+        .unwrap();
+
+        for v in self.vertices.iter() {
+            writeln!(s, "{} {} {} {}", v.x, v.y, v.z, v.w).unwrap();
+        }
+        let mut write_indices = |indices: &[usize]| {
+            write!(s, "{}", indices.len()).unwrap();
+            for i in indices {
+                write!(s, " {}", i).unwrap();
+            }
+            s.push('\n');
+        };
+        for face_idx in 0..self.faces.len() {
+            write_indices(&get_face_vertex_indices(self, face_idx));
+        }
+        for cell in self.cells.iter() {
+            write_indices(&cell.faces);
+        }
+
+        s
+    }
+
+    pub fn load_4off<P: AsRef<Path>>(path: P) -> Result<Self, MeshFileError> {
+        Self::from_4off(&fs::read_to_string(path)?)
+    }
+
+    pub fn save_4off<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        fs::write(path, self.to_4off())
+    }
+}
+
+// Reads little-endian words from the front of a byte slice.
+struct Reader<'a> {
+    bytes: &'a [u8],
+}
+
+impl<'a> Reader<'a> {
+    fn word(&mut self) -> Result<[u8; 4], MeshFileError> {
+        if self.bytes.len() < 4 {
+            return Err(MeshFileError::Malformed("unexpected end of file"));
+        }
+        let (word, rest) = self.bytes.split_at(4);
+        self.bytes = rest;
+        let mut result = [0; 4];
+        result.copy_from_slice(word);
+        Ok(result)
+    }
+
+    fn u32(&mut self) -> Result<u32, MeshFileError> {
+        Ok(u32::from_le_bytes(self.word()?))
+    }
+
+    fn vector(&mut self) -> Result<Vector4<f32>, MeshFileError> {
+        let mut v = Vector4::zero();
+        for i in 0..4 {
+            v[i] = f32::from_le_bytes(self.word()?);
+        }
+        Ok(v)
+    }
+}
+
+impl TetrahedronMesh {
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, MeshFileError> {
+        let mut reader = Reader { bytes };
+        if &reader.word()? != TETRAHEDRON_MAGIC {
+            return Err(MeshFileError::Malformed("not a tetrahedral mesh"));
+        }
+        if reader.u32()? != TETRAHEDRON_VERSION {
+            return Err(MeshFileError::Malformed("unsupported version"));
+        }
+        let vertex_count = reader.u32()? as usize;
+        let tetrahedron_count = reader.u32()? as usize;
+        // checked up front, so that nonsense counts can't cause huge
+        // allocations
+        let expected_len = vertex_count * 32 + tetrahedron_count * 16;
+        if reader.bytes.len() != expected_len {
+            return Err(MeshFileError::Malformed("wrong length for counts"));
+        }
+
+        let mut vertices = Vec::new();
+        for _ in 0..vertex_count {
+            let position = reader.vector()?;
+            let color = reader.vector()?;
+            vertices.push(Vertex4 { position, color });
+        }
+        let mut indices = Vec::with_capacity(tetrahedron_count * 4);
+        for _ in 0..tetrahedron_count * 4 {
+            let index = reader.u32()?;
+            if index as usize >= vertex_count {
+                return Err(MeshFileError::Malformed("vertex out of range"));
+            }
+            indices.push(index);
+        }
+
+        Ok(Self { vertices, indices })
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(
+            16 + self.vertices.len() * 32 + self.indices.len() * 4,
+        );
+        bytes.extend_from_slice(TETRAHEDRON_MAGIC);
+        for word in [
+            TETRAHEDRON_VERSION,
+            self.vertices.len() as u32,
+            (self.indices.len() / 4) as u32,
+        ]
+        .iter()
+        {
+            bytes.extend_from_slice(&word.to_le_bytes());
+        }
+        for vertex in self.vertices.iter() {
+            for v in [vertex.position, vertex.color].iter() {
+                for i in 0..4 {
+                    bytes.extend_from_slice(&v[i].to_le_bytes());
+                }
+            }
+        }
+        for index in self.indices.iter() {
+            bytes.extend_from_slice(&index.to_le_bytes());
+        }
+        bytes
+    }
+
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, MeshFileError> {
+        Self::from_bytes(&fs::read(path)?)
+    }
+
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        fs::write(path, self.to_bytes())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cgmath::InnerSpace;
+
+    fn counts(mesh: &Mesh) -> [usize; 4] {
+        [
+            mesh.vertices.len(),
+            mesh.edges.len(),
+            mesh.faces.len(),
+            mesh.cells.len(),
+        ]
+    }
+
+    #[test]
+    fn polytope_round_trip() {
+        for symbol in [[3, 3, 3], [4, 3, 3], [3, 4, 3], [5, 3, 3]].iter() {
+            let mesh = Mesh::from_schlafli_symbol(symbol);
+            let loaded = Mesh::from_4off(&mesh.to_4off()).unwrap();
+            assert_eq!(counts(&loaded), counts(&mesh));
+            for (a, b) in loaded.vertices.iter().zip(mesh.vertices.iter()) {
+                assert!((a - b).magnitude() < 1e-5);
+            }
+            for (a, b) in loaded.cells.iter().zip(mesh.cells.iter()) {
+                assert!((a.normal - b.normal).magnitude() < 1e-5);
+            }
+        }
+    }
+
+    #[test]
+    fn stella_style_file() {
+        // a 5-cell, off-centre, with colours and comments like Stella4D
+        // writes
+        let file = "4OFF
+            # Vertices, Faces, Edges, Cells
+            5 10 10 5
+
+            # Vertices
+            1 0 0 0
+            0 1 0 0
+            0 0 1 0
+            0 0 0 1
+            0 0 0 0
+
+            # Faces
+            3 0 1 2 255 0 0
+            3 0 1 3 255 0 0
+            3 0 1 4 255 0 0
+            3 0 2 3 255 0 0
+            3 0 2 4 255 0 0
+            3 0 3 4 255 0 0
+            3 1 2 3 255 0 0
+            3 1 2 4 255 0 0
+            3 1 3 4 255 0 0
+            3 2 3 4 255 0 0
+
+            # Cells
+            4 0 1 3 6
+            4 0 2 4 7
+            4 1 2 5 8
+            4 3 4 5 9
+            4 6 7 8 9
+        ";
+        let mesh = Mesh::from_4off(file).unwrap();
+        assert_eq!(counts(&mesh), [5, 10, 10, 5]);
+        let centre = Vector4::new(0.2, 0.2, 0.2, 0.2);
+        assert!((mesh.vertices[4] + centre).magnitude() < 1e-6);
+        assert!(
+            (mesh.mass_properties(1.0).hypervolume - 1.0 / 24.0).abs() < 1e-6
+        );
+    }
+
+    #[test]
+    fn bad_polytopes_are_errors() {
+        let error = |s: &str| Mesh::from_4off(s).unwrap_err();
+
+        assert!(matches!(
+            error("OFF\n4 4 6\n"),
+            MeshFileError::Syntax { line: 1, .. }
+        ));
+        assert!(matches!(
+            error("4OFF\n# comment\n1 0 0 1\n0 0 zero 0\n"),
+            MeshFileError::Syntax { line: 4, .. }
+        ));
+        assert!(matches!(
+            error("4OFF\n1 1 0 1\n0 0 0 0\n3 0 1 2\n"),
+            MeshFileError::Syntax { line: 4, .. }
+        ));
+        let huge = format!("4OFF\n1 1 0 1\n0 0 0 0\n{} 0 0 0\n", usize::MAX);
+        assert!(matches!(
+            error(&huge),
+            MeshFileError::Syntax { line: 4, .. }
+        ));
+        for coordinate in ["nan", "inf", "-inf", "NaN", "infinity"].iter() {
+            let file =
+                format!("4OFF\n2 1 0 1\n0 0 0 0\n0 {} 0 0\n", coordinate);
+            assert!(matches!(
+                error(&file),
+                MeshFileError::Syntax { line: 4, .. }
+            ));
+        }
+
+        // a valid file, but missing a cell of the tesseract
+        let mut file = Mesh::from_schlafli_symbol(&[4, 3, 3]).to_4off();
+        file = file.replace("16 24 32 8", "16 24 32 7");
+        assert!(matches!(
+            error(&file),
+            MeshFileError::Invalid(errors) if !errors.is_empty()
+        ));
+    }
+
+    #[test]
+    fn tetrahedra_round_trip() {
+        let mesh = Mesh::from_schlafli_symbol(&[3, 4, 3]);
+        let tetrahedra = TetrahedronMesh::from_mesh(&mesh, |normal| {
+            Vector4::new(normal.x, normal.y, 0.5, 1.0)
+        });
+
+        let bytes = tetrahedra.to_bytes();
+        let loaded = TetrahedronMesh::from_bytes(&bytes).unwrap();
+        assert_eq!(loaded.indices, tetrahedra.indices);
+        assert_eq!(loaded.vertices.len(), tetrahedra.vertices.len());
+        for (a, b) in loaded.vertices.iter().zip(tetrahedra.vertices.iter()) {
+            assert_eq!(a.position, b.position);
+            assert_eq!(a.color, b.color);
+        }
+
+        assert!(matches!(
+            TetrahedronMesh::from_bytes(&bytes[..bytes.len() - 1]),
+            Err(MeshFileError::Malformed(_))
+        ));
+        assert!(matches!(
+            TetrahedronMesh::from_bytes(b"OFF4"),
+            Err(MeshFileError::Malformed(_))
+        ));
+        let mut bad_index = bytes;
+        let len = bad_index.len();
+        bad_index[len - 4..].copy_from_slice(&1000u32.to_le_bytes());
+        assert!(matches!(
+            TetrahedronMesh::from_bytes(&bad_index),
+            Err(MeshFileError::Malformed("vertex out of range"))
+        ));
+    }
+}
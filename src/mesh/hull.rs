@@ -0,0 +1,520 @@
+// Convex hulls of arbitrary point clouds, so that custom convex bodies can be
+// built without having to list out their cells by hand.
+
+use super::Mesh;
+use crate::alg::triple_cross_product;
+use crate::util::NotNaN;
+use cgmath::{InnerSpace, Vector2, Vector4, Zero};
+use std::collections::{BTreeMap, HashMap};
+
+// Points closer than this to a hyperplane count as being on it, as a fraction
+// of the size of the point cloud.
+const HULL_TOLERANCE: f32 = 1e-5;
+
+// A tetrahedral facet of the hull while it's being built.
+struct Facet {
+    vertices: [usize; 4],
+    normal: Vector4<f32>,
+    offset: f32,
+    // The points which are above this facet and haven't been added yet.
+    outside: Vec<usize>,
+    alive: bool,
+}
+
+impl Facet {
+    // The origin has to be strictly inside the hull, which is used to orient
+    // the normal outwards.
+    fn new(points: &[Vector4<f32>], vertices: [usize; 4]) -> Self {
+        let v0 = points[vertices[0]];
+        let mut normal = triple_cross_product(
+            points[vertices[1]] - v0,
+            points[vertices[2]] - v0,
+            points[vertices[3]] - v0,
+        )
+        .normalize();
+        if normal.dot(v0) < 0.0 {
+            normal = -normal;
+        }
+
+        Self {
+            vertices,
+            normal,
+            offset: normal.dot(v0),
+            outside: Vec::new(),
+            alive: true,
+        }
+    }
+
+    fn distance(&self, p: Vector4<f32>) -> f32 {
+        self.normal.dot(p) - self.offset
+    }
+
+    fn ridges(&self) -> [[usize; 3]; 4] {
+        let [a, b, c, d] = self.vertices;
+        let mut ridges = [[b, c, d], [a, c, d], [a, b, d], [a, b, c]];
+        for ridge in ridges.iter_mut() {
+            ridge.sort_unstable();
+        }
+        ridges
+    }
+}
+
+// Distance of p from the affine span of the basis (which is orthonormal and
+// based at origin), along with the direction it's in.
+fn distance_from_span(
+    origin: Vector4<f32>,
+    basis: &[Vector4<f32>],
+    p: Vector4<f32>,
+) -> (f32, Vector4<f32>) {
+    let mut v = p - origin;
+    for b in basis.iter() {
+        v -= b * v.dot(*b);
+    }
+    (v.magnitude(), v)
+}
+
+// Greedily picks points from indices which are spread out as much as
+// possible, starting from the first one, until they span the given number of
+// dimensions. Returns the points picked and an orthonormal basis for their
+// span, or None if the points don't span that many dimensions.
+fn spread_points(
+    points: &[Vector4<f32>],
+    indices: &[usize],
+    dimensions: usize,
+    tolerance: f32,
+) -> Option<(Vec<usize>, Vec<Vector4<f32>>)> {
+    let origin = points[*indices.first()?];
+    let mut picked = vec![indices[0]];
+    let mut basis = Vec::new();
+    for _ in 0..dimensions {
+        let (i, (distance, direction)) = indices
+            .iter()
+            .map(|&i| (i, distance_from_span(origin, &basis, points[i])))
+            .max_by_key(|(_, (distance, _))| NotNaN::new(*distance).unwrap())?;
+        if distance <= tolerance {
+            return None;
+        }
+        picked.push(i);
+        basis.push(direction / distance);
+    }
+    Some((picked, basis))
+}
+
+// Finds five points which span all four dimensions and are spread out as much
+// as possible, or None if the points all lie in a hyperplane.
+fn initial_simplex(
+    points: &[Vector4<f32>],
+    tolerance: f32,
+) -> Option<[usize; 5]> {
+    let mut indices: Vec<usize> = (0..points.len()).collect();
+    let leftmost =
+        (0..points.len()).min_by_key(|&i| NotNaN::new(points[i].x).unwrap())?;
+    indices.swap(0, leftmost);
+
+    let (picked, _) = spread_points(points, &indices, 4, tolerance)?;
+    let mut simplex = [0; 5];
+    simplex.copy_from_slice(&picked);
+    Some(simplex)
+}
+
+// Puts the points of a polygon in order around it, leaving out any which
+// aren't corners. Returns None if the points don't span a plane.
+fn order_polygon(
+    points: &[Vector4<f32>],
+    indices: &[usize],
+    tolerance: f32,
+) -> Option<Vec<usize>> {
+    let origin = points[indices[0]];
+    let (_, basis) = spread_points(points, indices, 2, tolerance)?;
+
+    let mut projected: Vec<(usize, Vector2<f32>)> = indices
+        .iter()
+        .map(|&i| {
+            let v = points[i] - origin;
+            (i, Vector2::new(v.dot(basis[0]), v.dot(basis[1])))
+        })
+        .collect();
+    projected
+        .sort_by(|(_, a), (_, b)| (a.x, a.y).partial_cmp(&(b.x, b.y)).unwrap());
+
+    // Andrew's monotone chain, dropping points which don't make a proper turn.
+    let cross = |o: Vector2<f32>, a: Vector2<f32>, b: Vector2<f32>| {
+        (a.x - o.x) * (b.y - o.y) - (a.y - o.y) * (b.x - o.x)
+    };
+    let mut chain: Vec<(usize, Vector2<f32>)> = Vec::new();
+    for pass in 0..2 {
+        let start = chain.len();
+        for &(i, p) in projected.iter() {
+            while chain.len() >= start + 2 {
+                let (a, b) =
+                    (chain[chain.len() - 2].1, chain[chain.len() - 1].1);
+                if cross(a, b, p) > tolerance * (b - a).magnitude() {
+                    break;
+                }
+                chain.pop();
+            }
+            chain.push((i, p));
+        }
+        // the last point of each half is the first of the other
+        chain.pop();
+        if pass == 0 {
+            projected.reverse();
+        }
+    }
+
+    if chain.len() < 3 {
+        return None;
+    }
+    Some(chain.into_iter().map(|(i, _)| i).collect())
+}
+
+impl Mesh {
+    // Builds the convex hull of a set of points using quickhull. Points in the
+    // interior of the hull, or in the middle of one of its edges, faces or
+    // cells, don't become vertices. Returns None if the points don't span all
+    // four dimensions, or if any of them aren't finite.
+    pub fn convex_hull(points: &[Vector4<f32>]) -> Option<Self> {
+        if points.len() < 5 || !points.iter().all(|p| p.magnitude().is_finite())
+        {
+            return None;
+        }
+
+        let scale = points.iter().map(|p| p.magnitude()).fold(0.0, f32::max);
+        let tolerance = HULL_TOLERANCE * scale.max(1.0);
+
+        let simplex = initial_simplex(points, tolerance)?;
+
+        // Work relative to a point inside the initial simplex, so that the
+        // origin is always strictly inside the hull.
+        let centre = simplex
+            .iter()
+            .fold(Vector4::zero(), |acc: Vector4<f32>, &i| acc + points[i])
+            / 5.0;
+        let shifted: Vec<_> = points.iter().map(|p| p - centre).collect();
+
+        let mut facets: Vec<Facet> = (0..5)
+            .map(|skip| {
+                let mut vertices = [0; 4];
+                let others =
+                    simplex.iter().enumerate().filter(|(k, _)| *k != skip);
+                for (slot, (_, &i)) in vertices.iter_mut().zip(others) {
+                    *slot = i;
+                }
+                Facet::new(&shifted, vertices)
+            })
+            .collect();
+
+        // Points are most likely to be above one of the newest facets, but
+        // they can still be above one of the older ones.
+        let assign = |facets: &mut [Facet], first: usize, point: usize| {
+            let (older, newer) = facets.split_at_mut(first);
+            if let Some(facet) =
+                newer.iter_mut().chain(older.iter_mut()).find(|facet| {
+                    facet.alive && facet.distance(shifted[point]) > tolerance
+                })
+            {
+                facet.outside.push(point);
+            }
+        };
+        for i in 0..points.len() {
+            if !simplex.contains(&i) {
+                assign(&mut facets, 0, i);
+            }
+        }
+
+        while let Some(facet_idx) = facets
+            .iter()
+            .position(|facet| facet.alive && !facet.outside.is_empty())
+        {
+            let facet = &facets[facet_idx];
+            let apex = *facet
+                .outside
+                .iter()
+                .max_by_key(|&&i| {
+                    NotNaN::new(facet.distance(shifted[i])).unwrap()
+                })
+                .unwrap();
+            let p = shifted[apex];
+
+            // Remove every facet the new point can see, keeping track of the
+            // ridges around the edge of the hole this leaves.
+            let mut ridge_counts: BTreeMap<[usize; 3], usize> = BTreeMap::new();
+            let mut orphans = Vec::new();
+            for facet in facets.iter_mut() {
+                if facet.alive && facet.distance(p) > tolerance {
+                    facet.alive = false;
+                    orphans.append(&mut facet.outside);
+                    for ridge in facet.ridges().iter() {
+                        *ridge_counts.entry(*ridge).or_insert(0) += 1;
+                    }
+                }
+            }
+
+            // Then patch the hole up with a cone from the new point.
+            let first_new = facets.len();
+            for (ridge, count) in ridge_counts {
+                if count == 1 {
+                    facets.push(Facet::new(
+                        &shifted,
+                        [ridge[0], ridge[1], ridge[2], apex],
+                    ));
+                }
+            }
+            for point in orphans {
+                if point != apex {
+                    assign(&mut facets, first_new, point);
+                }
+            }
+        }
+
+        let mut hull_points: Vec<usize> = facets
+            .iter()
+            .filter(|facet| facet.alive)
+            .flat_map(|facet| facet.vertices.iter().copied())
+            .collect();
+        hull_points.sort_unstable();
+        hull_points.dedup();
+
+        // Facets which lie in the same hyperplane make up a single cell. Thin
+        // facets can have quite inaccurate normals, so each cell's hyperplane
+        // is fitted again through points spread out across the whole cell.
+        let on_plane = |normal: Vector4<f32>, offset: f32| -> Vec<usize> {
+            hull_points
+                .iter()
+                .copied()
+                .filter(|&i| {
+                    (normal.dot(shifted[i]) - offset).abs() <= tolerance
+                })
+                .collect()
+        };
+        let mut cells: Vec<(Vector4<f32>, f32, Vec<usize>)> = Vec::new();
+        for facet in facets.iter().filter(|facet| facet.alive) {
+            let covered = cells.iter().any(|(_, _, vertices)| {
+                facet
+                    .vertices
+                    .iter()
+                    .all(|i| vertices.binary_search(i).is_ok())
+            });
+            if covered {
+                continue;
+            }
+
+            let coplanar = on_plane(facet.normal, facet.offset);
+            let (normal, offset) =
+                match spread_points(&shifted, &coplanar, 3, tolerance) {
+                    Some((picked, basis)) => {
+                        let mut normal =
+                            triple_cross_product(basis[0], basis[1], basis[2])
+                                .normalize();
+                        if normal.dot(facet.normal) < 0.0 {
+                            normal = -normal;
+                        }
+                        (normal, normal.dot(shifted[picked[0]]))
+                    }
+                    None => (facet.normal, facet.offset),
+                };
+            let mut vertices = on_plane(normal, offset);
+            vertices.extend(&facet.vertices);
+            vertices.sort_unstable();
+            vertices.dedup();
+            cells.push((normal, offset, vertices));
+        }
+
+        // Two cells share a face wherever the vertices they have in common
+        // span a plane.
+        let mut cell_faces = vec![Vec::new(); cells.len()];
+        for i in 0..cells.len() {
+            for j in i + 1..cells.len() {
+                let shared: Vec<usize> = cells[i]
+                    .2
+                    .iter()
+                    .filter(|v| cells[j].2.binary_search(v).is_ok())
+                    .copied()
+                    .collect();
+                if shared.len() < 3 {
+                    continue;
+                }
+                if let Some(face) = order_polygon(&shifted, &shared, tolerance)
+                {
+                    cell_faces[i].push(face.clone());
+                    cell_faces[j].push(face);
+                }
+            }
+        }
+
+        // Only keep the points which ended up as corners of some face.
+        let mut remap = HashMap::new();
+        let mut vertices = Vec::new();
+        for face in cell_faces.iter_mut().flatten() {
+            for v in face.iter_mut() {
+                *v = *remap.entry(*v).or_insert_with(|| {
+                    vertices.push(shifted[*v]);
+                    vertices.len() - 1
+                });
+            }
+        }
+
+        let mut mesh = Mesh::from_cells(vertices, &cell_faces);
+        for v in mesh.vertices.iter_mut() {
+            *v += centre;
+        }
+        mesh.radius = mesh
+            .vertices
+            .iter()
+            .map(|v| v.magnitude())
+            .fold(0.0, f32::max);
+        Some(mesh)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::alg::Bivec4;
+
+    fn counts(mesh: &Mesh) -> [usize; 4] {
+        [
+            mesh.vertices.len(),
+            mesh.edges.len(),
+            mesh.faces.len(),
+            mesh.cells.len(),
+        ]
+    }
+
+    fn contains(mesh: &Mesh, p: Vector4<f32>) -> bool {
+        mesh.cells.iter().all(|cell| {
+            let v0 = mesh.vertices
+                [mesh.edges[mesh.faces[cell.faces[0]].edges[0]].hd_vertex];
+            p.dot(cell.normal) <= v0.dot(cell.normal) + 1e-4
+        })
+    }
+
+    #[test]
+    fn hull_of_regular_polytopes() {
+        for symbol in
+            [[3, 3, 3], [4, 3, 3], [3, 3, 4], [3, 4, 3], [3, 3, 5]].iter()
+        {
+            let polytope = Mesh::from_schlafli_symbol(symbol);
+            let hull = Mesh::convex_hull(&polytope.vertices).unwrap();
+            assert_eq!(counts(&hull), counts(&polytope), "{:?}", symbol);
+            assert!((hull.radius - 1.0).abs() < 1e-4);
+
+            let expected = polytope.mass_properties(1.0).hypervolume;
+            let actual = hull.mass_properties(1.0).hypervolume;
+            assert!((expected - actual).abs() < 1e-3 * expected);
+        }
+    }
+
+    #[test]
+    fn hull_of_truncated_one_twenty_cell() {
+        // Cutting the corners off far enough along each edge turns every
+        // pentagon into a regular decagon. Lots of the facets on the way are
+        // thin slivers, which used to drop some of the corners or split
+        // cells in two, depending on how the points were oriented.
+        let one_twenty_cell = Mesh::from_schlafli_symbol(&[5, 3, 3]);
+        let golden_ratio = (1.0 + 5f32.sqrt()) / 2.0;
+        let t = 1.0 / (2.0 + golden_ratio);
+        let mut points = Vec::new();
+        for edge in one_twenty_cell.edges.iter() {
+            let a = one_twenty_cell.vertices[edge.hd_vertex];
+            let b = one_twenty_cell.vertices[edge.tl_vertex];
+            points.push(a + (b - a) * t);
+            points.push(b + (a - b) * t);
+        }
+
+        for rotation in [
+            Bivec4::new(-0.5, -0.5, -0.5, -0.5, -0.5, -0.5),
+            Bivec4::new(0.4, -0.5, 0.4, -0.5, 0.4, -0.5),
+            Bivec4::new(0.1, -0.5, 0.7, 0.1, -0.5, 0.7),
+            Bivec4::new(0.7, -0.5, 0.1, 0.7, -0.5, 0.1),
+            Bivec4::new(-0.2, -0.5, -0.8, 0.7, 0.4, 0.1),
+            Bivec4::new(-0.8, -0.5, -0.2, 0.1, 0.4, 0.7),
+        ]
+        .iter()
+        {
+            let rotation = rotation.exp();
+            let rotated: Vec<_> = points
+                .iter()
+                .map(|p| Vector4::from(rotation.rotate(&(*p).into())))
+                .collect();
+            let hull = Mesh::convex_hull(&rotated).unwrap();
+            assert_eq!(counts(&hull), [2400, 4800, 3120, 720]);
+        }
+    }
+
+    #[test]
+    fn hull_skips_points_which_arent_corners() {
+        let tesseract = Mesh::from_schlafli_symbol(&[4, 3, 3]);
+        let rotation = Bivec4::new(0.3, -0.2, 0.5, 0.1, 0.7, -0.4).exp();
+        let offset = Vector4::new(5.0, -3.0, 2.0, 1.0);
+
+        // the corners, plus the centres of every edge and cell, plus a few
+        // points inside
+        let mut points = tesseract.vertices.clone();
+        for edge in tesseract.edges.iter() {
+            points.push(
+                (tesseract.vertices[edge.hd_vertex]
+                    + tesseract.vertices[edge.tl_vertex])
+                    / 2.0,
+            );
+        }
+        for cell in tesseract.cells.iter() {
+            points.push(cell.normal * 0.5);
+        }
+        points.push(Vector4::new(0.1, 0.2, -0.1, 0.0));
+        points.push(Vector4::zero());
+        let points: Vec<_> = points
+            .into_iter()
+            .map(|p| Vector4::from(rotation.rotate(&p.into())) + offset)
+            .collect();
+
+        let hull = Mesh::convex_hull(&points).unwrap();
+        assert_eq!(counts(&hull), [16, 32, 24, 8]);
+        for p in points.iter() {
+            assert!(contains(&hull, *p));
+        }
+        // the hull stays where the points are, rather than being centred
+        let props = hull.mass_properties(1.0);
+        assert!((props.centroid - offset).magnitude() < 1e-3);
+        assert!((props.hypervolume - 1.0).abs() < 1e-3);
+    }
+
+    #[test]
+    fn hull_of_random_points() {
+        use rand::{Rng, SeedableRng};
+
+        let mut rng = rand::rngs::StdRng::seed_from_u64(4);
+        let points: Vec<_> = (0..200)
+            .map(|_| {
+                Vector4::new(
+                    rng.gen_range(-0.5, 0.5),
+                    rng.gen_range(-0.5, 0.5),
+                    rng.gen_range(-0.5, 0.5),
+                    rng.gen_range(-0.5, 0.5),
+                )
+            })
+            .collect();
+
+        let hull = Mesh::convex_hull(&points).unwrap();
+        for p in points.iter() {
+            assert!(contains(&hull, *p));
+        }
+        // Euler's formula for 4-polytopes
+        let [v, e, f, c] = counts(&hull);
+        assert_eq!(v + f, e + c);
+        for face in hull.faces.iter() {
+            assert_ne!(face.tl_cell, usize::MAX);
+        }
+    }
+
+    #[test]
+    fn flat_points_have_no_hull() {
+        let points: Vec<_> = (0..20)
+            .map(|i| {
+                Vector4::new(i as f32, (i * i) as f32, (i % 3) as f32, 0.0)
+            })
+            .collect();
+        assert!(Mesh::convex_hull(&points).is_none());
+        assert!(Mesh::convex_hull(&points[..4]).is_none());
+    }
+}
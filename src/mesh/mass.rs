@@ -0,0 +1,239 @@
+use super::{Mesh, TetrahedronMesh};
+use crate::physics::Inertia;
+
+use cgmath::{InnerSpace, Matrix4, SquareMatrix, Vector4, Zero};
+
+#[derive(Debug, Clone, Copy)]
+pub struct MassProperties {
+    pub hypervolume: f32,
+    pub mass: f32,
+    pub centroid: Vector4<f32>,
+    // The second moment of mass about the centroid, i.e. the sum of
+    // m (x - c)(x - c)^T over the polytope.
+    pub second_moment: Matrix4<f32>,
+    // The inertia operator about the centroid.
+    pub inertia: Inertia,
+}
+
+impl Mesh {
+    // Computes the mass properties of the solid polytope with the given
+    // uniform density. This splits the polytope into 4-simplices which all
+    // share an apex at the average of the vertices, so it's only correct for
+    // convex polytopes.
+    pub fn mass_properties(&self, density: f32) -> MassProperties {
+        let apex = self
+            .vertices
+            .iter()
+            .fold(Vector4::zero(), |acc: Vector4<f32>, v| acc + v)
+            / self.vertices.len() as f32;
+
+        // Everything is computed relative to the apex to keep the numbers
+        // small, and accumulated in f64 as the 120-cell and 600-cell are made
+        // of thousands of simplices.
+        let tetrahedra = TetrahedronMesh::from_mesh(self, |_| Zero::zero());
+
+        let mut volume = 0.0f64;
+        let mut first_moment = [0.0f64; 4];
+        let mut second_moment = [[0.0f64; 4]; 4];
+        for tetrahedron in tetrahedra.indices.chunks_exact(4) {
+            let v: Vec<Vector4<f64>> = tetrahedron
+                .iter()
+                .map(|&i| {
+                    (tetrahedra.vertices[i as usize].position - apex).cast()
+                })
+                .map(Option::unwrap)
+                .collect();
+
+            let simplex_volume = Matrix4::from_cols(v[0], v[1], v[2], v[3])
+                .determinant()
+                .abs()
+                / 24.0;
+
+            // The apex is at the origin, so it drops out of all of these:
+            //   centroid = (sum v_k) / (n + 1)
+            //   second moment = V / ((n + 1)(n + 2))
+            //       * (sum v_k v_k^T + (sum v_k)(sum v_k)^T)
+            let sum = v[0] + v[1] + v[2] + v[3];
+            volume += simplex_volume;
+            for i in 0..4 {
+                first_moment[i] += simplex_volume * sum[i] / 5.0;
+                for j in 0..4 {
+                    let products: f64 = v.iter().map(|v| v[i] * v[j]).sum();
+                    second_moment[i][j] +=
+                        simplex_volume / 30.0 * (products + sum[i] * sum[j]);
+                }
+            }
+        }
+
+        let density = density as f64;
+        let mass = density * volume;
+        let mut centroid = Vector4::zero();
+        let mut central_second_moment = Matrix4::zero();
+        if volume > 0.0 {
+            for i in 0..4 {
+                centroid[i] = first_moment[i] / volume;
+            }
+            // parallel axis theorem, to move from the apex to the centroid
+            for i in 0..4 {
+                for j in 0..4 {
+                    central_second_moment[i][j] = (density
+                        * second_moment[i][j]
+                        - mass * centroid[i] * centroid[j])
+                        as f32;
+                }
+            }
+        }
+
+        MassProperties {
+            hypervolume: volume as f32,
+            mass: mass as f32,
+            centroid: apex + centroid.cast().unwrap(),
+            second_moment: central_second_moment,
+            inertia: Inertia::from_second_moment(central_second_moment),
+        }
+    }
+
+    // Moves the polytope so that its centroid is at the origin, and returns
+    // its mass properties from there.
+    pub fn recentre(&mut self, density: f32) -> MassProperties {
+        let mut props = self.mass_properties(density);
+        for v in self.vertices.iter_mut() {
+            *v -= props.centroid;
+        }
+        self.radius = self
+            .vertices
+            .iter()
+            .map(|v| v.magnitude())
+            .fold(0.0, f32::max);
+        props.centroid = Vector4::zero();
+        props
+    }
+}
+
+impl MassProperties {
+    // The inertia about the centroid if the same shape had the given mass
+    // instead, which is zero if it has no hypervolume to spread that over.
+    pub fn inertia_with_mass(&self, mass: f32) -> Inertia {
+        if self.hypervolume <= 0.0 {
+            return Inertia::zero();
+        }
+        Inertia::from_second_moment(
+            self.second_moment * (mass / self.hypervolume),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::alg::Bivec4;
+
+    fn edge_length(mesh: &Mesh) -> f32 {
+        let edge = &mesh.edges[0];
+        (mesh.vertices[edge.hd_vertex] - mesh.vertices[edge.tl_vertex])
+            .magnitude()
+    }
+
+    fn assert_close(a: f32, b: f32) {
+        assert!((a - b).abs() < 1e-3 * b.abs().max(1.0), "{} != {}", a, b);
+    }
+
+    fn assert_isotropic(props: &MassProperties, expected: f32) {
+        for i in 0..4 {
+            for j in 0..4 {
+                let value = props.second_moment[i][j];
+                assert_close(value, if i == j { expected } else { 0.0 });
+            }
+        }
+    }
+
+    #[test]
+    fn regular_polytope_volumes() {
+        let sqrt5 = 5f32.sqrt();
+        let polytopes: [([usize; 3], f32); 6] = [
+            ([3, 3, 3], sqrt5 / 96.0),
+            ([4, 3, 3], 1.0),
+            ([3, 3, 4], 1.0 / 6.0),
+            ([3, 4, 3], 2.0),
+            ([5, 3, 3], 15.0 / 4.0 * (105.0 + 47.0 * sqrt5)),
+            ([3, 3, 5], 25.0 / 4.0 * (2.0 + sqrt5)),
+        ];
+
+        for (symbol, volume_per_edge4) in polytopes.iter() {
+            let mesh = Mesh::from_schlafli_symbol(symbol);
+            let props = mesh.mass_properties(2.0);
+
+            let expected = volume_per_edge4 * edge_length(&mesh).powi(4);
+            assert_close(props.hypervolume, expected);
+            assert_close(props.mass, 2.0 * expected);
+            assert!(props.centroid.magnitude() < 1e-4);
+        }
+    }
+
+    #[test]
+    fn regular_polytope_second_moments() {
+        // Unit density, so these are all per unit of hypervolume. The
+        // tesseract has unit edges, the 16-cell is the unit cross-polytope,
+        // and the 5-cell has unit circumradius so the vertices v_k satisfy
+        // sum v_k v_k^T = 5/4 I.
+        let tesseract = Mesh::from_schlafli_symbol(&[4, 3, 3]);
+        let props = tesseract.mass_properties(1.0);
+        assert_isotropic(&props, props.mass / 12.0);
+
+        let sixteen_cell = Mesh::from_schlafli_symbol(&[3, 3, 4]);
+        let props = sixteen_cell.mass_properties(1.0);
+        assert_isotropic(&props, props.mass / 15.0);
+
+        let five_cell = Mesh::from_schlafli_symbol(&[3, 3, 3]);
+        let props = five_cell.mass_properties(1.0);
+        assert_isotropic(&props, props.mass / 24.0);
+
+        // the rest should at least be isotropic
+        for symbol in [[3, 4, 3], [5, 3, 3], [3, 3, 5]].iter() {
+            let props = Mesh::from_schlafli_symbol(symbol).mass_properties(1.0);
+            let moment = props.second_moment[0][0];
+            assert!(moment > 0.0);
+            assert_isotropic(&props, moment);
+        }
+    }
+
+    #[test]
+    fn translated_mesh() {
+        let offset = Vector4::new(1.0, -2.0, 0.5, 3.0);
+        let mut mesh = Mesh::from_schlafli_symbol(&[4, 3, 3]);
+        for v in mesh.vertices.iter_mut() {
+            *v += offset;
+        }
+
+        let props = mesh.mass_properties(3.0);
+        assert_close(props.mass, 3.0);
+        assert!((props.centroid - offset).magnitude() < 1e-4);
+        assert_isotropic(&props, 3.0 / 12.0);
+
+        let w = Bivec4::new(1.0, 0.0, 0.0, 0.0, 0.0, 0.0);
+        assert_close(props.inertia.apply(&w).xy, 0.5);
+    }
+
+    #[test]
+    fn recentred_mesh() {
+        let offset = Vector4::new(1.0, -2.0, 0.5, 3.0);
+        let mut mesh = Mesh::from_schlafli_symbol(&[3, 3, 3]);
+        for v in mesh.vertices.iter_mut() {
+            *v += offset;
+        }
+
+        let props = mesh.recentre(1.0);
+        assert!(props.centroid.magnitude() < 1e-4);
+        assert!(mesh.mass_properties(1.0).centroid.magnitude() < 1e-4);
+        assert_close(mesh.radius, 1.0);
+        assert_isotropic(&props, props.mass / 24.0);
+
+        // no volume, so no inertia rather than dividing by zero
+        let flat = MassProperties {
+            hypervolume: 0.0,
+            ..props
+        };
+        let w = Bivec4::new(1.0, 0.0, 0.0, 0.0, 0.0, 0.0);
+        assert_eq!(flat.inertia_with_mass(2.0).apply(&w).xy, 0.0);
+    }
+}
@@ -1,14 +1,27 @@
 mod clip;
+mod file;
+mod hull;
+mod mass;
+mod primitives;
+mod prisms;
+mod slice;
 mod tetrahedra;
 mod todd_coxeter;
+mod validate;
 
 use crate::alg::triple_cross_product;
 use crate::util::NotNaN;
-use cgmath::{InnerSpace, Vector4, Zero};
+use cgmath::{InnerSpace, Matrix, Matrix4, SquareMatrix, Vector4, Zero};
 use smallvec::SmallVec;
+use std::collections::HashMap;
+use tetrahedra::get_face_vertex_indices;
 
 pub use clip::*;
+pub use file::*;
+pub use mass::*;
+pub use prisms::*;
 pub use tetrahedra::*;
+pub use validate::*;
 
 #[derive(Debug, Clone)]
 pub struct VertexData {
@@ -49,6 +62,43 @@ fn reflect(v: Vector4<f32>, mirror_normal: Vector4<f32>) -> Vector4<f32> {
     v - 2.0 * mirror_normal.dot(v) * mirror_normal
 }
 
+// The relations between the reflections in the mirrors, which define the
+// symmetry group.
+fn coxeter_relations(symbol: &[usize; 3]) -> Vec<Vec<usize>> {
+    vec![
+        vec![0, 0],
+        vec![1, 1],
+        vec![2, 2],
+        vec![3, 3],
+        [0, 1].repeat(symbol[0]),
+        [1, 2].repeat(symbol[1]),
+        [2, 3].repeat(symbol[2]),
+        [0, 2].repeat(2),
+        [0, 3].repeat(2),
+        [1, 3].repeat(2),
+    ]
+}
+
+// The point which is the same distance from the mirrors of every ringed node,
+// and on the mirrors of every other node. The normals from get_mirror_normals
+// alternate between pointing into and out of the fundamental region, hence the
+// alternating signs.
+fn wythoff_vertex(
+    mirror_normals: &[Vector4<f32>; 4],
+    rings: [bool; 4],
+) -> Vector4<f32> {
+    let mut distances = Vector4::zero();
+    for i in 0..4 {
+        if rings[i] {
+            distances[i] = if i % 2 == 0 { 1.0 } else { -1.0 };
+        }
+    }
+
+    let [n0, n1, n2, n3] = *mirror_normals;
+    let mirrors = Matrix4::from_cols(n0, n1, n2, n3).transpose();
+    (mirrors.invert().unwrap() * distances).normalize()
+}
+
 fn get_mirror_normals(symbol: &[usize; 3]) -> [Vector4<f32>; 4] {
     use std::f32::consts::PI;
 
@@ -81,18 +131,9 @@ impl Mesh {
 
         // setup for todd-coxeter
         let num_gens = 4;
-        let relations: &[&[usize]] = &[
-            &[0, 0],
-            &[1, 1],
-            &[2, 2],
-            &[3, 3],
-            &[0, 1].repeat(symbol[0]),
-            &[1, 2].repeat(symbol[1]),
-            &[2, 3].repeat(symbol[2]),
-            &[0, 2].repeat(2),
-            &[0, 3].repeat(2),
-            &[1, 3].repeat(2),
-        ];
+        let relations = coxeter_relations(symbol);
+        let relations: &[&[usize]] =
+            &relations.iter().map(Vec::as_slice).collect::<Vec<_>>();
 
         let vertex_table =
             todd_coxeter::coset_table(num_gens, relations, &[1, 2, 3]);
@@ -182,10 +223,9 @@ impl Mesh {
             let mut i = 0;
             while i < faces.len() {
                 let f = faces[i];
-                for j in 0..3 {
-                    let new_face = face_table[f][j];
-                    if !faces.contains(&new_face) {
-                        faces.push(new_face);
+                for new_face in face_table[f].iter().take(3) {
+                    if !faces.contains(new_face) {
+                        faces.push(*new_face);
                     }
                 }
                 i += 1;
@@ -208,8 +248,8 @@ impl Mesh {
         let mut faces: Vec<_> = face_tmp
             .into_iter()
             .map(|edges| Face {
-                hd_cell: std::usize::MAX,
-                tl_cell: std::usize::MAX,
+                hd_cell: usize::MAX,
+                tl_cell: usize::MAX,
                 edges,
             })
             .collect();
@@ -217,7 +257,7 @@ impl Mesh {
         // populate cells for each face
         for (i, cell) in cells.iter().enumerate() {
             for j in cell.faces.iter() {
-                if faces[*j].hd_cell == std::usize::MAX {
+                if faces[*j].hd_cell == usize::MAX {
                     faces[*j].hd_cell = i;
                 } else {
                     faces[*j].tl_cell = i;
@@ -225,31 +265,126 @@ impl Mesh {
             }
         }
 
-        // populate cells for each vertex
-        let mut vertex_data = vec![
-            VertexData {
-                cells: SmallVec::new()
-            };
-            vertices.len()
-        ];
-        for (cell_idx, cell) in cells.iter().enumerate() {
-            for face_idx in cell.faces.iter() {
-                for edge_idx in faces[*face_idx].edges.iter() {
-                    let edge = &edges[*edge_idx];
-                    let v0 = &mut vertex_data[edge.hd_vertex];
-                    if !v0.cells.contains(&cell_idx) {
-                        v0.cells.push(cell_idx);
+        let vertex_data =
+            get_vertex_data(vertices.len(), &edges, &faces, &cells);
+
+        Self {
+            radius: 1.0,
+            vertices,
+            vertex_data,
+            edges,
+            faces,
+            cells,
+        }
+    }
+
+    // Builds a uniform polytope using Wythoff's construction, from the
+    // Coxeter-Dynkin diagram with the given Schläfli symbol and ringed nodes.
+    // Ringing only the first node gives the regular polytope, and ringing
+    // other nodes as well truncates, cantellates or runcinates it, e.g.
+    // [true, true, false, false] is the truncated polytope and
+    // [false, true, false, false] is the rectified one. Like the regular
+    // polytopes, the result has unit circumradius.
+    pub fn from_coxeter_diagram(symbol: &[usize; 3], rings: [bool; 4]) -> Self {
+        assert!(
+            rings.iter().any(|&ring| ring),
+            "at least one node must be ringed"
+        );
+        let mirror_normals = get_mirror_normals(symbol);
+        let relations = coxeter_relations(symbol);
+        let relations: Vec<&[usize]> =
+            relations.iter().map(Vec::as_slice).collect();
+
+        // The vertex is left where it is by the mirrors of the unringed nodes,
+        // so each of the other vertices corresponds to a coset of the subgroup
+        // they generate.
+        let unringed: Vec<usize> = (0..4).filter(|&i| !rings[i]).collect();
+        let vertex_table = todd_coxeter::coset_table(4, &relations, &unringed);
+        let vertices = todd_coxeter::table_bfs_fold(
+            &vertex_table,
+            0,
+            wythoff_vertex(&mirror_normals, rings),
+            |v, mirror| reflect(v, mirror_normals[mirror]),
+        );
+
+        Self::convex_hull(&vertices)
+            .expect("uniform polytopes are always four-dimensional")
+    }
+
+    // Builds a convex polytope out of its cells, where each cell is a list of
+    // faces and each face lists its vertices in order around the face. The
+    // polytope must contain the origin, and every face must be shared by
+    // exactly two cells.
+    pub fn from_cells(
+        vertices: Vec<Vector4<f32>>,
+        cell_faces: &[Vec<Vec<usize>>],
+    ) -> Self {
+        let mut edges: Vec<Edge> = Vec::new();
+        let mut edge_map: HashMap<(usize, usize), usize> = HashMap::new();
+        let mut faces: Vec<Face> = Vec::new();
+        let mut face_map: HashMap<Vec<usize>, usize> = HashMap::new();
+        let mut cells = Vec::with_capacity(cell_faces.len());
+
+        for (cell_idx, cell) in cell_faces.iter().enumerate() {
+            let mut cell_face_indices = SmallVec::new();
+
+            for face in cell.iter() {
+                let mut key = face.clone();
+                key.sort_unstable();
+
+                let face_idx = match face_map.get(&key) {
+                    Some(&face_idx) => {
+                        faces[face_idx].tl_cell = cell_idx;
+                        face_idx
                     }
-                    let v1 = &mut vertex_data[edge.tl_vertex];
-                    if !v1.cells.contains(&cell_idx) {
-                        v1.cells.push(cell_idx);
+                    None => {
+                        let face_idx = faces.len();
+                        // going around the face in order means consecutive
+                        // edges are never parallel
+                        let face_edges = (0..face.len())
+                            .map(|i| {
+                                let (a, b) =
+                                    (face[i], face[(i + 1) % face.len()]);
+                                *edge_map
+                                    .entry((a.min(b), a.max(b)))
+                                    .or_insert_with(|| {
+                                        edges.push(Edge {
+                                            hd_vertex: a,
+                                            tl_vertex: b,
+                                            faces: SmallVec::new(),
+                                        });
+                                        edges.len() - 1
+                                    })
+                            })
+                            .collect::<SmallVec<[usize; 8]>>();
+                        for edge_idx in face_edges.iter() {
+                            edges[*edge_idx].faces.push(face_idx);
+                        }
+
+                        faces.push(Face {
+                            hd_cell: cell_idx,
+                            tl_cell: usize::MAX,
+                            edges: face_edges,
+                        });
+                        face_map.insert(key, face_idx);
+                        face_idx
                     }
-                }
+                };
+                cell_face_indices.push(face_idx);
             }
+
+            cells.push(Cell {
+                normal: cell_normal(&vertices, cell),
+                faces: cell_face_indices,
+            });
         }
 
+        let vertex_data =
+            get_vertex_data(vertices.len(), &edges, &faces, &cells);
+        let radius = vertices.iter().map(|v| v.magnitude()).fold(0.0, f32::max);
+
         Self {
-            radius: 1.0,
+            radius,
             vertices,
             vertex_data,
             edges,
@@ -258,6 +393,71 @@ impl Mesh {
         }
     }
 
+    // Like from_cells, but for cells which come from outside the program, so
+    // they're checked before building the mesh and the result is validated.
+    pub fn try_from_cells(
+        vertices: Vec<Vector4<f32>>,
+        cell_faces: &[Vec<Vec<usize>>],
+    ) -> Result<Self, Vec<MeshError>> {
+        let mut errors = Vec::new();
+        for (cell_idx, cell) in cell_faces.iter().enumerate() {
+            for &vertex in cell.iter().flatten() {
+                if vertex >= vertices.len() {
+                    errors.push(MeshError::CellVertexOutOfRange {
+                        cell: cell_idx,
+                        vertex,
+                    });
+                }
+            }
+        }
+        if !errors.is_empty() {
+            return Err(errors);
+        }
+
+        let mesh = Self::from_cells(vertices, cell_faces);
+        mesh.validate()?;
+        Ok(mesh)
+    }
+
+    // The cells of the mesh in the form taken by from_cells.
+    pub fn to_cells(&self) -> Vec<Vec<Vec<usize>>> {
+        self.cells
+            .iter()
+            .map(|cell| {
+                cell.faces
+                    .iter()
+                    .map(|&face_idx| get_face_vertex_indices(self, face_idx))
+                    .collect()
+            })
+            .collect()
+    }
+
+    // Applies a linear transformation to the mesh, e.g. to stretch it along
+    // some axes. The matrix has to be invertible, so that the origin stays
+    // inside the mesh and the topology doesn't change.
+    pub fn transformed(&self, matrix: Matrix4<f32>) -> Self {
+        // Normals are transformed by the inverse transpose, which keeps them
+        // perpendicular to the cells and pointing outwards.
+        let normal_matrix = matrix
+            .invert()
+            .expect("mesh transformations must be invertible")
+            .transpose();
+
+        let mut mesh = self.clone();
+        for v in mesh.vertices.iter_mut() {
+            *v = matrix * *v;
+        }
+        for cell in mesh.cells.iter_mut() {
+            cell.normal = (normal_matrix * cell.normal).normalize();
+        }
+        mesh.radius = mesh
+            .vertices
+            .iter()
+            .map(|v| v.magnitude())
+            .fold(0.0, f32::max);
+        mesh
+    }
+
     pub fn closest_point_to(&self, point: Vector4<f32>) -> Vector4<f32> {
         // first run half-space tests to determine if the point is inside the
         // mesh
@@ -385,7 +585,7 @@ impl Mesh {
         let ab = b - a;
 
         let lambda = (a - point).dot(ab) / ab.magnitude2();
-        let lambda = lambda.min(1.0).max(0.0);
+        let lambda = lambda.clamp(0.0, 1.0);
 
         a + lambda * ab
     }
@@ -410,6 +610,77 @@ impl Mesh {
     }
 }
 
+// Finds the outward normal of a cell given as a list of faces. The origin is
+// assumed to be inside the polytope.
+fn cell_normal(vertices: &[Vector4<f32>], cell: &[Vec<usize>]) -> Vector4<f32> {
+    let mut cell_vertices: Vec<usize> =
+        cell.iter().flatten().copied().collect();
+    cell_vertices.sort_unstable();
+    cell_vertices.dedup();
+
+    // Look for three edge vectors out of a common vertex which aren't
+    // coplanar, which is guaranteed to exist as the cell is 3-dimensional.
+    // an empty cell, which validation will catch
+    let v0 = match cell_vertices.first() {
+        Some(&v0) => vertices[v0],
+        None => return Vector4::zero(),
+    };
+    let mut normal = Vector4::zero();
+    let mut best = 0.0;
+    for (i, &a) in cell_vertices.iter().enumerate().skip(1) {
+        for (j, &b) in cell_vertices.iter().enumerate().skip(i + 1) {
+            for &c in cell_vertices.iter().skip(j + 1) {
+                let n = triple_cross_product(
+                    vertices[a] - v0,
+                    vertices[b] - v0,
+                    vertices[c] - v0,
+                );
+                if n.magnitude2() > best {
+                    best = n.magnitude2();
+                    normal = n;
+                }
+            }
+        }
+    }
+
+    let normal = normal.normalize();
+    if normal.dot(v0) < 0.0 {
+        -normal
+    } else {
+        normal
+    }
+}
+
+fn get_vertex_data(
+    vertex_count: usize,
+    edges: &[Edge],
+    faces: &[Face],
+    cells: &[Cell],
+) -> Vec<VertexData> {
+    let mut vertex_data = vec![
+        VertexData {
+            cells: SmallVec::new()
+        };
+        vertex_count
+    ];
+    for (cell_idx, cell) in cells.iter().enumerate() {
+        for face_idx in cell.faces.iter() {
+            for edge_idx in faces[*face_idx].edges.iter() {
+                let edge = &edges[*edge_idx];
+                let v0 = &mut vertex_data[edge.hd_vertex];
+                if !v0.cells.contains(&cell_idx) {
+                    v0.cells.push(cell_idx);
+                }
+                let v1 = &mut vertex_data[edge.tl_vertex];
+                if !v1.cells.contains(&cell_idx) {
+                    v1.cells.push(cell_idx);
+                }
+            }
+        }
+    }
+    vertex_data
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
@@ -423,4 +694,93 @@ mod test {
         Mesh::from_schlafli_symbol(&[3, 3, 4]);
         Mesh::from_schlafli_symbol(&[3, 3, 5]);
     }
+
+    fn counts(mesh: &Mesh) -> [usize; 4] {
+        [
+            mesh.vertices.len(),
+            mesh.edges.len(),
+            mesh.faces.len(),
+            mesh.cells.len(),
+        ]
+    }
+
+    #[test]
+    fn transformed() {
+        // a 4x1x1x0.2 domino
+        let tesseract = Mesh::from_schlafli_symbol(&[4, 3, 3]);
+        let domino = tesseract.transformed(Matrix4::from_diagonal(
+            Vector4::new(4.0, 1.0, 1.0, 0.2),
+        ));
+        assert_eq!(counts(&domino), counts(&tesseract));
+        assert!((domino.radius - 0.5 * 18.04f32.sqrt()).abs() < 1e-5);
+        let props = domino.mass_properties(1.0);
+        assert!((props.hypervolume - 0.8).abs() < 1e-5);
+        for cell in domino.cells.iter() {
+            let v0 = domino.cell_representative_vertex(cell);
+            let expected = if cell.normal.x.abs() > 0.5 {
+                2.0
+            } else if cell.normal.w.abs() > 0.5 {
+                0.1
+            } else {
+                0.5
+            };
+            assert!((v0.dot(cell.normal) - expected).abs() < 1e-5);
+        }
+
+        // after a shear, the normals still point away from the rest of the
+        // mesh
+        let mut shear = Matrix4::identity();
+        shear.y.x = 0.7;
+        shear.w.z = -1.3;
+        let cell_24 = Mesh::from_schlafli_symbol(&[3, 4, 3]);
+        let sheared = cell_24.transformed(shear);
+        for cell in sheared.cells.iter() {
+            let offset =
+                sheared.cell_representative_vertex(cell).dot(cell.normal);
+            assert!(offset > 0.0);
+            for v in sheared.vertices.iter() {
+                assert!(v.dot(cell.normal) <= offset + 1e-5);
+            }
+        }
+        let volume = cell_24.mass_properties(1.0).hypervolume;
+        assert!(
+            (sheared.mass_properties(1.0).hypervolume - volume).abs() < 1e-4
+        );
+    }
+
+    #[test]
+    fn coxeter_diagrams() {
+        let t = true;
+        let f = false;
+        let polytopes = [
+            // the regular ones come out the same as before
+            ([4, 3, 3], [t, f, f, f], [16, 32, 24, 8]),
+            ([3, 3, 4], [t, f, f, f], [8, 24, 32, 16]),
+            ([3, 3, 3], [f, t, f, f], [10, 30, 30, 10]),
+            ([3, 3, 3], [t, f, t, f], [30, 90, 80, 20]),
+            ([3, 3, 3], [t, f, f, t], [20, 60, 70, 30]),
+            ([4, 3, 3], [f, t, f, f], [32, 96, 88, 24]),
+            ([4, 3, 3], [t, t, f, f], [64, 128, 88, 24]),
+            ([4, 3, 3], [t, t, t, t], [384, 768, 464, 80]),
+            ([3, 4, 3], [f, t, f, f], [96, 288, 240, 48]),
+            ([5, 3, 3], [t, t, f, f], [2400, 4800, 3120, 720]),
+        ];
+
+        for (symbol, rings, expected) in polytopes.iter() {
+            let mesh = Mesh::from_coxeter_diagram(symbol, *rings);
+            assert_eq!(counts(&mesh), *expected, "{:?} {:?}", symbol, rings);
+
+            // uniform polytopes have every vertex on the unit hypersphere and
+            // all of their edges the same length
+            assert!((mesh.radius - 1.0).abs() < 1e-4);
+            let length = |edge: &Edge| {
+                (mesh.vertices[edge.hd_vertex] - mesh.vertices[edge.tl_vertex])
+                    .magnitude()
+            };
+            let first = length(&mesh.edges[0]);
+            for edge in mesh.edges.iter() {
+                assert!((length(edge) - first).abs() < 1e-4);
+            }
+        }
+    }
 }
@@ -0,0 +1,291 @@
+// Polytopes for the primitive colliders. The round ones are only
+// approximations, which are good enough for rendering.
+
+use super::Mesh;
+use cgmath::{Vector2, Vector3, Vector4, Zero};
+use std::f32::consts::PI;
+
+// How many sides to give circles.
+const CIRCLE_SEGMENTS: usize = 16;
+// How many bands of latitude to give spheres.
+const SPHERE_RINGS: usize = 8;
+// How many layers to build each hemispherical end of a capsule out of.
+const CAP_LAYERS: usize = 4;
+
+pub(super) fn polygon(radius: f32, sides: usize) -> Vec<Vector2<f32>> {
+    (0..sides)
+        .map(|i| {
+            let angle = 2.0 * PI * i as f32 / sides as f32;
+            Vector2::new(angle.cos(), angle.sin()) * radius
+        })
+        .collect()
+}
+
+fn rectangle(half_width: f32, half_height: f32) -> Vec<Vector2<f32>> {
+    vec![
+        Vector2::new(-half_width, -half_height),
+        Vector2::new(half_width, -half_height),
+        Vector2::new(half_width, half_height),
+        Vector2::new(-half_width, half_height),
+    ]
+}
+
+// The product of polygon p, lying in the plane of p_axes, and polygon q, lying
+// in the plane of q_axes.
+pub(super) fn duoprism(
+    p: &[Vector2<f32>],
+    p_axes: (usize, usize),
+    q: &[Vector2<f32>],
+    q_axes: (usize, usize),
+) -> Mesh {
+    let mut vertices = Vec::with_capacity(p.len() * q.len());
+    for a in p.iter() {
+        for b in q.iter() {
+            let mut v = Vector4::zero();
+            v[p_axes.0] = a.x;
+            v[p_axes.1] = a.y;
+            v[q_axes.0] = b.x;
+            v[q_axes.1] = b.y;
+            vertices.push(v);
+        }
+    }
+    let idx = |i: usize, j: usize| (i % p.len()) * q.len() + j % q.len();
+
+    let mut cells = Vec::with_capacity(p.len() + q.len());
+    // Each edge of p times q gives a prism over q...
+    for i in 0..p.len() {
+        let mut faces: Vec<Vec<usize>> = (0..q.len())
+            .map(|j| {
+                vec![idx(i, j), idx(i + 1, j), idx(i + 1, j + 1), idx(i, j + 1)]
+            })
+            .collect();
+        faces.push((0..q.len()).map(|j| idx(i, j)).collect());
+        faces.push((0..q.len()).map(|j| idx(i + 1, j)).collect());
+        cells.push(faces);
+    }
+    // ...and vice versa.
+    for j in 0..q.len() {
+        let mut faces: Vec<Vec<usize>> = (0..p.len())
+            .map(|i| {
+                vec![idx(i, j), idx(i + 1, j), idx(i + 1, j + 1), idx(i, j + 1)]
+            })
+            .collect();
+        faces.push((0..p.len()).map(|i| idx(i, j)).collect());
+        faces.push((0..p.len()).map(|i| idx(i, j + 1)).collect());
+        cells.push(faces);
+    }
+
+    Mesh::from_cells(vertices, &cells)
+}
+
+struct Polyhedron {
+    vertices: Vec<Vector3<f32>>,
+    faces: Vec<Vec<usize>>,
+}
+
+// A unit sphere made of bands of latitude and longitude. All the quads between
+// them are planar, so this is a proper convex polyhedron.
+fn uv_sphere(segments: usize, rings: usize) -> Polyhedron {
+    let mut vertices = vec![Vector3::unit_z(), -Vector3::unit_z()];
+    for ring in 1..rings {
+        let theta = PI * ring as f32 / rings as f32;
+        for segment in 0..segments {
+            let phi = 2.0 * PI * segment as f32 / segments as f32;
+            vertices.push(Vector3::new(
+                theta.sin() * phi.cos(),
+                theta.sin() * phi.sin(),
+                theta.cos(),
+            ));
+        }
+    }
+    let idx = |ring: usize, segment: usize| {
+        2 + (ring - 1) * segments + segment % segments
+    };
+
+    let mut faces = Vec::new();
+    for segment in 0..segments {
+        faces.push(vec![0, idx(1, segment), idx(1, segment + 1)]);
+        faces.push(vec![
+            1,
+            idx(rings - 1, segment + 1),
+            idx(rings - 1, segment),
+        ]);
+        for ring in 1..rings - 1 {
+            faces.push(vec![
+                idx(ring, segment),
+                idx(ring + 1, segment),
+                idx(ring + 1, segment + 1),
+                idx(ring, segment + 1),
+            ]);
+        }
+    }
+
+    Polyhedron { vertices, faces }
+}
+
+// Stacks copies of a polyhedron lying in the xzw hyperplane along the y axis.
+// Each layer is a (height, scale) pair. Consecutive layers are joined by
+// frustums over each face of the polyhedron, and the ends are capped off with
+// the polyhedron itself.
+fn stack(polyhedron: &Polyhedron, layers: &[(f32, f32)]) -> Mesh {
+    let count = polyhedron.vertices.len();
+    let vertices = layers
+        .iter()
+        .flat_map(|&(y, scale)| {
+            polyhedron.vertices.iter().map(move |v| {
+                Vector4::new(v.x * scale, y, v.y * scale, v.z * scale)
+            })
+        })
+        .collect();
+    let at_layer = |face: &[usize], layer: usize| -> Vec<usize> {
+        face.iter().map(|v| layer * count + v).collect()
+    };
+
+    let mut cells = Vec::new();
+    cells.push(
+        polyhedron
+            .faces
+            .iter()
+            .map(|face| at_layer(face, 0))
+            .collect(),
+    );
+    for layer in 0..layers.len() - 1 {
+        for face in polyhedron.faces.iter() {
+            let mut faces =
+                vec![at_layer(face, layer), at_layer(face, layer + 1)];
+            for i in 0..face.len() {
+                let (a, b) = (face[i], face[(i + 1) % face.len()]);
+                faces.push(vec![
+                    layer * count + a,
+                    layer * count + b,
+                    (layer + 1) * count + b,
+                    (layer + 1) * count + a,
+                ]);
+            }
+            cells.push(faces);
+        }
+    }
+    cells.push(
+        polyhedron
+            .faces
+            .iter()
+            .map(|face| at_layer(face, layers.len() - 1))
+            .collect(),
+    );
+
+    Mesh::from_cells(vertices, &cells)
+}
+
+impl Mesh {
+    pub fn hyperbox(half_extents: Vector4<f32>) -> Self {
+        duoprism(
+            &rectangle(half_extents.x, half_extents.y),
+            (0, 1),
+            &rectangle(half_extents.z, half_extents.w),
+            (2, 3),
+        )
+    }
+
+    // A ball in the xzw hyperplane, extruded along the y axis.
+    pub fn spherinder(radius: f32, half_height: f32) -> Self {
+        stack(
+            &uv_sphere(CIRCLE_SEGMENTS, SPHERE_RINGS),
+            &[(-half_height, radius), (half_height, radius)],
+        )
+    }
+
+    // A disk in the xz plane, extruded along both the y and w axes.
+    pub fn cubinder(radius: f32, half_height: f32) -> Self {
+        duoprism(
+            &polygon(radius, CIRCLE_SEGMENTS),
+            (0, 2),
+            &rectangle(half_height, half_height),
+            (1, 3),
+        )
+    }
+
+    // Every point within the radius of the segment along the y axis between
+    // -half_height and half_height.
+    pub fn capsule(radius: f32, half_height: f32) -> Self {
+        let cap: Vec<(f32, f32)> = (0..CAP_LAYERS)
+            .map(|i| {
+                let angle = 0.5 * PI * i as f32 / CAP_LAYERS as f32;
+                (radius * angle.sin(), radius * angle.cos())
+            })
+            .collect();
+
+        let mut layers: Vec<(f32, f32)> = cap
+            .iter()
+            .rev()
+            .map(|&(height, scale)| (-half_height - height, scale))
+            .collect();
+        for &(height, scale) in cap.iter() {
+            let layer = (half_height + height, scale);
+            // with no height the two middle layers would coincide
+            if layers.last() != Some(&layer) {
+                layers.push(layer);
+            }
+        }
+
+        stack(&uv_sphere(CIRCLE_SEGMENTS, SPHERE_RINGS), &layers)
+    }
+
+    // The product of a disk in the xy plane and a disk in the zw plane.
+    pub fn duocylinder(radius_xy: f32, radius_zw: f32) -> Self {
+        duoprism(
+            &polygon(radius_xy, CIRCLE_SEGMENTS),
+            (0, 1),
+            &polygon(radius_zw, CIRCLE_SEGMENTS),
+            (2, 3),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cgmath::InnerSpace;
+
+    #[test]
+    fn hyperbox_volume() {
+        let mesh = Mesh::hyperbox(Vector4::new(0.5, 1.0, 1.5, 2.0));
+        assert_eq!(mesh.vertices.len(), 16);
+        assert_eq!(mesh.cells.len(), 8);
+        let volume = mesh.mass_properties(1.0).hypervolume;
+        assert!((volume - 24.0).abs() < 1e-3, "volume = {}", volume);
+    }
+
+    #[test]
+    fn round_shapes_are_well_formed() {
+        let meshes = [
+            Mesh::spherinder(1.0, 0.5),
+            Mesh::cubinder(1.0, 0.5),
+            Mesh::capsule(1.0, 0.5),
+            Mesh::capsule(1.0, 0.0),
+            Mesh::duocylinder(1.0, 0.5),
+        ];
+        for mesh in meshes.iter() {
+            for face in mesh.faces.iter() {
+                assert!(face.tl_cell != usize::MAX);
+            }
+            // every vertex is on the boundary of the polytope
+            for v in mesh.vertices.iter() {
+                let on_boundary = mesh.cells.iter().any(|cell| {
+                    let v0 = mesh.vertices[mesh.edges
+                        [mesh.faces[cell.faces[0]].edges[0]]
+                        .hd_vertex];
+                    (v - v0).dot(cell.normal).abs() < 1e-4
+                });
+                assert!(on_boundary);
+            }
+            // and no vertex is outside any cell
+            for cell in mesh.cells.iter() {
+                let v0 = mesh.vertices
+                    [mesh.edges[mesh.faces[cell.faces[0]].edges[0]].hd_vertex];
+                for v in mesh.vertices.iter() {
+                    assert!((v - v0).dot(cell.normal) < 1e-4);
+                }
+            }
+        }
+    }
+}
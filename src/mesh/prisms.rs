@@ -0,0 +1,222 @@
+// Prisms over polyhedra and polygons, for building things like ramps, pillars
+// and walls which are only a little thick in w.
+
+use super::primitives::{duoprism, polygon};
+use super::Mesh;
+use cgmath::{InnerSpace, Vector3, Vector4};
+use serde::{Deserialize, Serialize};
+use std::f32::consts::PI;
+
+// Polyhedra to make prisms out of. The regular ones have unit circumradius,
+// and the prisms and antiprisms also have all their edges the same length.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub enum PrismBase {
+    Tetrahedron,
+    Cube,
+    Octahedron,
+    Dodecahedron,
+    Icosahedron,
+    Prism { sides: usize },
+    Antiprism { sides: usize },
+}
+
+fn normalized(vertices: Vec<Vector3<f32>>) -> Vec<Vector3<f32>> {
+    vertices.into_iter().map(|v| v.normalize()).collect()
+}
+
+// All the sign combinations of the non-zero components of v.
+fn signs(v: Vector3<f32>) -> Vec<Vector3<f32>> {
+    let mut result = vec![v];
+    for axis in 0..3 {
+        if v[axis] != 0.0 {
+            result = result
+                .into_iter()
+                .flat_map(|v| {
+                    let mut flipped = v;
+                    flipped[axis] = -flipped[axis];
+                    vec![v, flipped]
+                })
+                .collect();
+        }
+    }
+    result
+}
+
+// The cyclic permutations of the components of v.
+fn cycles(v: Vector3<f32>) -> Vec<Vector3<f32>> {
+    vec![v, Vector3::new(v.y, v.z, v.x), Vector3::new(v.z, v.x, v.y)]
+}
+
+impl PrismBase {
+    pub fn vertices(self) -> Vec<Vector3<f32>> {
+        // the golden ratio
+        let phi = (1.0 + 5f32.sqrt()) / 2.0;
+
+        match self {
+            PrismBase::Tetrahedron => normalized(vec![
+                Vector3::new(1.0, 1.0, 1.0),
+                Vector3::new(1.0, -1.0, -1.0),
+                Vector3::new(-1.0, 1.0, -1.0),
+                Vector3::new(-1.0, -1.0, 1.0),
+            ]),
+            PrismBase::Cube => normalized(signs(Vector3::new(1.0, 1.0, 1.0))),
+            PrismBase::Octahedron => normalized(
+                cycles(Vector3::unit_x())
+                    .into_iter()
+                    .flat_map(signs)
+                    .collect(),
+            ),
+            PrismBase::Dodecahedron => {
+                let mut vertices = signs(Vector3::new(1.0, 1.0, 1.0));
+                for v in cycles(Vector3::new(0.0, 1.0 / phi, phi)) {
+                    vertices.extend(signs(v));
+                }
+                normalized(vertices)
+            }
+            PrismBase::Icosahedron => normalized(
+                cycles(Vector3::new(0.0, 1.0, phi))
+                    .into_iter()
+                    .flat_map(signs)
+                    .collect(),
+            ),
+            PrismBase::Prism { sides } => {
+                // square sides, so the height is the same as the edge length
+                let half_height = (PI / sides as f32).sin();
+                let mut vertices = Vec::with_capacity(2 * sides);
+                for &z in [-half_height, half_height].iter() {
+                    vertices.extend(
+                        polygon(1.0, sides)
+                            .into_iter()
+                            .map(|v| Vector3::new(v.x, v.y, z)),
+                    );
+                }
+                normalized(vertices)
+            }
+            PrismBase::Antiprism { sides } => {
+                // The top is rotated by half a side, and the height is chosen
+                // so that the triangles around the sides are equilateral.
+                let half_angle = PI / sides as f32;
+                let edge2 = (2.0 * half_angle.sin()).powi(2);
+                let half_height =
+                    0.5 * (edge2 - 2.0 * (1.0 - half_angle.cos())).sqrt();
+                let mut vertices = Vec::with_capacity(2 * sides);
+                for i in 0..2 * sides {
+                    let angle = half_angle * i as f32;
+                    let z = if i % 2 == 0 {
+                        -half_height
+                    } else {
+                        half_height
+                    };
+                    vertices.push(Vector3::new(angle.cos(), angle.sin(), z));
+                }
+                normalized(vertices)
+            }
+        }
+    }
+}
+
+impl Mesh {
+    // A convex polyhedron in the xyz hyperplane, given by its vertices,
+    // extruded along the w axis from -half_height to half_height.
+    pub fn polyhedral_prism(
+        vertices: &[Vector3<f32>],
+        half_height: f32,
+    ) -> Option<Self> {
+        let mut points = Vec::with_capacity(2 * vertices.len());
+        for &w in [-half_height, half_height].iter() {
+            points.extend(vertices.iter().map(|v| v.extend(w)));
+        }
+        Self::convex_hull(&points)
+    }
+
+    // The product of a regular p-gon in the xy plane and a regular q-gon in
+    // the zw plane, with all edges the same length and unit circumradius.
+    pub fn duoprism(p: usize, q: usize) -> Self {
+        // equal edges means r_p sin(pi / p) = r_q sin(pi / q)
+        let (sin_p, sin_q) = ((PI / p as f32).sin(), (PI / q as f32).sin());
+        let radii = Vector4::new(sin_q, sin_p, 0.0, 0.0).normalize();
+        duoprism(&polygon(radii.x, p), (0, 1), &polygon(radii.y, q), (2, 3))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn counts(mesh: &Mesh) -> [usize; 4] {
+        [
+            mesh.vertices.len(),
+            mesh.edges.len(),
+            mesh.faces.len(),
+            mesh.cells.len(),
+        ]
+    }
+
+    fn edge_lengths(mesh: &Mesh) -> (f32, f32) {
+        mesh.edges
+            .iter()
+            .map(|edge| {
+                (mesh.vertices[edge.hd_vertex] - mesh.vertices[edge.tl_vertex])
+                    .magnitude()
+            })
+            .fold((f32::INFINITY, 0.0), |(min, max), length| {
+                (min.min(length), max.max(length))
+            })
+    }
+
+    #[test]
+    fn prisms() {
+        let bases = [
+            (PrismBase::Tetrahedron, [8, 16, 14, 6]),
+            (PrismBase::Cube, [16, 32, 24, 8]),
+            (PrismBase::Octahedron, [12, 30, 28, 10]),
+            (PrismBase::Dodecahedron, [40, 80, 54, 14]),
+            (PrismBase::Icosahedron, [24, 72, 70, 22]),
+            (PrismBase::Prism { sides: 5 }, [20, 40, 29, 9]),
+            (PrismBase::Antiprism { sides: 3 }, [12, 30, 28, 10]),
+            (PrismBase::Antiprism { sides: 5 }, [20, 50, 44, 14]),
+        ];
+        for (base, expected) in bases.iter() {
+            let vertices = base.vertices();
+            assert!(vertices
+                .iter()
+                .all(|v| (v.magnitude() - 1.0).abs() < 1e-5));
+
+            // with the right height these are all uniform
+            let edge = vertices
+                .iter()
+                .skip(1)
+                .map(|v| (v - vertices[0]).magnitude())
+                .fold(f32::INFINITY, f32::min);
+            let mesh = Mesh::polyhedral_prism(&vertices, edge / 2.0).unwrap();
+            assert_eq!(counts(&mesh), *expected, "{:?}", base);
+            let (min, max) = edge_lengths(&mesh);
+            assert!(max - min < 1e-4, "{:?}: {} {}", base, min, max);
+        }
+
+        // a wedge for a ramp
+        let wedge = [
+            Vector3::new(-1.0, 0.0, -1.0),
+            Vector3::new(1.0, 0.0, -1.0),
+            Vector3::new(1.0, 1.0, -1.0),
+            Vector3::new(-1.0, 0.0, 1.0),
+            Vector3::new(1.0, 0.0, 1.0),
+            Vector3::new(1.0, 1.0, 1.0),
+        ];
+        let mesh = Mesh::polyhedral_prism(&wedge, 0.25).unwrap();
+        assert_eq!(counts(&mesh), [12, 24, 19, 7]);
+        let volume = mesh.mass_properties(1.0).hypervolume;
+        assert!((volume - 1.0).abs() < 1e-4, "volume = {}", volume);
+    }
+
+    #[test]
+    fn duoprisms() {
+        for &(p, q) in [(3, 3), (3, 4), (5, 8), (4, 4)].iter() {
+            let mesh = Mesh::duoprism(p, q);
+            assert_eq!(counts(&mesh), [p * q, 2 * p * q, p * q + p + q, p + q]);
+            let (min, max) = edge_lengths(&mesh);
+            assert!(max - min < 1e-5);
+            assert!((mesh.radius - 1.0).abs() < 1e-5);
+        }
+    }
+}
@@ -0,0 +1,349 @@
+// CPU implementation of the slicing compute shader in the testapp
+// (shaders/slice.comp). This should produce exactly the same triangle list as
+// the shader does, so if you change one remember to change the other.
+
+use super::TetrahedronMesh;
+use crate::graphics::{SlicePlane, Transform4, Vertex3, Vertex4};
+use crate::util::EPSILON;
+
+use cgmath::{InnerSpace, Vector3, Vector4};
+
+const EDGES: [(usize, usize); 6] =
+    [(0, 1), (0, 2), (0, 3), (1, 2), (1, 3), (2, 3)];
+
+const TETRAHEDRON_INDICES: [usize; 12] = [
+    0, 1, 2, //
+    0, 1, 3, //
+    0, 2, 3, //
+    1, 2, 3, //
+];
+
+fn approx_zero(x: f32) -> bool {
+    x.abs() < EPSILON
+}
+
+fn approx_eq(a: f32, b: f32) -> bool {
+    (a - b).abs() < EPSILON
+}
+
+fn approx_vec_eq(a: Vector4<f32>, b: Vector4<f32>) -> bool {
+    (a - b).magnitude2() < EPSILON
+}
+
+fn project(slice_plane: &SlicePlane, x: Vector4<f32>) -> Vector4<f32> {
+    let projected = slice_plane.proj_matrix * (x - slice_plane.base_point);
+    Vector4::new(projected.x, projected.y, projected.z, 1.0)
+}
+
+fn emit_triangle(dst: &mut Vec<Vertex3>, a: Vertex4, b: Vertex4, c: Vertex4) {
+    let a3 = a.position.truncate() / a.position.w;
+    let b3 = b.position.truncate() / b.position.w;
+    let c3 = c.position.truncate() / c.position.w;
+    let normal = (b3 - a3).cross(c3 - a3).normalize().extend(1.0);
+
+    for v in [a, b, c].iter() {
+        dst.push(Vertex3 {
+            position: v.position,
+            color: v.color,
+            normal,
+        });
+    }
+}
+
+fn slice_tetrahedron(
+    dst: &mut Vec<Vertex3>,
+    slice_plane: &SlicePlane,
+    vertices: [Vertex4; 4],
+) {
+    let normal = slice_plane.normal;
+    let base_point = slice_plane.base_point;
+
+    // check to see if the tetrahedron is exactly in the cut plane
+    let cut_plane_offset = base_point.dot(normal);
+    if vertices
+        .iter()
+        .all(|v| approx_eq(v.position.dot(normal), cut_plane_offset))
+    {
+        // emit a tetrahedron
+        for triangle in TETRAHEDRON_INDICES.chunks_exact(3) {
+            let mut corners = [
+                vertices[triangle[0]],
+                vertices[triangle[1]],
+                vertices[triangle[2]],
+            ];
+            for corner in corners.iter_mut() {
+                corner.position = project(slice_plane, corner.position);
+            }
+
+            emit_triangle(dst, corners[0], corners[1], corners[2]);
+        }
+        return;
+    }
+
+    let mut intersections = [Vertex4 {
+        position: Vector4::new(0.0, 0.0, 0.0, 0.0),
+        color: Vector4::new(0.0, 0.0, 0.0, 0.0),
+    }; 4];
+    let mut count = 0;
+
+    for v in vertices.iter() {
+        if approx_zero(normal.dot(base_point - v.position)) {
+            intersections[count] = *v;
+            count += 1;
+        }
+    }
+
+    for &(start_idx, end_idx) in EDGES.iter() {
+        let start = vertices[start_idx];
+        let end = vertices[end_idx];
+        let a = start.position;
+        let b = end.position;
+
+        let denom = normal.dot(b - a);
+        if approx_zero(denom) {
+            continue;
+        }
+
+        let t = normal.dot(base_point - a) / denom;
+        if !(0.0..=1.0).contains(&t) {
+            continue;
+        }
+
+        let intersection = a + t * (b - a);
+        for j in 0..4 {
+            if j == count {
+                intersections[count] = Vertex4 {
+                    position: intersection,
+                    color: start.color + t * (end.color - start.color),
+                };
+                count += 1;
+                break;
+            }
+            if approx_vec_eq(intersections[j].position, intersection) {
+                break;
+            }
+        }
+    }
+
+    for intersection in intersections[..count].iter_mut() {
+        intersection.position = project(slice_plane, intersection.position);
+    }
+
+    match count {
+        3 => {
+            // emit a triangle
+            emit_triangle(
+                dst,
+                intersections[0],
+                intersections[1],
+                intersections[2],
+            );
+        }
+        4 => {
+            // emit a quadrilateral. Sort the corners by their angle around the
+            // centroid so that we get two non-overlapping triangles
+            let points: Vec<Vector3<f32>> = intersections
+                .iter()
+                .map(|v| v.position.truncate())
+                .collect();
+            let (a, b, c) = (points[0], points[1], points[2]);
+            let n = (b - a).cross(c - a).normalize();
+
+            let quad_centroid = points
+                .iter()
+                .fold(Vector3::new(0.0, 0.0, 0.0), |acc, p| acc + p)
+                / 4.0;
+
+            let mut angles = [(0, 0.0), (1, 0.0), (2, 0.0), (3, 0.0)];
+            let first = (a - quad_centroid).normalize();
+            for i in 1..4 {
+                let edge = (points[i] - quad_centroid).normalize();
+                let mut angle = first.dot(edge).clamp(-1.0, 1.0).acos();
+                if n.dot(first.cross(edge)) < 0.0 {
+                    angle *= -1.0;
+                }
+                angles[i].1 = angle;
+            }
+
+            // insertion sort, the same as the shader does, so that ties are
+            // broken in the same way
+            for i in 1..4 {
+                let mut j = i;
+                while j > 0 && angles[j - 1].1 > angles[j].1 {
+                    angles.swap(j - 1, j);
+                    j -= 1;
+                }
+            }
+
+            emit_triangle(
+                dst,
+                intersections[angles[0].0],
+                intersections[angles[1].0],
+                intersections[angles[2].0],
+            );
+            emit_triangle(
+                dst,
+                intersections[angles[0].0],
+                intersections[angles[2].0],
+                intersections[angles[3].0],
+            );
+        }
+        _ => (),
+    }
+}
+
+impl TetrahedronMesh {
+    // Intersects the mesh, transformed by `transform`, with the slice plane and
+    // returns the resulting triangle list, projected into the 3D space of the
+    // slice plane.
+    pub fn slice(
+        &self,
+        transform: &Transform4,
+        slice_plane: &SlicePlane,
+    ) -> Vec<Vertex3> {
+        let mut dst = Vec::new();
+
+        for tetrahedron in self.indices.chunks_exact(4) {
+            let mut vertices = [self.vertices[0]; 4];
+            for (i, index) in tetrahedron.iter().enumerate() {
+                let source = self.vertices[*index as usize];
+                vertices[i] = Vertex4 {
+                    position: transform.transform * source.position
+                        + transform.displacement,
+                    color: source.color,
+                };
+            }
+
+            slice_tetrahedron(&mut dst, slice_plane, vertices);
+        }
+
+        dst
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mesh::Mesh;
+    use cgmath::{Matrix4, One, SquareMatrix};
+
+    fn tesseract() -> TetrahedronMesh {
+        TetrahedronMesh::from_mesh(
+            &Mesh::from_schlafli_symbol(&[4, 3, 3]),
+            |_| Vector4::new(1.0, 0.0, 0.0, 1.0),
+        )
+    }
+
+    fn surface_area(triangles: &[Vertex3]) -> f32 {
+        triangles
+            .chunks_exact(3)
+            .map(|t| {
+                let a = t[0].position.truncate();
+                let b = t[1].position.truncate();
+                let c = t[2].position.truncate();
+                0.5 * (b - a).cross(c - a).magnitude()
+            })
+            .sum()
+    }
+
+    #[test]
+    fn tesseract_cross_section_is_a_cube() {
+        let triangles =
+            tesseract().slice(&Transform4::default(), &SlicePlane::default());
+
+        assert!(!triangles.is_empty());
+        assert_eq!(triangles.len() % 3, 0);
+        for v in triangles.iter() {
+            assert_eq!(v.position.w, 1.0);
+            assert_eq!(v.color, Vector4::new(1.0, 0.0, 0.0, 1.0));
+            for i in 0..3 {
+                assert!(v.position[i].abs() <= 0.5 + EPSILON);
+            }
+        }
+
+        // the tesseract has vertices at (+-0.5, +-0.5, +-0.5, +-0.5), so the
+        // slice through w = 0 is a unit cube
+        assert!((surface_area(&triangles) - 6.0).abs() < 1e-4);
+    }
+
+    #[test]
+    fn transform_is_applied() {
+        let transform = Transform4 {
+            displacement: Vector4::new(1.0, 2.0, 3.0, 0.25),
+            transform: Matrix4::one(),
+        };
+        let triangles = tesseract().slice(&transform, &SlicePlane::default());
+
+        for v in triangles.iter() {
+            assert!((v.position.x - 1.0).abs() <= 0.5 + EPSILON);
+            assert!((v.position.y - 2.0).abs() <= 0.5 + EPSILON);
+            assert!((v.position.z - 3.0).abs() <= 0.5 + EPSILON);
+        }
+        assert!((surface_area(&triangles) - 6.0).abs() < 1e-4);
+    }
+
+    #[test]
+    fn projection_is_relative_to_base_point() {
+        let slice_plane = SlicePlane {
+            normal: Vector4::unit_w(),
+            base_point: Vector4::new(1.0, 0.0, 0.0, 0.0),
+            proj_matrix: Matrix4::from_diagonal(Vector4::new(
+                2.0, 1.0, 1.0, 1.0,
+            )),
+        };
+        let triangles = tesseract().slice(&Transform4::default(), &slice_plane);
+
+        assert!(!triangles.is_empty());
+        for v in triangles.iter() {
+            assert!(v.position.x >= -3.0 - EPSILON);
+            assert!(v.position.x <= -1.0 + EPSILON);
+        }
+    }
+
+    #[test]
+    fn missed_slice_is_empty() {
+        let slice_plane = SlicePlane {
+            base_point: Vector4::new(0.0, 0.0, 0.0, 2.0),
+            ..SlicePlane::default()
+        };
+        let triangles = tesseract().slice(&Transform4::default(), &slice_plane);
+        assert!(triangles.is_empty());
+    }
+
+    #[test]
+    fn single_tetrahedron() {
+        let color = Vector4::new(0.0, 1.0, 0.0, 1.0);
+        let vertex = |x, y, z, w| Vertex4 {
+            position: Vector4::new(x, y, z, w),
+            color,
+        };
+        let mesh = TetrahedronMesh {
+            vertices: vec![
+                vertex(0.0, 0.0, 0.0, -1.0),
+                vertex(1.0, 0.0, 0.0, 1.0),
+                vertex(0.0, 1.0, 0.0, 1.0),
+                vertex(0.0, 0.0, 1.0, 1.0),
+            ],
+            indices: vec![0, 1, 2, 3],
+        };
+
+        // one vertex on one side, three on the other gives a triangle halfway
+        // along each edge
+        let triangles =
+            mesh.slice(&Transform4::default(), &SlicePlane::default());
+        assert_eq!(triangles.len(), 3);
+        let expected = [
+            Vector4::new(0.5, 0.0, 0.0, 1.0),
+            Vector4::new(0.0, 0.5, 0.0, 1.0),
+            Vector4::new(0.0, 0.0, 0.5, 1.0),
+        ];
+        for (v, e) in triangles.iter().zip(expected.iter()) {
+            assert!((v.position - e).magnitude() < EPSILON);
+        }
+        let n = 1.0 / 3f32.sqrt();
+        assert!(
+            (triangles[0].normal - Vector4::new(n, n, n, 1.0)).magnitude()
+                < EPSILON
+        );
+    }
+}
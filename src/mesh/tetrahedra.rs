@@ -1,5 +1,5 @@
 use super::Mesh;
-use crate::context::graphics::Vertex4;
+use crate::graphics::Vertex4;
 
 use cgmath::{InnerSpace, Vector4};
 use std::collections::HashMap;
@@ -10,15 +10,38 @@ pub struct TetrahedronMesh {
     pub indices: Vec<u32>,
 }
 
-fn get_face_vertex_indices(mesh: &Mesh, face_idx: usize) -> Vec<usize> {
-    let mut vertex_indices = Vec::new();
-    for edge_idx in mesh.faces[face_idx].edges.iter() {
+// Walks around the edges of a face, which are stored in order, to get its
+// vertices in order. Edges aren't necessarily all pointing the same way around
+// the face, so each step looks for the vertex shared with the previous edge.
+pub(super) fn get_face_vertex_indices(
+    mesh: &Mesh,
+    face_idx: usize,
+) -> Vec<usize> {
+    let face_edges = &mesh.faces[face_idx].edges;
+    let first = &mesh.edges[face_edges[0]];
+    let second = &mesh.edges[face_edges[1]];
+
+    let mut current = if first.tl_vertex == second.hd_vertex
+        || first.tl_vertex == second.tl_vertex
+    {
+        first.tl_vertex
+    } else {
+        first.hd_vertex
+    };
+    let mut vertex_indices = vec![if current == first.tl_vertex {
+        first.hd_vertex
+    } else {
+        first.tl_vertex
+    }];
+
+    for edge_idx in face_edges.iter().skip(1) {
         let edge = &mesh.edges[*edge_idx];
-        if vertex_indices.contains(&edge.hd_vertex) {
-            vertex_indices.push(edge.tl_vertex);
+        vertex_indices.push(current);
+        current = if edge.hd_vertex == current {
+            edge.tl_vertex
         } else {
-            vertex_indices.push(edge.hd_vertex);
-        }
+            edge.hd_vertex
+        };
     }
 
     vertex_indices
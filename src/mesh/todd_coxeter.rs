@@ -55,7 +55,7 @@ impl CosetTable {
         let mut queue = Vec::new();
 
         self.merge(&mut queue, coset1, coset2);
-        while queue.len() != 0 {
+        while !queue.is_empty() {
             let e = queue.remove(0);
             for g in 0..self.num_gens {
                 if let Some(f) = self.table[e][g] {
@@ -171,7 +171,7 @@ pub fn coset_table(
 }
 
 pub fn table_bfs_fold<T, F>(
-    table: &Vec<Vec<usize>>,
+    table: &[Vec<usize>],
     start: usize,
     initial: T,
     f: F,
@@ -189,7 +189,7 @@ where
 
     while let Some(top) = queue.pop_front() {
         for (g, next) in table[top].iter().enumerate() {
-            if seen.contains(&next) {
+            if seen.contains(next) {
                 continue;
             }
 
@@ -0,0 +1,496 @@
+// Consistency checks for meshes, since the collision code assumes a valid
+// convex polytope and gives nonsense results rather than failing loudly when
+// it isn't one.
+
+use super::Mesh;
+use crate::util::EPSILON;
+use cgmath::{InnerSpace, Vector4};
+use std::error::Error;
+use std::fmt;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum MeshError {
+    // An index which is past the end of the list it refers into.
+    VertexOutOfRange {
+        edge: usize,
+        vertex: usize,
+    },
+    EdgeOutOfRange {
+        face: usize,
+        edge: usize,
+    },
+    FaceOutOfRange {
+        cell: usize,
+        face: usize,
+    },
+    // A vertex index in the cells given to Mesh::try_from_cells.
+    CellVertexOutOfRange {
+        cell: usize,
+        vertex: usize,
+    },
+    CellOutOfRange {
+        face: usize,
+        cell: usize,
+    },
+    // The vertex data doesn't have one entry per vertex.
+    VertexDataLength {
+        vertices: usize,
+        vertex_data: usize,
+    },
+    // V - E + F - C is always 0 for the boundary of a 4D polytope.
+    EulerCharacteristic {
+        characteristic: isize,
+    },
+    // Every face needs to be listed by exactly two cells.
+    FaceNotShared {
+        face: usize,
+        cells: usize,
+    },
+    // The cells listing a face aren't its hd_cell and tl_cell.
+    FaceCellsMismatch {
+        face: usize,
+    },
+    // The edges of a face don't form a single loop.
+    OpenFace {
+        face: usize,
+    },
+    // A vertex with a NaN or infinite coordinate.
+    NonFiniteVertex {
+        vertex: usize,
+    },
+    NonUnitNormal {
+        cell: usize,
+        magnitude: f32,
+    },
+    // The normal of a cell points towards the average of the mesh's vertices,
+    // which is inside any convex polytope.
+    InwardNormal {
+        cell: usize,
+    },
+    // A vertex of a cell which isn't on the cell's hyperplane.
+    NonPlanarCell {
+        cell: usize,
+        vertex: usize,
+        distance: f32,
+    },
+    // A vertex of the mesh which is above the hyperplane of a cell.
+    NonConvex {
+        cell: usize,
+        vertex: usize,
+        distance: f32,
+    },
+}
+
+impl fmt::Display for MeshError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            MeshError::VertexOutOfRange { edge, vertex } => {
+                write!(f, "edge {} has nonexistent vertex {}", edge, vertex)
+            }
+            MeshError::EdgeOutOfRange { face, edge } => {
+                write!(f, "face {} has nonexistent edge {}", face, edge)
+            }
+            MeshError::FaceOutOfRange { cell, face } => {
+                write!(f, "cell {} has nonexistent face {}", cell, face)
+            }
+            MeshError::CellVertexOutOfRange { cell, vertex } => {
+                write!(f, "cell {} has nonexistent vertex {}", cell, vertex)
+            }
+            MeshError::CellOutOfRange { face, cell } => {
+                write!(f, "face {} refers to nonexistent cell {}", face, cell)
+            }
+            MeshError::VertexDataLength {
+                vertices,
+                vertex_data,
+            } => write!(
+                f,
+                "{} vertices but vertex data for {}",
+                vertices, vertex_data
+            ),
+            MeshError::EulerCharacteristic { characteristic } => write!(
+                f,
+                "Euler characteristic is {} rather than 0",
+                characteristic
+            ),
+            MeshError::FaceNotShared { face, cells } => {
+                write!(
+                    f,
+                    "face {} is part of {} cells rather than 2",
+                    face, cells
+                )
+            }
+            MeshError::FaceCellsMismatch { face } => write!(
+                f,
+                "hd_cell and tl_cell of face {} aren't the cells it's part of",
+                face
+            ),
+            MeshError::OpenFace { face } => {
+                write!(f, "edges of face {} don't form a loop", face)
+            }
+            MeshError::NonFiniteVertex { vertex } => {
+                write!(f, "vertex {} isn't finite", vertex)
+            }
+            MeshError::NonUnitNormal { cell, magnitude } => {
+                write!(f, "normal of cell {} has magnitude {}", cell, magnitude)
+            }
+            MeshError::InwardNormal { cell } => {
+                write!(f, "normal of cell {} points inwards", cell)
+            }
+            MeshError::NonPlanarCell {
+                cell,
+                vertex,
+                distance,
+            } => write!(
+                f,
+                "vertex {} is {} away from the hyperplane of cell {}",
+                vertex, distance, cell
+            ),
+            MeshError::NonConvex {
+                cell,
+                vertex,
+                distance,
+            } => write!(
+                f,
+                "vertex {} is {} above the hyperplane of cell {}",
+                vertex, distance, cell
+            ),
+        }
+    }
+}
+
+impl Error for MeshError {}
+
+impl Mesh {
+    // Checks that the mesh is a convex polytope with consistent topology.
+    // Returns every problem found.
+    pub fn validate(&self) -> Result<(), Vec<MeshError>> {
+        let errors = self.index_errors();
+        // the rest of the checks would index out of bounds
+        if !errors.is_empty() {
+            return Err(errors);
+        }
+
+        let mut errors = self.topology_errors();
+        errors.extend(self.geometry_errors());
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors)
+        }
+    }
+
+    fn index_errors(&self) -> Vec<MeshError> {
+        let mut errors = Vec::new();
+
+        if self.vertex_data.len() != self.vertices.len() {
+            errors.push(MeshError::VertexDataLength {
+                vertices: self.vertices.len(),
+                vertex_data: self.vertex_data.len(),
+            });
+        }
+        for (edge_idx, edge) in self.edges.iter().enumerate() {
+            for &vertex in [edge.hd_vertex, edge.tl_vertex].iter() {
+                if vertex >= self.vertices.len() {
+                    errors.push(MeshError::VertexOutOfRange {
+                        edge: edge_idx,
+                        vertex,
+                    });
+                }
+            }
+        }
+        for (face_idx, face) in self.faces.iter().enumerate() {
+            for &edge in face.edges.iter() {
+                if edge >= self.edges.len() {
+                    errors.push(MeshError::EdgeOutOfRange {
+                        face: face_idx,
+                        edge,
+                    });
+                }
+            }
+            for &cell in [face.hd_cell, face.tl_cell].iter() {
+                if cell >= self.cells.len() {
+                    errors.push(MeshError::CellOutOfRange {
+                        face: face_idx,
+                        cell,
+                    });
+                }
+            }
+        }
+        for (cell_idx, cell) in self.cells.iter().enumerate() {
+            for &face in cell.faces.iter() {
+                if face >= self.faces.len() {
+                    errors.push(MeshError::FaceOutOfRange {
+                        cell: cell_idx,
+                        face,
+                    });
+                }
+            }
+        }
+
+        errors
+    }
+
+    fn topology_errors(&self) -> Vec<MeshError> {
+        let mut errors = Vec::new();
+
+        let characteristic = self.vertices.len() as isize
+            - self.edges.len() as isize
+            + self.faces.len() as isize
+            - self.cells.len() as isize;
+        if characteristic != 0 {
+            errors.push(MeshError::EulerCharacteristic { characteristic });
+        }
+
+        let mut face_cells = vec![Vec::new(); self.faces.len()];
+        for (cell_idx, cell) in self.cells.iter().enumerate() {
+            for &face_idx in cell.faces.iter() {
+                face_cells[face_idx].push(cell_idx);
+            }
+        }
+        for (face_idx, (face, cells)) in
+            self.faces.iter().zip(face_cells.iter()).enumerate()
+        {
+            if cells.len() != 2 {
+                errors.push(MeshError::FaceNotShared {
+                    face: face_idx,
+                    cells: cells.len(),
+                });
+            } else if !(cells.contains(&face.hd_cell)
+                && cells.contains(&face.tl_cell)
+                && face.hd_cell != face.tl_cell)
+            {
+                errors.push(MeshError::FaceCellsMismatch { face: face_idx });
+            }
+
+            if !self.is_closed_loop(&face.edges) {
+                errors.push(MeshError::OpenFace { face: face_idx });
+            }
+        }
+
+        errors
+    }
+
+    // Whether the edges join up end to end into a single loop.
+    fn is_closed_loop(&self, edges: &[usize]) -> bool {
+        if edges.len() < 3 {
+            return false;
+        }
+
+        let first = &self.edges[edges[0]];
+        let mut vertex = first.tl_vertex;
+        let mut visited = vec![false; edges.len()];
+        visited[0] = true;
+        for _ in 1..edges.len() {
+            let next = edges.iter().enumerate().position(|(i, &edge_idx)| {
+                let edge = &self.edges[edge_idx];
+                !visited[i]
+                    && (edge.hd_vertex == vertex || edge.tl_vertex == vertex)
+            });
+            match next {
+                Some(i) => {
+                    visited[i] = true;
+                    let edge = &self.edges[edges[i]];
+                    vertex = if edge.hd_vertex == vertex {
+                        edge.tl_vertex
+                    } else {
+                        edge.hd_vertex
+                    };
+                }
+                None => return false,
+            }
+        }
+        vertex == first.hd_vertex
+    }
+
+    fn geometry_errors(&self) -> Vec<MeshError> {
+        // NaNs fail every comparison, so they'd get through the other checks.
+        let finite = |v: &Vector4<f32>| {
+            v.x.is_finite()
+                && v.y.is_finite()
+                && v.z.is_finite()
+                && v.w.is_finite()
+        };
+        let errors: Vec<_> = (0..self.vertices.len())
+            .filter(|&vertex| !finite(&self.vertices[vertex]))
+            .map(|vertex| MeshError::NonFiniteVertex { vertex })
+            .collect();
+        if !errors.is_empty() {
+            return errors;
+        }
+
+        let mut errors = Vec::new();
+        // Generated meshes pick up a few times EPSILON of rounding error.
+        let tolerance = 10.0 * EPSILON * self.radius.max(1.0);
+        // Any point inside the mesh would do to tell which way is out, and
+        // the average vertex is always inside, even if the origin isn't.
+        let centre = self.vertices.iter().sum::<Vector4<f32>>()
+            / self.vertices.len().max(1) as f32;
+
+        for (cell_idx, cell) in self.cells.iter().enumerate() {
+            let magnitude = cell.normal.magnitude();
+            if !magnitude.is_finite() || (magnitude - 1.0).abs() > tolerance {
+                errors.push(MeshError::NonUnitNormal {
+                    cell: cell_idx,
+                    magnitude,
+                });
+                continue;
+            }
+
+            let mut cell_vertices: Vec<usize> = cell
+                .faces
+                .iter()
+                .flat_map(|&face_idx| self.faces[face_idx].edges.iter())
+                .flat_map(|&edge_idx| {
+                    let edge = &self.edges[edge_idx];
+                    vec![edge.hd_vertex, edge.tl_vertex]
+                })
+                .collect();
+            cell_vertices.sort_unstable();
+            cell_vertices.dedup();
+            if cell_vertices.is_empty() {
+                continue;
+            }
+
+            // Measure everything from the average offset of the cell's
+            // vertices, so that no one vertex is favoured.
+            let height = |v: Vector4<f32>| cell.normal.dot(v - centre);
+            let offset = cell_vertices
+                .iter()
+                .map(|&i| height(self.vertices[i]))
+                .sum::<f32>()
+                / cell_vertices.len() as f32;
+            if offset <= 0.0 {
+                errors.push(MeshError::InwardNormal { cell: cell_idx });
+                continue;
+            }
+
+            for &vertex in cell_vertices.iter() {
+                let distance = height(self.vertices[vertex]) - offset;
+                if distance.abs() > tolerance {
+                    errors.push(MeshError::NonPlanarCell {
+                        cell: cell_idx,
+                        vertex,
+                        distance,
+                    });
+                }
+            }
+            for (vertex, v) in self.vertices.iter().enumerate() {
+                let distance = height(*v) - offset;
+                if distance > tolerance
+                    && cell_vertices.binary_search(&vertex).is_err()
+                {
+                    errors.push(MeshError::NonConvex {
+                        cell: cell_idx,
+                        vertex,
+                        distance,
+                    });
+                }
+            }
+        }
+
+        errors
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cgmath::Matrix4;
+
+    #[test]
+    fn generated_meshes_are_valid() {
+        let t = true;
+        let f = false;
+        let mut meshes = Vec::new();
+        for symbol in [
+            [3, 3, 3],
+            [4, 3, 3],
+            [3, 3, 4],
+            [3, 4, 3],
+            [5, 3, 3],
+            [3, 3, 5],
+        ]
+        .iter()
+        {
+            meshes.push(Mesh::from_schlafli_symbol(symbol));
+        }
+        meshes.push(Mesh::from_coxeter_diagram(&[4, 3, 3], [t, t, f, f]));
+        meshes.push(Mesh::from_coxeter_diagram(&[3, 4, 3], [f, t, f, t]));
+        meshes.push(Mesh::hyperbox(Vector4::new(2.0, 1.0, 0.5, 0.1)));
+        meshes.push(Mesh::capsule(0.5, 1.0));
+        meshes.push(Mesh::duoprism(3, 7));
+        meshes.push(
+            Mesh::from_schlafli_symbol(&[3, 3, 4]).transformed(
+                Matrix4::from_nonuniform_scale(3.0, 1.0, 0.5) * 2.0,
+            ),
+        );
+        // nowhere near the origin
+        let mut mesh = Mesh::from_schlafli_symbol(&[5, 3, 3]);
+        for v in mesh.vertices.iter_mut() {
+            *v += Vector4::new(3.0, -2.0, 5.0, 1.0);
+        }
+        meshes.push(mesh);
+
+        for mesh in meshes.iter() {
+            assert_eq!(mesh.validate(), Ok(()));
+        }
+    }
+
+    #[test]
+    fn broken_meshes_are_reported() {
+        let tesseract = Mesh::from_schlafli_symbol(&[4, 3, 3]);
+
+        let mut mesh = tesseract.clone();
+        mesh.cells[3].normal = -mesh.cells[3].normal;
+        assert_eq!(
+            mesh.validate(),
+            Err(vec![MeshError::InwardNormal { cell: 3 }])
+        );
+
+        // NaNs would sneak past the comparisons in the other checks
+        let mut mesh = tesseract.clone();
+        mesh.vertices[2].z = f32::NAN;
+        mesh.vertices[7].w = f32::INFINITY;
+        assert_eq!(
+            mesh.validate(),
+            Err(vec![
+                MeshError::NonFiniteVertex { vertex: 2 },
+                MeshError::NonFiniteVertex { vertex: 7 },
+            ])
+        );
+        let mut mesh = tesseract.clone();
+        mesh.cells[4].normal.x = f32::NAN;
+        let errors = mesh.validate().unwrap_err();
+        assert_eq!(errors.len(), 1);
+        assert!(matches!(
+            errors[0],
+            MeshError::NonUnitNormal { cell: 4, magnitude }
+                if magnitude.is_nan()
+        ));
+
+        let mut mesh = tesseract.clone();
+        mesh.faces[5].tl_cell = usize::MAX;
+        assert_eq!(
+            mesh.validate(),
+            Err(vec![MeshError::CellOutOfRange {
+                face: 5,
+                cell: usize::MAX,
+            }])
+        );
+
+        let mut mesh = tesseract.clone();
+        let face = mesh.cells[0].faces.pop().unwrap();
+        let errors = mesh.validate().unwrap_err();
+        assert!(errors.contains(&MeshError::FaceNotShared { face, cells: 1 }));
+
+        // moving a vertex bends the four cells around it, so none of their
+        // eight vertices are on the average hyperplane
+        let mut mesh = tesseract;
+        mesh.vertices[0] *= 1.1;
+        let errors = mesh.validate().unwrap_err();
+        assert_eq!(errors.len(), 32);
+        assert!(errors
+            .iter()
+            .all(|e| matches!(e, MeshError::NonPlanarCell { .. })));
+    }
+}
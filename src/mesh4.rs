@@ -1,13 +1,9 @@
-use crate::context::graphics::Vertex4;
+use crate::graphics::Vertex4;
+use crate::mesh::TetrahedronMesh;
 
 // TODO: this file is only used for generating the floor surface, replace with
 // better system
 
-pub struct Mesh4 {
-    pub vertices: Vec<Vertex4>,
-    pub indices: Vec<u32>,
-}
-
 fn cube(
     size: f32,
     fixed_axis: usize,
@@ -20,11 +16,11 @@ fn cube(
 
     for mut i in 0..8 {
         let mut position = [0f32; 4];
-        for j in 0..4 {
+        for (j, p) in position.iter_mut().enumerate() {
             if j == fixed_axis {
-                position[j] = fixed_value * size;
+                *p = fixed_value * size;
             } else {
-                position[j] = ((i & 1) as f32 * 2.0 - 1.0) * size;
+                *p = ((i & 1) as f32 * 2.0 - 1.0) * size;
                 i /= 2;
             }
         }
@@ -34,7 +30,7 @@ fn cube(
         });
     }
 
-    #[cfg_attr(rustfmt, rustfmt_skip)]
+    #[rustfmt::skip]
     let new_indices = vec![
         1, 2, 4, 7,
         0, 1, 2, 4,
@@ -46,7 +42,7 @@ fn cube(
     indices.extend(new_indices.iter().map(|x| x + vertex_size));
 }
 
-pub fn floor(size: f32) -> Mesh4 {
+pub fn floor(size: f32) -> TetrahedronMesh {
     let x = size / 2.0;
     let color = [1.0f32; 4];
 
@@ -55,5 +51,5 @@ pub fn floor(size: f32) -> Mesh4 {
 
     cube(x, 1, 0.0, color, &mut vertices, &mut indices);
 
-    Mesh4 { vertices, indices }
+    TetrahedronMesh { vertices, indices }
 }
@@ -1,13 +1,81 @@
-use super::Collider;
+use super::{Aabb, Collider, Inertia};
 use crate::alg::{Bivec4, Rotor4, Vec4};
+use crate::util::EPSILON;
 use cgmath::{InnerSpace, Vector4, Zero};
+use serde::{Deserialize, Serialize};
 
-#[derive(Debug, Clone)]
+// How the values from two materials in contact are combined. If the two
+// materials use different modes, the one that comes last in this list wins.
+#[derive(
+    Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize,
+)]
+pub enum CombineMode {
+    Average,
+    Min,
+    Multiply,
+    Max,
+}
+
+impl CombineMode {
+    pub fn combine(a: (f32, CombineMode), b: (f32, CombineMode)) -> f32 {
+        let (a, b, mode) = (a.0, b.0, a.1.max(b.1));
+        match mode {
+            CombineMode::Average => 0.5 * (a + b),
+            CombineMode::Min => a.min(b),
+            CombineMode::Multiply => a * b,
+            CombineMode::Max => a.max(b),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
 pub struct Material {
     pub restitution: f32,
+    // Friction coefficients for when the surfaces are at rest relative to
+    // each other and for when they are sliding.
+    pub static_friction: f32,
+    pub dynamic_friction: f32,
+    pub restitution_combine: CombineMode,
+    pub friction_combine: CombineMode,
 }
 
-#[derive(Debug, Clone)]
+impl Default for Material {
+    fn default() -> Self {
+        Self {
+            restitution: 0.2,
+            static_friction: 0.4,
+            dynamic_friction: 0.4,
+            restitution_combine: CombineMode::Min,
+            friction_combine: CombineMode::Average,
+        }
+    }
+}
+
+impl Material {
+    pub fn restitution_with(&self, other: &Material) -> f32 {
+        CombineMode::combine(
+            (self.restitution, self.restitution_combine),
+            (other.restitution, other.restitution_combine),
+        )
+    }
+
+    pub fn static_friction_with(&self, other: &Material) -> f32 {
+        CombineMode::combine(
+            (self.static_friction, self.friction_combine),
+            (other.static_friction, other.friction_combine),
+        )
+    }
+
+    pub fn dynamic_friction_with(&self, other: &Material) -> f32 {
+        CombineMode::combine(
+            (self.dynamic_friction, self.friction_combine),
+            (other.dynamic_friction, other.friction_combine),
+        )
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Velocity {
     pub linear: Vector4<f32>,
     pub angular: Bivec4,
@@ -25,11 +93,17 @@ impl Velocity {
 #[derive(Clone)]
 pub struct Body {
     pub mass: f32,
-    // for tesseracts it's sufficient to keep this as a scalar, but it really
-    // should be a tensor of shape Bivec4 -> Bivec4
-    pub moment_inertia_scalar: f32,
+    pub inertia: Inertia,
     pub material: Material,
     pub stationary: bool,
+    // Sleeping bodies are skipped by the simulation until something wakes
+    // them up. idle_time is how long the body has been moving slowly enough
+    // to be put to sleep.
+    pub asleep: bool,
+    pub idle_time: f32,
+    // Bullets have their motion swept each step so that they can't pass
+    // through other bodies when moving fast. Spheres always get this.
+    pub bullet: bool,
 
     pub pos: Vector4<f32>,
     pub rotation: Rotor4,
@@ -40,12 +114,39 @@ pub struct Body {
 }
 
 impl Body {
+    // A body at rest with no rotation and the default material. Anything else
+    // can be set afterwards, or with struct update syntax.
+    pub fn new(
+        collider: Collider,
+        mass: f32,
+        inertia: Inertia,
+        pos: Vector4<f32>,
+    ) -> Self {
+        Self {
+            mass,
+            inertia,
+            material: Material::default(),
+            stationary: false,
+            asleep: false,
+            idle_time: 0.0,
+            bullet: false,
+            pos,
+            rotation: Rotor4::identity(),
+            vel: Velocity::zero(),
+            collider,
+        }
+    }
+
     pub fn resolve_impulse(
         &mut self,
         impulse: Vector4<f32>,
         world_contact: Vector4<f32>,
     ) {
         if !self.stationary {
+            if self.asleep {
+                self.wake();
+            }
+
             let body_contact = self.world_pos_to_body(world_contact);
             let delta_angular_vel = self.inverse_moment_of_inertia(
                 &Vec4::from(body_contact)
@@ -57,22 +158,66 @@ impl Body {
         }
     }
 
-    pub fn step(&mut self, dt: f32) {
+    // Like resolve_impulse, but for a purely angular impulse given in world
+    // space.
+    pub fn resolve_angular_impulse(&mut self, impulse: &Bivec4) {
         if !self.stationary {
-            // apply gravity
-            self.vel.linear += Vector4::unit_y() * (-9.8 * dt);
+            if self.asleep {
+                self.wake();
+            }
 
-            self.pos += self.vel.linear * dt;
-            self.rotation.update(&(dt * self.vel.angular));
+            let delta_angular_vel = self.inverse_moment_of_inertia(
+                &self.rotation.reverse().rotate_bv(impulse),
+            );
+            self.vel.angular = self.vel.angular + delta_angular_vel;
         }
     }
 
-    pub fn inverse_moment_of_inertia(&self, body_bivec: &Bivec4) -> Bivec4 {
-        if self.moment_inertia_scalar <= 0.0 {
-            return Bivec4::zero();
+    // Whether the simulation needs to move this body at all.
+    pub fn is_active(&self) -> bool {
+        !self.stationary && !self.asleep
+    }
+
+    pub fn wake(&mut self) {
+        self.asleep = false;
+        self.idle_time = 0.0;
+    }
+
+    pub fn sleep(&mut self) {
+        self.asleep = true;
+        self.vel = Velocity::zero();
+    }
+
+    pub fn apply_gravity(&mut self, dt: f32, gravity: Vector4<f32>) {
+        if self.is_active() {
+            self.vel.linear += gravity * dt;
         }
+    }
 
-        1.0 / self.moment_inertia_scalar * *body_bivec
+    pub fn step(&mut self, dt: f32) {
+        if self.is_active() {
+            self.pos += self.vel.linear * dt;
+            // With no torque it's the angular momentum in world space that
+            // stays the same rather than the angular velocity in the body
+            // frame, so the velocity is worked out again from that as the
+            // body turns, using the velocity half way through the step. The
+            // two only differ if the inertia isn't isotropic.
+            let momentum = self
+                .rotation
+                .rotate_bv(&self.inertia.apply(&self.vel.angular));
+            let inertia = &self.inertia;
+            let velocity_at = |rotation: Rotor4| {
+                inertia.apply_inverse(&rotation.reverse().rotate_bv(&momentum))
+            };
+            let mut halfway = self.rotation;
+            halfway.update(&(0.5 * dt * self.vel.angular));
+            self.rotation.update(&(dt * velocity_at(halfway)));
+            self.vel.angular = velocity_at(self.rotation);
+        }
+    }
+
+    pub fn inverse_moment_of_inertia(&self, body_bivec: &Bivec4) -> Bivec4 {
+        self.inertia.apply_inverse(body_bivec)
     }
 
     pub fn vel_at(&self, world_pos: Vector4<f32>) -> Vector4<f32> {
@@ -90,50 +235,86 @@ impl Body {
         &self,
         start: Vector4<f32>,
         dir: Vector4<f32>,
-    ) -> Option<f32> {
-        let start = self.world_pos_to_body(start);
-        let dir = self.world_vec_to_body(dir);
+    ) -> Option<RayHit> {
+        // Half-space normals are in world space, as they are for collisions,
+        // so those aren't turned with the body.
+        if let Collider::HalfSpace { .. } = self.collider {
+            return ray_intersect_local(&self.collider, start - self.pos, dir);
+        }
+        let hit = ray_intersect_local(
+            &self.collider,
+            self.world_pos_to_body(start),
+            self.world_vec_to_body(dir),
+        )?;
+        Some(RayHit {
+            normal: self.body_vec_to_world(hit.normal),
+            ..hit
+        })
+    }
 
+    pub fn aabb(&self) -> Aabb {
         match &self.collider {
-            Collider::Mesh { mesh } => {
-                let mut interval = (std::f32::NEG_INFINITY, std::f32::INFINITY);
-
-                for cell in mesh.cells.iter() {
-                    // grab a representative vertex on the cell
-                    let v0 = mesh.vertices[mesh.edges
-                        [mesh.faces[cell.faces[0]].edges[0]]
-                        .hd_vertex];
-
-                    let denom = dir.dot(cell.normal);
-                    let lambda = (v0 - start).dot(cell.normal) / denom;
-
-                    if denom < 0.0 {
-                        interval.0 = interval.0.max(lambda);
-                    } else {
-                        interval.1 = interval.1.min(lambda);
+            Collider::HalfSpace { normal } => {
+                // Only axis-aligned half-spaces have any finite bounds.
+                let mut aabb = Aabb::infinite();
+                for i in 0..4 {
+                    if (normal[i].abs() - 1.0).abs() < EPSILON {
+                        if normal[i] > 0.0 {
+                            aabb.max[i] = self.pos[i];
+                        } else {
+                            aabb.min[i] = self.pos[i];
+                        }
                     }
-
-                    if interval.1 < interval.0 {
-                        return None;
+                }
+                aabb
+            }
+            Collider::Mesh { mesh } => {
+                let mut aabb = Aabb {
+                    min: self.pos,
+                    max: self.pos,
+                };
+                for v in mesh.vertices.iter() {
+                    let v = self.body_pos_to_world(*v);
+                    for i in 0..4 {
+                        aabb.min[i] = aabb.min[i].min(v[i]);
+                        aabb.max[i] = aabb.max[i].max(v[i]);
                     }
                 }
-
-                Some(interval.0)
+                aabb
             }
             Collider::Sphere { radius } => {
-                // Solve a quadratic equation!
-                let a = dir.magnitude2();
-                let b = 2.0 * start.dot(dir);
-                let c = start.magnitude2() - radius * radius;
-
-                let discriminant = b * b - 4.0 * a * c;
-                if discriminant >= 0.0 {
-                    Some((-b - discriminant.sqrt()) / (2.0 * a))
-                } else {
-                    None
+                let extent = Vector4::new(1.0, 1.0, 1.0, 1.0) * *radius;
+                Aabb {
+                    min: self.pos - extent,
+                    max: self.pos + extent,
+                }
+            }
+            collider => {
+                let mut aabb = Aabb {
+                    min: self.pos,
+                    max: self.pos,
+                };
+                for i in 0..4 {
+                    let mut axis = Vector4::zero();
+                    axis[i] = 1.0;
+                    for &d in [axis, -axis].iter() {
+                        // Only compounds which Collider::compound would have
+                        // rejected, with a half-space or nothing in them,
+                        // have no support. Bounding those by everything
+                        // keeps them from being missed.
+                        let support = match collider
+                            .local_support(self.world_vec_to_body(d))
+                        {
+                            Some(support) => support,
+                            None => return Aabb::infinite(),
+                        };
+                        let v = self.body_pos_to_world(support);
+                        aabb.min[i] = aabb.min[i].min(v[i]);
+                        aabb.max[i] = aabb.max[i].max(v[i]);
+                    }
                 }
+                aabb
             }
-            _ => None,
         }
     }
 
@@ -157,3 +338,295 @@ impl Body {
             .into()
     }
 }
+
+// Where a ray first hits a body, as a multiple of the ray's direction, along
+// with the child of the collider that was hit if it's a compound. The normal
+// is the outward normal of the surface there. Rays starting inside a body hit
+// it straight away, with the normal pointing back along the ray.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RayHit {
+    pub lambda: f32,
+    pub child: usize,
+    pub normal: Vector4<f32>,
+}
+
+// Intersects a ray with a collider, in the collider's own frame. Children of
+// compounds are numbered in the same order as ColliderRef::leaves.
+fn ray_intersect_local(
+    collider: &Collider,
+    start: Vector4<f32>,
+    dir: Vector4<f32>,
+) -> Option<RayHit> {
+    let (t0, t1) = match collider {
+        Collider::Mesh { mesh } => {
+            let mut interval = (f32::NEG_INFINITY, f32::INFINITY);
+
+            for cell in mesh.cells.iter() {
+                // grab a representative vertex on the cell
+                let v0 = mesh.vertices
+                    [mesh.edges[mesh.faces[cell.faces[0]].edges[0]].hd_vertex];
+
+                let denom = dir.dot(cell.normal);
+                let height = (v0 - start).dot(cell.normal);
+                // Rays parallel to the cell are either always or never
+                // inside it.
+                if denom.abs() < EPSILON {
+                    if height < 0.0 {
+                        return None;
+                    }
+                    continue;
+                }
+                let lambda = height / denom;
+
+                if denom < 0.0 {
+                    interval.0 = interval.0.max(lambda);
+                } else {
+                    interval.1 = interval.1.min(lambda);
+                }
+
+                if interval.1 < interval.0 {
+                    return None;
+                }
+            }
+
+            interval
+        }
+        Collider::Sphere { radius } => {
+            // Solve a quadratic equation!
+            let a = dir.magnitude2();
+            let b = 2.0 * start.dot(dir);
+            let c = start.magnitude2() - radius * radius;
+
+            let discriminant = b * b - 4.0 * a * c;
+            if discriminant >= 0.0 {
+                (
+                    (-b - discriminant.sqrt()) / (2.0 * a),
+                    (-b + discriminant.sqrt()) / (2.0 * a),
+                )
+            } else {
+                return None;
+            }
+        }
+        Collider::HalfSpace { normal } => {
+            let height = start.dot(*normal);
+            let denom = dir.dot(*normal);
+            if denom.abs() < EPSILON {
+                if height <= 0.0 {
+                    (f32::NEG_INFINITY, f32::INFINITY)
+                } else {
+                    return None;
+                }
+            } else if denom < 0.0 {
+                (-height / denom, f32::INFINITY)
+            } else {
+                (f32::NEG_INFINITY, -height / denom)
+            }
+        }
+        Collider::Compound { children } => {
+            let mut closest: Option<RayHit> = None;
+            let mut first_child = 0;
+            for child in children.iter() {
+                let to_child = child.rotation.reverse();
+                let hit = ray_intersect_local(
+                    &child.collider,
+                    to_child.rotate(&(start - child.offset).into()).into(),
+                    to_child.rotate(&dir.into()).into(),
+                );
+                if let Some(hit) = hit {
+                    if !matches!(closest, Some(c) if c.lambda <= hit.lambda) {
+                        closest = Some(RayHit {
+                            lambda: hit.lambda,
+                            child: first_child + hit.child,
+                            normal: child
+                                .rotation
+                                .rotate(&hit.normal.into())
+                                .into(),
+                        });
+                    }
+                }
+                first_child += child.collider.leaf_count();
+            }
+            return closest;
+        }
+        collider => collider.primitive_ray_intersect(start, dir)?,
+    };
+
+    // Anything entirely behind the start of the ray doesn't count.
+    if t1 < 0.0 {
+        return None;
+    }
+    let (lambda, normal) = if t0 < 0.0 {
+        (0.0, -dir.normalize())
+    } else {
+        (t0, collider.local_normal(start + dir * t0))
+    };
+
+    Some(RayHit {
+        lambda,
+        child: 0,
+        normal,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::physics::{BodyKey, ChildCollider, CollisionDetection};
+    use cgmath::{Matrix4, SquareMatrix};
+    use slotmap::Key;
+
+    #[test]
+    fn combine_modes() {
+        use CombineMode::*;
+
+        assert_eq!(CombineMode::combine((0.2, Average), (0.6, Average)), 0.4);
+        assert_eq!(CombineMode::combine((0.2, Min), (0.6, Min)), 0.2);
+        assert_eq!(CombineMode::combine((0.5, Multiply), (0.6, Multiply)), 0.3);
+        assert_eq!(CombineMode::combine((0.2, Max), (0.6, Max)), 0.6);
+
+        // the higher priority mode wins, whichever side it's on
+        assert_eq!(CombineMode::combine((0.2, Average), (0.6, Max)), 0.6);
+        assert_eq!(CombineMode::combine((0.2, Min), (0.6, Average)), 0.2);
+    }
+
+    #[test]
+    fn default_material_matches_old_behaviour() {
+        let bouncy = Material {
+            restitution: 0.8,
+            ..Material::default()
+        };
+        let dull = Material::default();
+        assert_eq!(bouncy.restitution_with(&dull), 0.2);
+        assert_eq!(bouncy.static_friction_with(&dull), 0.4);
+        assert_eq!(bouncy.dynamic_friction_with(&dull), 0.4);
+    }
+
+    #[test]
+    fn ray_reports_compound_child() {
+        let child = |offset: Vector4<f32>, collider: Collider| ChildCollider {
+            offset,
+            rotation: Rotor4::identity(),
+            collider,
+        };
+        let collider = Collider::compound(vec![
+            child(Vector4::unit_x() * -1.0, Collider::Sphere { radius: 0.5 }),
+            child(
+                Vector4::unit_x(),
+                Collider::Hyperbox {
+                    half_extents: Vector4::new(0.5, 0.5, 0.5, 0.5),
+                },
+            ),
+        ])
+        .unwrap();
+        let body = Body::new(
+            collider,
+            1.0,
+            Inertia::isotropic(1.0),
+            Vector4::unit_y(),
+        );
+
+        let down = -Vector4::unit_y();
+        let hit = body
+            .ray_intersect(Vector4::new(1.0, 5.0, 0.0, 0.0), down)
+            .unwrap();
+        assert_eq!(hit.child, 1);
+        assert!((hit.lambda - 3.5).abs() < 1e-4);
+
+        let hit = body
+            .ray_intersect(Vector4::new(-1.0, 5.0, 0.0, 0.0), down)
+            .unwrap();
+        assert_eq!(hit.child, 0);
+        assert!((hit.lambda - 3.5).abs() < 1e-4);
+
+        assert!(body
+            .ray_intersect(Vector4::new(0.0, 5.0, 0.0, 0.0), down)
+            .is_none());
+
+        // the hyperbox is hit on its top face
+        let hit = body
+            .ray_intersect(Vector4::new(1.0, 5.0, 0.0, 0.0), down)
+            .unwrap();
+        assert!((hit.normal - Vector4::unit_y()).magnitude() < 1e-4);
+        // and nothing's hit if the body is behind the ray
+        assert!(body
+            .ray_intersect(Vector4::new(1.0, 5.0, 0.0, 0.0), -down)
+            .is_none());
+    }
+
+    #[test]
+    fn half_space_ray_ignores_body_rotation() {
+        let floor = Body {
+            stationary: true,
+            rotation: Bivec4::new(0.5, 0.0, 0.3, 0.0, 0.0, 0.0).exp(),
+            ..Body::new(
+                Collider::HalfSpace {
+                    normal: Vector4::unit_y(),
+                },
+                0.0,
+                Inertia::zero(),
+                Vector4::unit_y(),
+            )
+        };
+
+        let hit = floor
+            .ray_intersect(Vector4::new(1.0, 3.0, 0.0, 0.0), -Vector4::unit_y())
+            .unwrap();
+        assert!((hit.lambda - 2.0).abs() < 1e-4, "{}", hit.lambda);
+        assert!((hit.normal - Vector4::unit_y()).magnitude() < 1e-4);
+
+        // collisions see the floor in the same place
+        let ball = Body::new(
+            Collider::Sphere { radius: 0.5 },
+            1.0,
+            Inertia::isotropic(0.1),
+            Vector4::new(1.0, 1.25, 0.0, 0.0),
+        );
+        let manifold = CollisionDetection::new()
+            .detect_collisions(
+                (BodyKey::null(), BodyKey::null()),
+                &floor,
+                &ball,
+            )
+            .unwrap();
+        assert!((manifold.depth - 0.25).abs() < 1e-4, "{}", manifold.depth);
+        assert!((manifold.normal - Vector4::unit_y()).magnitude() < 1e-4);
+    }
+
+    #[test]
+    fn tumbling_conserves_angular_momentum() {
+        // a domino, spinning in a mix of planes so that it tumbles
+        let (x, y, z, w) = (2.0, 0.5, 0.5, 0.1);
+        let initial = Bivec4::new(1.0, 0.0, 0.0, 1.0, 0.0, 0.5);
+        let inertia = Inertia::from_second_moment(Matrix4::from_diagonal(
+            Vector4::new(x * x, y * y, z * z, w * w) / 3.0,
+        ));
+        let mut body = Body::new(
+            Collider::Sphere { radius: 1.0 },
+            1.0,
+            inertia,
+            Vector4::zero(),
+        );
+        body.vel.angular = initial;
+        let momentum = |body: &Body| {
+            body.rotation
+                .rotate_bv(&body.inertia.apply(&body.vel.angular))
+        };
+        let energy = |body: &Body| {
+            body.vel.angular.dot(&body.inertia.apply(&body.vel.angular))
+        };
+
+        let initial_momentum = momentum(&body);
+        let initial_energy = energy(&body);
+        let mut change: f32 = 0.0;
+        for _ in 0..600 {
+            body.step(1.0 / 60.0);
+            let error = momentum(&body) + -1.0 * initial_momentum;
+            assert!(error.mag() < 1e-3 * initial_momentum.mag());
+            let error = energy(&body) - initial_energy;
+            assert!(error.abs() < 0.01 * initial_energy, "{}", error);
+            change = change.max((body.vel.angular + -1.0 * initial).mag());
+        }
+        // the angular velocity in the body frame doesn't stay the same
+        assert!(change > 0.1, "{}", change);
+    }
+}
@@ -0,0 +1,274 @@
+use cgmath::Vector4;
+use serde::{Deserialize, Serialize};
+use slotmap::DenseSlotMap;
+
+use super::{Body, BodyKey};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Aabb {
+    pub min: Vector4<f32>,
+    pub max: Vector4<f32>,
+}
+
+impl Aabb {
+    pub fn infinite() -> Self {
+        Self {
+            min: Vector4::new(
+                f32::NEG_INFINITY,
+                f32::NEG_INFINITY,
+                f32::NEG_INFINITY,
+                f32::NEG_INFINITY,
+            ),
+            max: Vector4::new(
+                f32::INFINITY,
+                f32::INFINITY,
+                f32::INFINITY,
+                f32::INFINITY,
+            ),
+        }
+    }
+
+    // The smallest box containing both boxes.
+    pub fn union(&self, other: &Aabb) -> Aabb {
+        let mut result = *self;
+        for i in 0..4 {
+            result.min[i] = self.min[i].min(other.min[i]);
+            result.max[i] = self.max[i].max(other.max[i]);
+        }
+        result
+    }
+
+    pub fn intersects(&self, other: &Aabb) -> bool {
+        (0..4)
+            .all(|i| self.min[i] <= other.max[i] && other.min[i] <= self.max[i])
+    }
+}
+
+// How candidate pairs are found before running the (expensive) narrowphase.
+#[derive(
+    Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize,
+)]
+pub enum Broadphase {
+    // Every pair of bodies. Only really useful for comparison.
+    BruteForce,
+    #[default]
+    SweepAndPrune,
+}
+
+impl Broadphase {
+    // Pairs where neither body is active (i.e. both are stationary or asleep)
+    // are never returned, as there's nothing for the solver to do.
+    pub fn candidate_pairs(
+        self,
+        bodies: &DenseSlotMap<BodyKey, Body>,
+    ) -> Vec<(BodyKey, BodyKey)> {
+        match self {
+            Broadphase::BruteForce => brute_force(bodies),
+            Broadphase::SweepAndPrune => sweep_and_prune(bodies),
+        }
+    }
+}
+
+fn brute_force(
+    bodies: &DenseSlotMap<BodyKey, Body>,
+) -> Vec<(BodyKey, BodyKey)> {
+    let body_keys: Vec<_> = bodies.keys().collect();
+
+    let mut pairs = Vec::new();
+    for i in 0..body_keys.len() {
+        for j in i + 1..body_keys.len() {
+            let (ka, kb) = (body_keys[i], body_keys[j]);
+            if bodies[ka].is_active() || bodies[kb].is_active() {
+                pairs.push((ka, kb));
+            }
+        }
+    }
+    pairs
+}
+
+fn sweep_and_prune(
+    bodies: &DenseSlotMap<BodyKey, Body>,
+) -> Vec<(BodyKey, BodyKey)> {
+    // A body whose position has gone NaN can't overlap anything, and would
+    // only confuse the sort.
+    let entries: Vec<_> = bodies
+        .iter()
+        .map(|(key, body)| (key, body.aabb(), body.is_active()))
+        .filter(|(_, aabb, _)| {
+            (0..4).all(|i| !aabb.min[i].is_nan() && !aabb.max[i].is_nan())
+        })
+        .collect();
+
+    // Sweep along the axis where the bodies are most spread out, ignoring
+    // unbounded bodies like half-spaces which would swamp everything else.
+    let mut sum = [0.0f32; 4];
+    let mut sum2 = [0.0f32; 4];
+    let mut count = 0.0f32;
+    for (_, aabb, _) in entries.iter() {
+        let centre = 0.5 * (aabb.min + aabb.max);
+        if (0..4).all(|i| centre[i].is_finite()) {
+            for i in 0..4 {
+                sum[i] += centre[i];
+                sum2[i] += centre[i] * centre[i];
+            }
+            count += 1.0;
+        }
+    }
+    let axis = (0..4)
+        .max_by(|&i, &j| {
+            let var_i = sum2[i] - sum[i] * sum[i] / count.max(1.0);
+            let var_j = sum2[j] - sum[j] * sum[j] / count.max(1.0);
+            var_i.total_cmp(&var_j)
+        })
+        .unwrap();
+
+    let mut sorted: Vec<_> = (0..entries.len()).collect();
+    sorted.sort_by(|&i, &j| {
+        entries[i].1.min[axis].total_cmp(&entries[j].1.min[axis])
+    });
+
+    let mut pairs = Vec::new();
+    let mut active: Vec<usize> = Vec::new();
+    for i in sorted {
+        let (key, aabb, awake) = entries[i];
+        active.retain(|&j| entries[j].1.max[axis] >= aabb.min[axis]);
+
+        for &j in active.iter() {
+            let (other_key, other_aabb, other_awake) = entries[j];
+            if !awake && !other_awake {
+                continue;
+            }
+            if aabb.intersects(&other_aabb) {
+                pairs.push((key.min(other_key), key.max(other_key)));
+            }
+        }
+
+        active.push(i);
+    }
+
+    // keep the output independent of the sort order so that simulations are
+    // deterministic
+    pairs.sort();
+    pairs
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mesh::Mesh;
+    use crate::physics::{Collider, Inertia};
+    use cgmath::{InnerSpace, Zero};
+
+    fn body(collider: Collider, pos: Vector4<f32>, stationary: bool) -> Body {
+        Body {
+            stationary,
+            ..Body::new(collider, 1.0, Inertia::isotropic(1.0 / 6.0), pos)
+        }
+    }
+
+    #[test]
+    fn half_space_bounds() {
+        let floor = body(
+            Collider::HalfSpace {
+                normal: Vector4::unit_y(),
+            },
+            Vector4::unit_y(),
+            true,
+        );
+        let aabb = floor.aabb();
+        assert_eq!(aabb.max.y, 1.0);
+        assert_eq!(aabb.min.y, f32::NEG_INFINITY);
+        assert_eq!(aabb.max.x, f32::INFINITY);
+
+        let slanted = body(
+            Collider::HalfSpace {
+                normal: Vector4::new(1.0, 1.0, 0.0, 0.0).normalize(),
+            },
+            Vector4::zero(),
+            true,
+        );
+        assert_eq!(slanted.aabb(), Aabb::infinite());
+    }
+
+    #[test]
+    fn sweep_and_prune_matches_brute_force() {
+        let mut bodies = DenseSlotMap::with_key();
+        bodies.insert(body(
+            Collider::HalfSpace {
+                normal: Vector4::unit_y(),
+            },
+            Vector4::zero(),
+            true,
+        ));
+        bodies.insert(body(
+            Collider::HalfSpace {
+                normal: -Vector4::unit_x(),
+            },
+            Vector4::unit_x() * 4.0,
+            true,
+        ));
+
+        let tesseract = Mesh::from_schlafli_symbol(&[4, 3, 3]);
+        let mut seed = 12345u32;
+        let mut random = || {
+            seed = seed.wrapping_mul(1_103_515_245).wrapping_add(12345);
+            (seed >> 16) as f32 / 65536.0
+        };
+        for i in 0..60 {
+            let pos = Vector4::new(
+                8.0 * random() - 4.0,
+                4.0 * random() - 0.5,
+                8.0 * random() - 4.0,
+                2.0 * random() - 1.0,
+            );
+            let collider = if i % 2 == 0 {
+                Collider::Sphere { radius: 0.5 }
+            } else {
+                Collider::Mesh {
+                    mesh: tesseract.clone(),
+                }
+            };
+            bodies.insert(body(collider, pos, false));
+        }
+
+        let sap = Broadphase::SweepAndPrune.candidate_pairs(&bodies);
+        assert!(!sap.is_empty());
+
+        // everything SAP finds is a real AABB overlap, and no overlap is
+        // missed
+        let mut expected: Vec<_> = Broadphase::BruteForce
+            .candidate_pairs(&bodies)
+            .into_iter()
+            .filter(|&(a, b)| bodies[a].aabb().intersects(&bodies[b].aabb()))
+            .map(|(a, b)| (a.min(b), a.max(b)))
+            .collect();
+        expected.sort();
+        assert_eq!(sap, expected);
+    }
+
+    #[test]
+    fn sweep_and_prune_skips_broken_bodies() {
+        let mut bodies = DenseSlotMap::with_key();
+        let sphere = Collider::Sphere { radius: 0.5 };
+        let a = bodies.insert(body(sphere.clone(), Vector4::zero(), false));
+        let b = bodies.insert(body(sphere.clone(), Vector4::unit_x(), false));
+        bodies.insert(body(
+            sphere.clone(),
+            Vector4::new(f32::NAN, 0.0, 0.0, 0.0),
+            false,
+        ));
+        bodies.insert(body(
+            sphere.clone(),
+            Vector4::new(f32::INFINITY, 0.0, 0.0, 0.0),
+            false,
+        ));
+        bodies.insert(body(
+            sphere,
+            Vector4::new(0.0, f32::NEG_INFINITY, 0.0, 0.0),
+            false,
+        ));
+
+        let pairs = Broadphase::SweepAndPrune.candidate_pairs(&bodies);
+        assert_eq!(pairs, vec![(a.min(b), a.max(b))]);
+    }
+}
@@ -1,7 +1,9 @@
-use super::Body;
+use super::{convex_collision, Body, BodyKey, ColliderRef};
+use crate::alg::Rotor4;
 use crate::mesh::{ClipMesh, Mesh};
-use crate::util::EPSILON;
-use crate::world::ObjectKey;
+use crate::util::{NotNaN, EPSILON};
+
+use std::cell::RefCell;
 
 use cgmath::{
     Array, InnerSpace, Matrix3, SquareMatrix, Vector3, Vector4, Zero,
@@ -12,24 +14,100 @@ pub enum Collider {
     HalfSpace { normal: Vector4<f32> },
     Mesh { mesh: Mesh },
     Sphere { radius: f32 },
+    // A box aligned with the body axes.
+    Hyperbox { half_extents: Vector4<f32> },
+    // A ball in the xzw hyperplane, extruded along the y axis.
+    Spherinder { radius: f32, half_height: f32 },
+    // A disk in the xz plane, extruded along both the y and w axes.
+    Cubinder { radius: f32, half_height: f32 },
+    // Every point within the radius of the segment along the y axis between
+    // -half_height and half_height.
+    Capsule { radius: f32, half_height: f32 },
+    // The product of a disk in the xy plane and a disk in the zw plane.
+    Duocylinder { radius_xy: f32, radius_zw: f32 },
+    // Several colliders fixed to the same body.
+    Compound { children: Vec<ChildCollider> },
+}
+
+// A part of a compound collider, placed relative to the body it's attached to.
+#[derive(Clone)]
+pub struct ChildCollider {
+    pub offset: Vector4<f32>,
+    pub rotation: Rotor4,
+    pub collider: Collider,
+}
+
+// Identifies a single collider: the body it's attached to, along with which
+// child it is if the body has a compound collider (and 0 otherwise).
+pub type ColliderKey = (BodyKey, usize);
+
+// Identifies the features of the two colliders that produced a contact point,
+// so that the same contact can be recognised in the next step.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ContactId {
+    // The only contact between the two colliders, e.g. for spheres.
+    Single,
+    // A vertex of a mesh, or a support point of some other collider, below a
+    // half-space.
+    Vertex(usize),
+    // A point on the incident cell after clipping it against the reference
+    // cell. `side` is true if the reference cell is on body a.
+    VertexCell {
+        side: bool,
+        reference_cell: usize,
+        incident_cell: usize,
+        clip_vertex: u64,
+    },
+    // The closest point between an edge and a face. `side` is true if the
+    // edge is on body b.
+    EdgeFace {
+        side: bool,
+        edge: usize,
+        face: usize,
+    },
+    // A support point of one collider which lies inside the other. `side` is
+    // true if the point is on body b.
+    Feature {
+        side: bool,
+        feature: u32,
+    },
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct Contact {
+    pub point: Vector4<f32>,
+    pub id: ContactId,
+    // The children of the two colliders that the contact is between, if they
+    // are compounds.
+    pub children: (usize, usize),
+}
+
+impl Contact {
+    pub fn new(point: Vector4<f32>, id: ContactId) -> Self {
+        Self {
+            point,
+            id,
+            children: (0, 0),
+        }
+    }
 }
 
 #[derive(Debug)]
 pub struct CollisionManifold {
     pub normal: Vector4<f32>,
     pub depth: f32,
-    pub contacts: Vec<Vector4<f32>>,
+    pub contacts: Vec<Contact>,
 }
 
 #[derive(Copy, Clone)]
 pub struct MeshRef<'a> {
-    pub body: &'a Body,
+    pub body: ColliderRef<'a>,
     pub mesh: &'a Mesh,
 }
 
 #[derive(Copy, Clone)]
 pub struct SphereRef<'a> {
-    pub body: &'a Body,
+    pub body: ColliderRef<'a>,
     pub radius: f32,
 }
 
@@ -46,6 +124,8 @@ struct VertexCellContact {
 struct EdgeFaceContact {
     // if true indicates that the edge is on body b but the face is on body a
     side: bool,
+    edge_idx: usize,
+    face_idx: usize,
     k: Vector4<f32>,
     t: Vector4<f32>,
     s: Vector4<f32>,
@@ -87,23 +167,121 @@ enum AxisResult {
 }
 
 pub struct CollisionDetection {
-    sat_cache: lru::LruCache<(ObjectKey, ObjectKey), Vector4<f32>>,
+    // Behind a RefCell so that detection can take &self, letting queries
+    // share the world's detector without needing it mutably.
+    sat_cache: RefCell<lru::LruCache<(ColliderKey, ColliderKey), Vector4<f32>>>,
+    // SAT has to check every pair of edges and faces, which gets expensive for
+    // big meshes like the 120-cell. Past this many checks mesh pairs are
+    // handled by GJK/EPA instead, which only gives a single contact point.
+    pub sat_feature_limit: usize,
+}
+
+impl Default for CollisionDetection {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl CollisionDetection {
     pub fn new() -> Self {
         Self {
-            sat_cache: lru::LruCache::new(1000),
+            sat_cache: RefCell::new(lru::LruCache::new(1000)),
+            sat_feature_limit: 10_000,
         }
     }
 
+    // Compound colliders can touch in several places at once, in which case
+    // this only returns the deepest collision. Use detect_all_collisions to
+    // get all of them.
     pub fn detect_collisions(
-        &mut self,
-        key: (ObjectKey, ObjectKey),
+        &self,
+        key: (BodyKey, BodyKey),
         a: &Body,
         b: &Body,
     ) -> Option<CollisionManifold> {
-        match (&a.collider, &b.collider) {
+        self.detect_all_collisions(key, a, b)
+            .into_iter()
+            .max_by_key(|manifold| NotNaN::new(manifold.depth).unwrap())
+    }
+
+    // Finds a separate manifold for each pair of children which are touching,
+    // with the contacts labelled with the children they're between.
+    pub fn detect_all_collisions(
+        &self,
+        key: (BodyKey, BodyKey),
+        a: &Body,
+        b: &Body,
+    ) -> Vec<CollisionManifold> {
+        self.detect_placed_collisions(Some(key), a, b)
+    }
+
+    // Like detect_all_collisions, but leaves the SAT cache alone. The cache
+    // is keyed on the pair of bodies, so checking them anywhere other than
+    // where they really are, e.g. partway along a sweep, would replace the
+    // separating axis for their actual positions.
+    pub fn detect_all_collisions_uncached(
+        &self,
+        a: &Body,
+        b: &Body,
+    ) -> Vec<CollisionManifold> {
+        self.detect_placed_collisions(None, a, b)
+    }
+
+    fn detect_placed_collisions(
+        &self,
+        key: Option<(BodyKey, BodyKey)>,
+        a: &Body,
+        b: &Body,
+    ) -> Vec<CollisionManifold> {
+        let (a, b) = (ColliderRef::new(a), ColliderRef::new(b));
+        let is_compound = |collider: ColliderRef| {
+            matches!(collider.collider, Collider::Compound { .. })
+        };
+        if !is_compound(a) && !is_compound(b) {
+            return self
+                .detect_child_collisions(
+                    key.map(|(ka, kb)| ((ka, 0), (kb, 0))),
+                    a,
+                    b,
+                )
+                .into_iter()
+                .collect();
+        }
+
+        let bounding_distance =
+            a.collider.bounding_radius() + b.collider.bounding_radius();
+        if (a.pos - b.pos).magnitude() > bounding_distance {
+            return Vec::new();
+        }
+
+        let children_a = a.leaves();
+        let children_b = b.leaves();
+        let mut manifolds = Vec::new();
+        for (i, &child_a) in children_a.iter().enumerate() {
+            for (j, &child_b) in children_b.iter().enumerate() {
+                if let Some(mut manifold) = self.detect_child_collisions(
+                    key.map(|(ka, kb)| ((ka, i), (kb, j))),
+                    child_a,
+                    child_b,
+                ) {
+                    for contact in manifold.contacts.iter_mut() {
+                        contact.children = (i, j);
+                    }
+                    manifolds.push(manifold);
+                }
+            }
+        }
+        manifolds
+    }
+
+    // The key is None when the SAT cache shouldn't be used.
+    fn detect_child_collisions(
+        &self,
+        key: Option<(ColliderKey, ColliderKey)>,
+        a: ColliderRef,
+        b: ColliderRef,
+    ) -> Option<CollisionManifold> {
+        match (a.collider, b.collider) {
             (Collider::HalfSpace { normal }, Collider::Mesh { mesh }) => {
                 let plane_distance = a.pos.dot(*normal);
                 let mut max_depth = 0.0;
@@ -111,7 +289,8 @@ impl CollisionDetection {
                 let contacts: Vec<_> = mesh
                     .vertices
                     .iter()
-                    .filter_map(|position| {
+                    .enumerate()
+                    .filter_map(|(vertex_idx, position)| {
                         let pos = b.body_pos_to_world(*position);
 
                         let distance = pos.dot(*normal);
@@ -121,14 +300,17 @@ impl CollisionDetection {
                             if depth > max_depth {
                                 max_depth = depth;
                             }
-                            Some(pos)
+                            Some(Contact::new(
+                                pos,
+                                ContactId::Vertex(vertex_idx),
+                            ))
                         } else {
                             None
                         }
                     })
                     .collect();
 
-                if contacts.len() > 0 {
+                if !contacts.is_empty() {
                     Some(CollisionManifold {
                         normal: *normal,
                         depth: max_depth,
@@ -147,7 +329,10 @@ impl CollisionDetection {
                     Some(CollisionManifold {
                         normal: *normal,
                         depth: radius - center_distance,
-                        contacts: vec![b.pos - *radius * normal],
+                        contacts: vec![Contact::new(
+                            b.pos - *radius * normal,
+                            ContactId::Single,
+                        )],
                     })
                 } else {
                     None
@@ -155,24 +340,43 @@ impl CollisionDetection {
             }
             (Collider::Mesh { .. }, Collider::HalfSpace { .. }) => {
                 // Just call this again with the arguments swapped
-                let mut manifold = self.detect_collisions((key.1, key.0), b, a);
+                let mut manifold =
+                    self.detect_child_collisions(swap(key), b, a);
                 if let Some(m) = &mut manifold {
                     m.normal *= -1.0;
                 }
                 manifold
             }
             (
-                Collider::Mesh { mesh: mesh_a },
-                Collider::Mesh { mesh: mesh_b },
+                Collider::Mesh { .. } | Collider::Hyperbox { .. },
+                Collider::Mesh { .. } | Collider::Hyperbox { .. },
             ) => {
+                // Boxes are polytopes too, so they get proper face contacts
+                // from SAT rather than going through GJK/EPA.
+                let mesh_a = a.collider.polytope().unwrap();
+                let mesh_b = b.collider.polytope().unwrap();
                 let a = MeshRef {
                     body: a,
-                    mesh: mesh_a,
+                    mesh: &mesh_a,
                 };
                 let b = MeshRef {
                     body: b,
-                    mesh: mesh_b,
+                    mesh: &mesh_b,
                 };
+                let sat_checks = mesh_a.edges.len() * mesh_b.faces.len()
+                    + mesh_b.edges.len() * mesh_a.faces.len();
+                if sat_checks > self.sat_feature_limit {
+                    if (a.body.pos - b.body.pos).magnitude()
+                        > mesh_a.radius + mesh_b.radius
+                    {
+                        return None;
+                    }
+                    return convex_collision(
+                        a,
+                        b,
+                        initial_direction(a.body, b.body),
+                    );
+                }
                 if let Some(contact) = self.mesh_sat(key, a, b) {
                     // dbg!(&contact);
                     return Some(match contact {
@@ -180,16 +384,17 @@ impl CollisionDetection {
                             resolve_vertex_cell_contact(a, b, contact)
                         }
                         ContactData::EdgeFace(contact) => {
-                            resolve_edge_face_contact(a, b, contact)
+                            resolve_edge_face_contact(contact)
                         }
                     });
                 }
                 None
             }
             (
-                Collider::Mesh { mesh: mesh_a },
+                Collider::Mesh { .. } | Collider::Hyperbox { .. },
                 Collider::Sphere { radius: radius_b },
             ) => {
+                let mesh_a = &a.collider.polytope().unwrap();
                 if (a.pos - b.pos).magnitude() > mesh_a.radius + radius_b {
                     return None;
                 }
@@ -199,14 +404,30 @@ impl CollisionDetection {
                 );
                 let displacement = closest_point - b.pos;
                 if displacement.magnitude() < EPSILON {
-                    None
+                    // The centre of the sphere is inside the mesh, so the
+                    // closest point doesn't tell us anything. EPA can handle
+                    // this though.
+                    convex_collision(
+                        MeshRef {
+                            body: a,
+                            mesh: mesh_a,
+                        },
+                        SphereRef {
+                            body: b,
+                            radius: *radius_b,
+                        },
+                        initial_direction(a, b),
+                    )
                 } else {
                     let depth = radius_b - displacement.magnitude();
                     if depth > 0.0 {
                         Some(CollisionManifold {
                             depth,
                             normal: -displacement.normalize(),
-                            contacts: vec![closest_point],
+                            contacts: vec![Contact::new(
+                                closest_point,
+                                ContactId::Single,
+                            )],
                         })
                     } else {
                         None
@@ -215,15 +436,20 @@ impl CollisionDetection {
             }
             (Collider::Sphere { .. }, Collider::HalfSpace { .. }) => {
                 // Just call this again with the arguments swapped
-                let mut manifold = self.detect_collisions((key.1, key.0), b, a);
+                let mut manifold =
+                    self.detect_child_collisions(swap(key), b, a);
                 if let Some(m) = &mut manifold {
                     m.normal *= -1.0;
                 }
                 manifold
             }
-            (Collider::Sphere { .. }, Collider::Mesh { .. }) => {
+            (
+                Collider::Sphere { .. },
+                Collider::Mesh { .. } | Collider::Hyperbox { .. },
+            ) => {
                 // Just call this again with the arguments swapped
-                let mut manifold = self.detect_collisions((key.1, key.0), b, a);
+                let mut manifold =
+                    self.detect_child_collisions(swap(key), b, a);
                 if let Some(m) = &mut manifold {
                     m.normal *= -1.0;
                 }
@@ -240,19 +466,35 @@ impl CollisionDetection {
                     Some(CollisionManifold {
                         normal,
                         depth,
-                        contacts: vec![a.pos + depth * normal],
+                        contacts: vec![Contact::new(
+                            a.pos + depth * normal,
+                            ContactId::Single,
+                        )],
                     })
                 } else {
                     None
                 }
             }
-            _ => None,
+            (Collider::HalfSpace { .. }, Collider::HalfSpace { .. }) => None,
+            (Collider::HalfSpace { normal }, _) => {
+                half_space_collision(a.pos, *normal, b)
+            }
+            (_, Collider::HalfSpace { .. }) => {
+                // Just call this again with the arguments swapped
+                let mut manifold =
+                    self.detect_child_collisions(swap(key), b, a);
+                if let Some(m) = &mut manifold {
+                    m.normal *= -1.0;
+                }
+                manifold
+            }
+            _ => convex_primitive_collision(a, b),
         }
     }
 
     fn mesh_sat(
-        &mut self,
-        key: (ObjectKey, ObjectKey),
+        &self,
+        key: Option<(ColliderKey, ColliderKey)>,
         a: MeshRef,
         b: MeshRef,
     ) -> Option<ContactData> {
@@ -263,18 +505,20 @@ impl CollisionDetection {
             return None;
         }
 
-        let mut min_penetration = std::f32::INFINITY;
+        let mut min_penetration = f32::INFINITY;
         let mut curr_contact = None;
 
         let mut edge_cells_cache = None;
 
-        if let Some(axis) = self.sat_cache.get(&key) {
-            let axis = *axis;
-            if !self.fast_check_axis(a, b, axis) {
-                return None;
+        if let Some(key) = key {
+            let cached = self.sat_cache.borrow_mut().get(&key).copied();
+            if let Some(axis) = cached {
+                if !self.fast_check_axis(a, b, axis) {
+                    return None;
+                }
+                // If we got here then the cache entry is no longer useful.
+                self.sat_cache.borrow_mut().pop(&key);
             }
-            // If we got here then the cache entry is no longer useful.
-            self.sat_cache.pop(&key);
         }
 
         macro_rules! axis_check {
@@ -294,7 +538,9 @@ impl CollisionDetection {
                         curr_contact = Some(contact);
                     }
                     AxisResult::NoIntersection { normal } => {
-                        self.sat_cache.put(key, normal);
+                        if let Some(key) = key {
+                            self.sat_cache.borrow_mut().put(key, normal);
+                        }
                         return None;
                     }
                     _ => (),
@@ -346,8 +592,8 @@ impl CollisionDetection {
     }
 
     fn axis_span(&self, a: MeshRef, normal: Vector4<f32>) -> (f32, f32) {
-        let mut min = std::f32::NEG_INFINITY;
-        let mut max = std::f32::INFINITY;
+        let mut min = f32::INFINITY;
+        let mut max = f32::NEG_INFINITY;
 
         for v in a.mesh.vertices.iter() {
             let d = a.body.body_pos_to_world(*v).dot(normal);
@@ -493,6 +739,8 @@ impl CollisionDetection {
                     penetration: dist_a - dist_b,
                     contact: ContactData::EdgeFace(EdgeFaceContact {
                         side,
+                        edge_idx,
+                        face_idx,
                         k: a.body.body_pos_to_world(v0),
                         t: a.body.body_vec_to_world(u),
                         s: b.body
@@ -569,22 +817,25 @@ fn resolve_vertex_cell_contact(
     b: MeshRef,
     contact: VertexCellContact,
 ) -> CollisionManifold {
-    if !contact.side {
-        // just swap the meshes around in the call
-        let mut result = resolve_vertex_cell_contact(
-            b,
-            a,
-            VertexCellContact {
-                side: true,
-                ..contact
-            },
-        );
+    let side = contact.side;
+    if !side {
+        // just swap the meshes around
+        let mut result = clip_vertex_cell_contact(b, a, contact);
         // flip the normal as the collision resolution code expects the normal
         // to be oriented in a certain way
         result.normal *= -1.0;
         return result;
     }
+    clip_vertex_cell_contact(a, b, contact)
+}
 
+// Assumes that the reference cell is on a and the incident vertex on b,
+// regardless of contact.side.
+fn clip_vertex_cell_contact(
+    a: MeshRef,
+    b: MeshRef,
+    contact: VertexCellContact,
+) -> CollisionManifold {
     let reference_cell = &a.mesh.cells[contact.cell_idx];
 
     // Need to determine incident cell - find the cell with the least dot
@@ -629,16 +880,24 @@ fn resolve_vertex_cell_contact(
     // keep points that are below the reference plane
     let mut max_depth = 0f32;
     let contacts = clipper
-        .to_vertices()
+        .to_vertices_with_ids()
         .into_iter()
-        .filter_map(|b_vec| {
+        .filter_map(|(clip_vertex, b_vec)| {
             let world_vec = b.body.body_pos_to_world(b_vec);
             let a_vec = a.body.world_pos_to_body(world_vec);
 
             let dist = a_vec.dot(reference_cell.normal);
             if dist < reference_dist {
                 max_depth = max_depth.max(reference_dist - dist);
-                Some(world_vec)
+                Some(Contact::new(
+                    world_vec,
+                    ContactId::VertexCell {
+                        side: contact.side,
+                        reference_cell: contact.cell_idx,
+                        incident_cell: incident_cell_idx,
+                        clip_vertex,
+                    },
+                ))
             } else {
                 None
             }
@@ -652,35 +911,29 @@ fn resolve_vertex_cell_contact(
     }
 }
 
-fn resolve_edge_face_contact(
-    a: MeshRef,
-    b: MeshRef,
-    contact: EdgeFaceContact,
-) -> CollisionManifold {
-    if contact.side {
-        // just swap the meshes around in the call
-        let mut result = resolve_edge_face_contact(
-            b,
-            a,
-            EdgeFaceContact {
-                side: false,
-                ..contact
-            },
-        );
+fn resolve_edge_face_contact(contact: EdgeFaceContact) -> CollisionManifold {
+    let side = contact.side;
+    let mut result = closest_edge_face_points(contact);
+    if side {
+        // the contact data is already in world space, so we just need to
         // flip the normal as the collision resolution code expects the normal
         // to be oriented in a certain way
         result.normal *= -1.0;
-        return result;
     }
+    result
+}
 
+fn closest_edge_face_points(contact: EdgeFaceContact) -> CollisionManifold {
     let EdgeFaceContact {
+        side,
+        edge_idx,
+        face_idx,
         k,
         t,
         s,
         u,
         v,
         normal,
-        ..
     } = contact;
 
     // Now we gotta solve an equation in three variables to get the closest point
@@ -714,16 +967,114 @@ fn resolve_edge_face_contact(
     CollisionManifold {
         normal,
         depth,
-        contacts: vec![(p1 + p2) / 2.0],
+        contacts: vec![Contact::new(
+            (p1 + p2) / 2.0,
+            ContactId::EdgeFace {
+                side,
+                edge: edge_idx,
+                face: face_idx,
+            },
+        )],
+    }
+}
+
+// The support points of a convex collider along the inward normal of the plane
+// which are below it.
+fn half_space_collision(
+    plane_pos: Vector4<f32>,
+    normal: Vector4<f32>,
+    body: ColliderRef,
+) -> Option<CollisionManifold> {
+    let plane_distance = plane_pos.dot(normal);
+    let mut max_depth = 0.0;
+    let contacts: Vec<_> = body
+        .collider
+        .support_feature(body.world_vec_to_body(-normal))
+        .into_iter()
+        .filter_map(|(id, point)| {
+            let pos = body.body_pos_to_world(point);
+            let depth = plane_distance - pos.dot(normal);
+            if depth > 0.0 {
+                max_depth = f32::max(max_depth, depth);
+                Some(Contact::new(pos, ContactId::Vertex(id as usize)))
+            } else {
+                None
+            }
+        })
+        .collect();
+
+    if !contacts.is_empty() {
+        Some(CollisionManifold {
+            normal,
+            depth: max_depth,
+            contacts,
+        })
+    } else {
+        None
+    }
+}
+
+// GJK/EPA finds the normal and depth, but only a single contact point, which
+// isn't enough for flat sides to rest on each other. So the contacts are
+// replaced with the points of each collider's support feature along the
+// normal which are inside the other.
+fn convex_primitive_collision(
+    a: ColliderRef,
+    b: ColliderRef,
+) -> Option<CollisionManifold> {
+    let bounding_distance =
+        a.collider.bounding_radius() + b.collider.bounding_radius();
+    if (a.pos - b.pos).magnitude() > bounding_distance {
+        return None;
+    }
+
+    let mut manifold = convex_collision(a, b, initial_direction(a, b))?;
+
+    let mut contacts = Vec::new();
+    let sides = [
+        (b, a, -manifold.normal, true),
+        (a, b, manifold.normal, false),
+    ];
+    for &(body, other, direction, side) in sides.iter() {
+        let feature = body
+            .collider
+            .support_feature(body.world_vec_to_body(direction));
+        for (id, point) in feature {
+            let pos = body.body_pos_to_world(point);
+            if other.collider.contains_local(other.world_pos_to_body(pos)) {
+                contacts.push(Contact::new(
+                    pos,
+                    ContactId::Feature { side, feature: id },
+                ));
+            }
+        }
+    }
+    if !contacts.is_empty() {
+        manifold.contacts = contacts;
+    }
+
+    Some(manifold)
+}
+
+fn swap<T>(key: Option<(T, T)>) -> Option<(T, T)> {
+    key.map(|(a, b)| (b, a))
+}
+
+fn initial_direction(a: ColliderRef, b: ColliderRef) -> Vector4<f32> {
+    let direction = b.pos - a.pos;
+    if direction.magnitude2() > EPSILON {
+        direction
+    } else {
+        Vector4::unit_x()
     }
 }
 
 fn minkowski_edge_face_check(
-    edge_cells: &Vec<Vector4<f32>>,
+    edge_cells: &[Vector4<f32>],
     face_cells: (Vector4<f32>, Vector4<f32>),
 ) -> bool {
     // grab the normal corresponding to the great sphere the edge lies in
-    let normal = if let &[a, b, c, ..] = &edge_cells[..] {
+    let normal = if let &[a, b, c, ..] = edge_cells {
         crate::alg::triple_cross_product(a, b, c)
     } else {
         return false;
@@ -780,9 +1131,64 @@ fn minkowski_edge_face_check(
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::alg::Bivec4;
-    use crate::physics::Material;
-    use crate::physics::Velocity;
+    use crate::physics::Inertia;
+    use slotmap::Key;
+
+    #[test]
+    fn uncached_detection_leaves_the_sat_cache_alone() {
+        let tesseract = |x: f32| {
+            let mesh = Mesh::from_schlafli_symbol(&[4, 3, 3]);
+            let pos = Vector4::unit_x() * x;
+            Body::new(Collider::Mesh { mesh }, 1.0, Inertia::zero(), pos)
+        };
+        let key = (BodyKey::null(), BodyKey::null());
+        let a = tesseract(0.0);
+        let mut b = tesseract(1.5);
+        let collision = CollisionDetection::new();
+
+        assert!(collision.detect_all_collisions_uncached(&a, &b).is_empty());
+        assert_eq!(collision.sat_cache.borrow().len(), 0);
+        assert!(collision.detect_all_collisions(key, &a, &b).is_empty());
+        assert_eq!(collision.sat_cache.borrow().len(), 1);
+
+        // checking somewhere else, like a sweep does, keeps the axis for
+        // where the bodies really are
+        b.pos.x = 0.9;
+        assert!(!collision.detect_all_collisions_uncached(&a, &b).is_empty());
+        assert_eq!(collision.sat_cache.borrow().len(), 1);
+    }
+
+    #[test]
+    fn cached_axis_rejects_separated_meshes() {
+        fn mesh_ref(body: &Body) -> MeshRef<'_> {
+            match &body.collider {
+                Collider::Mesh { mesh } => MeshRef {
+                    body: ColliderRef::new(body),
+                    mesh,
+                },
+                _ => unreachable!(),
+            }
+        }
+        let tesseract = |x: f32| {
+            let mesh = Mesh::from_schlafli_symbol(&[4, 3, 3]);
+            let pos = Vector4::unit_x() * x;
+            Body::new(Collider::Mesh { mesh }, 1.0, Inertia::zero(), pos)
+        };
+        let a = tesseract(0.0);
+        let mut b = tesseract(1.5);
+        let collision = CollisionDetection::new();
+        let axis = Vector4::unit_x();
+
+        // The spans have to start out empty and grow to fit the vertices.
+        // Starting them out infinite made every cached axis look
+        // overlapped, so the cache never let a pair be skipped.
+        let (min, max) = collision.axis_span(mesh_ref(&b), axis);
+        assert!((min - 1.0).abs() < 1e-4 && (max - 2.0).abs() < 1e-4);
+        assert!(!collision.fast_check_axis(mesh_ref(&a), mesh_ref(&b), axis));
+
+        b.pos.x = 0.9;
+        assert!(collision.fast_check_axis(mesh_ref(&a), mesh_ref(&b), axis));
+    }
 
     #[test]
     pub fn edge_edge_separation() {
@@ -791,7 +1197,7 @@ mod tests {
 
         let tess_a = Body {
             mass: 1.0,
-            moment_inertia_scalar: 1.0 / 6.0,
+            inertia: Inertia::isotropic(1.0 / 6.0),
             material: Material { restitution: 0.4 },
             stationary: false,
             pos: Vector4::new(0.0, 0.0, 0.0, 0.0),
@@ -1,9 +1,23 @@
-use super::{Body, CollisionManifold};
+use super::{Body, CollisionManifold, ContactId, WorldSettings};
 use crate::alg::Vec4;
-use cgmath::{InnerSpace, Vector3, Vector4};
+use cgmath::{InnerSpace, Vector4, Zero};
+
+// The impulses accumulated at a contact by the end of a step, kept around so
+// that the next step can start solving from them instead of from zero.
+#[derive(Debug, Clone, Copy)]
+pub struct ContactImpulse {
+    pub id: ContactId,
+    pub children: (usize, usize),
+    pub normal: f32,
+    // Stored as a vector rather than per tangent, as the tangent basis can
+    // change from step to step.
+    pub tangent: Vector4<f32>,
+}
 
 #[derive(Debug)]
 pub struct ContactState {
+    id: ContactId,
+    children: (usize, usize),
     contact: Vector4<f32>,
     bias: f32,
     normal_mass: f32,
@@ -16,7 +30,8 @@ pub struct CollisionConstraint {
     normal: Vector4<f32>,
     tangents: [Vector4<f32>; 3],
     contacts: Vec<ContactState>,
-    mu: f32,
+    static_friction: f32,
+    dynamic_friction: f32,
 }
 
 impl CollisionConstraint {
@@ -26,6 +41,8 @@ impl CollisionConstraint {
         mass_adjustment_a: f32,
         b: &Body,
         mass_adjustment_b: f32,
+        settings: &WorldSettings,
+        dt: f32,
     ) -> Self {
         let CollisionManifold {
             normal,
@@ -33,22 +50,24 @@ impl CollisionConstraint {
             contacts,
         } = manifold;
 
-        let e = a.material.restitution.min(b.material.restitution);
-        // TODO: move this into the Material struct
-        let mu = 0.4;
+        let e = a.material.restitution_with(&b.material);
+        let static_friction = a.material.static_friction_with(&b.material);
+        let dynamic_friction = a.material.dynamic_friction_with(&b.material);
 
         let tangents = crate::util::orthonormal_basis(normal);
 
         let contacts: Vec<_> = contacts
             .into_iter()
             .map(|contact| {
+                let id = contact.id;
+                let children = contact.children;
+                let contact = contact.point;
                 let rel_vel = b.vel_at(contact) - a.vel_at(contact);
                 let rel_vel_normal = rel_vel.dot(normal);
 
-                let slop = 0.01;
-                let baumgarte = 0.2;
-                let bias = -baumgarte * 60.0 * (slop - depth).min(0.0)
-                    + if rel_vel_normal < -1.0 {
+                let bias = -settings.baumgarte / dt
+                    * (settings.slop - depth).min(0.0)
+                    + if rel_vel_normal < -settings.restitution_threshold {
                         -e * rel_vel_normal
                     } else {
                         0.0
@@ -108,6 +127,8 @@ impl CollisionConstraint {
                 }
 
                 ContactState {
+                    id,
+                    children,
                     contact,
                     bias,
                     normal_mass,
@@ -122,13 +143,64 @@ impl CollisionConstraint {
             normal,
             tangents,
             contacts,
-            mu,
+            static_friction,
+            dynamic_friction,
+        }
+    }
+
+    // Applies the impulses from the previous step to any contacts which are
+    // still around.
+    pub fn warm_start(
+        &mut self,
+        previous: &[ContactImpulse],
+        a: &mut Body,
+        b: &mut Body,
+    ) {
+        for contact_state in self.contacts.iter_mut() {
+            let cached = match previous.iter().find(|i| {
+                i.id == contact_state.id && i.children == contact_state.children
+            }) {
+                Some(cached) => cached,
+                None => continue,
+            };
+
+            contact_state.normal_impulse = cached.normal;
+            let mut impulse = self.normal * cached.normal;
+            for i in 0..3 {
+                let tangent_impulse = cached.tangent.dot(self.tangents[i]);
+                contact_state.tangent_impulse[i] = tangent_impulse;
+                impulse += self.tangents[i] * tangent_impulse;
+            }
+
+            a.resolve_impulse(-impulse, contact_state.contact);
+            b.resolve_impulse(impulse, contact_state.contact);
         }
     }
 
+    pub fn impulses(&self) -> Vec<ContactImpulse> {
+        self.contacts
+            .iter()
+            .map(|contact_state| {
+                let mut tangent = Vector4::zero();
+                for i in 0..3 {
+                    tangent +=
+                        self.tangents[i] * contact_state.tangent_impulse[i];
+                }
+                ContactImpulse {
+                    id: contact_state.id,
+                    children: contact_state.children,
+                    normal: contact_state.normal_impulse,
+                    tangent,
+                }
+            })
+            .collect()
+    }
+
     pub fn solve(&mut self, a: &mut Body, b: &mut Body) {
         for contact_state in self.contacts.iter_mut() {
             let ContactState {
+                id: _,
+                children: _,
                 contact,
                 bias,
                 normal_mass,
@@ -146,12 +218,15 @@ impl CollisionConstraint {
                 new_impulses[i] = tangent_impulse[i] + lambda;
             }
 
-            // clamp the total magnitude
-            let max_impulse = (self.mu * *normal_impulse).abs();
+            // clamp the total magnitude. If static friction can't hold the
+            // contact in place then it slides, and dynamic friction applies
+            // instead.
             let mut impulse_mag2 = 0f32;
             new_impulses.iter().for_each(|i| impulse_mag2 += i * i);
             let impulse_mag = impulse_mag2.sqrt();
-            if impulse_mag > max_impulse {
+            if impulse_mag > (self.static_friction * *normal_impulse).abs() {
+                let max_impulse =
+                    (self.dynamic_friction * *normal_impulse).abs();
                 let factor = max_impulse / impulse_mag;
                 new_impulses.iter_mut().for_each(|i| *i *= factor);
             }
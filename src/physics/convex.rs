@@ -0,0 +1,1316 @@
+// Geometric queries on the bounded convex colliders, i.e. everything but
+// half-spaces. Bodies are assumed to be centred on the origin of their local
+// frame, so all points and directions here are in body space.
+
+use super::{Body, ChildCollider, Collider, Inertia, Support};
+use crate::alg::Rotor4;
+use crate::mesh::{MassProperties, Mesh};
+use crate::util::{NotNaN, EPSILON};
+
+use cgmath::{
+    InnerSpace, Matrix, Matrix4, SquareMatrix, Vector2, Vector3, Vector4, Zero,
+};
+use std::cell::RefCell;
+use std::error::Error;
+use std::f32::consts::PI;
+use std::fmt;
+use std::ops::Deref;
+use std::rc::Rc;
+
+// A flat or round part of a shape counts as facing a direction when the
+// direction is within this (sine of an) angle of it. This way a shape resting
+// on one of its sides gets a contact at each corner instead of just one.
+const FEATURE_TOLERANCE: f32 = 0.05;
+// How many points to sample around the rim of a flat round face.
+const RIM_SAMPLES: usize = 8;
+// Points this far outside a shape still count as being inside it.
+const CONTAINS_TOLERANCE: f32 = 1e-4;
+
+// How many hyperbox meshes to keep around. Most scenes only have a few
+// different sizes of box.
+const HYPERBOX_CACHE_SIZE: usize = 64;
+
+thread_local! {
+    // Hyperboxes collide with other polytopes as meshes, so these are built
+    // once for each size of box rather than for every pair on every step.
+    static HYPERBOX_MESHES: RefCell<lru::LruCache<[u32; 4], Rc<Mesh>>> =
+        RefCell::new(lru::LruCache::new(HYPERBOX_CACHE_SIZE));
+}
+
+fn hyperbox_mesh(half_extents: Vector4<f32>) -> Rc<Mesh> {
+    let key = [
+        half_extents.x.to_bits(),
+        half_extents.y.to_bits(),
+        half_extents.z.to_bits(),
+        half_extents.w.to_bits(),
+    ];
+    HYPERBOX_MESHES.with(|cache| {
+        let mut cache = cache.borrow_mut();
+        if let Some(mesh) = cache.get(&key) {
+            return mesh.clone();
+        }
+        let mesh = Rc::new(Mesh::hyperbox(half_extents));
+        cache.put(key, mesh.clone());
+        mesh
+    })
+}
+
+// The mesh of a collider which is a polytope, which is either the collider's
+// own or one shared between colliders of the same shape.
+pub enum PolytopeMesh<'a> {
+    Borrowed(&'a Mesh),
+    Shared(Rc<Mesh>),
+}
+
+impl Deref for PolytopeMesh<'_> {
+    type Target = Mesh;
+
+    fn deref(&self) -> &Mesh {
+        match self {
+            PolytopeMesh::Borrowed(mesh) => mesh,
+            PolytopeMesh::Shared(mesh) => mesh,
+        }
+    }
+}
+
+// Why a list of children can't be made into a compound collider.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CompoundError {
+    // A compound with nothing in it has no shape or bounds at all.
+    NoChildren,
+    // Compounds are bounded by their children's support points, which
+    // half-spaces don't have. The index is of the half-space among the
+    // children of whichever compound holds it.
+    HalfSpaceChild(usize),
+}
+
+impl fmt::Display for CompoundError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            CompoundError::NoChildren => {
+                write!(f, "compound colliders need at least one child")
+            }
+            CompoundError::HalfSpaceChild(child) => write!(
+                f,
+                "child {} of a compound collider is a half-space",
+                child
+            ),
+        }
+    }
+}
+
+impl Error for CompoundError {}
+
+// A collider along with where it is in the world. For the children of a
+// compound collider, this is the body's pose combined with the child's
+// placement, so that they can be treated like colliders of their own without
+// copying them out.
+#[derive(Copy, Clone)]
+pub struct ColliderRef<'a> {
+    pub pos: Vector4<f32>,
+    pub rotation: Rotor4,
+    pub collider: &'a Collider,
+}
+
+impl<'a> ColliderRef<'a> {
+    pub fn new(body: &'a Body) -> Self {
+        Self {
+            pos: body.pos,
+            rotation: body.rotation,
+            collider: &body.collider,
+        }
+    }
+
+    // The colliders that make this one up: the children of a compound, with
+    // children which are compounds themselves flattened out, or just this
+    // collider for anything else.
+    pub fn leaves(self) -> Vec<ColliderRef<'a>> {
+        let children = match self.collider {
+            Collider::Compound { children } => children,
+            _ => return vec![self],
+        };
+
+        let mut leaves = Vec::new();
+        for child in children.iter() {
+            let placed = ColliderRef {
+                pos: self.body_pos_to_world(child.offset),
+                rotation: self.rotation * child.rotation,
+                collider: &child.collider,
+            };
+            leaves.extend(placed.leaves());
+        }
+        leaves
+    }
+
+    pub fn body_vec_to_world(&self, v: Vector4<f32>) -> Vector4<f32> {
+        self.rotation.rotate(&v.into()).into()
+    }
+
+    pub fn world_vec_to_body(&self, v: Vector4<f32>) -> Vector4<f32> {
+        self.rotation.reverse().rotate(&v.into()).into()
+    }
+
+    pub fn body_pos_to_world(&self, v: Vector4<f32>) -> Vector4<f32> {
+        self.body_vec_to_world(v) + self.pos
+    }
+
+    pub fn world_pos_to_body(&self, v: Vector4<f32>) -> Vector4<f32> {
+        self.world_vec_to_body(v - self.pos)
+    }
+}
+
+impl<'a> Support for ColliderRef<'a> {
+    fn support(&self, direction: Vector4<f32>) -> Vector4<f32> {
+        let body_d = self.world_vec_to_body(direction);
+        let support = self
+            .collider
+            .local_support(body_d)
+            .expect("half-spaces don't have a support function");
+        self.body_pos_to_world(support)
+    }
+}
+
+fn safe_normalize2(v: Vector2<f32>) -> Vector2<f32> {
+    if v.magnitude2() > EPSILON * EPSILON {
+        v.normalize()
+    } else {
+        Vector2::unit_x()
+    }
+}
+
+fn safe_normalize3(v: Vector3<f32>) -> Vector3<f32> {
+    if v.magnitude2() > EPSILON * EPSILON {
+        v.normalize()
+    } else {
+        Vector3::unit_x()
+    }
+}
+
+fn safe_normalize4(v: Vector4<f32>) -> Vector4<f32> {
+    if v.magnitude2() > EPSILON * EPSILON {
+        v.normalize()
+    } else {
+        Vector4::unit_x()
+    }
+}
+
+// The support features of the factors that the primitives are built out of.
+// Each point comes with an id which is unique within the factor, and `d` is a
+// component of a unit vector.
+
+fn segment_feature(half_height: f32, d: f32) -> Vec<(u32, f32)> {
+    if d.abs() <= FEATURE_TOLERANCE {
+        vec![(0, -half_height), (1, half_height)]
+    } else if d > 0.0 {
+        vec![(1, half_height)]
+    } else {
+        vec![(0, -half_height)]
+    }
+}
+const SEGMENT_IDS: u32 = 2;
+
+fn disk_feature(radius: f32, d: Vector2<f32>) -> Vec<(u32, Vector2<f32>)> {
+    if d.magnitude() <= FEATURE_TOLERANCE {
+        (0..RIM_SAMPLES)
+            .map(|i| {
+                let angle = 2.0 * PI * i as f32 / RIM_SAMPLES as f32;
+                (i as u32, Vector2::new(angle.cos(), angle.sin()) * radius)
+            })
+            .collect()
+    } else {
+        vec![(RIM_SAMPLES as u32, d.normalize() * radius)]
+    }
+}
+const DISK_IDS: u32 = RIM_SAMPLES as u32 + 1;
+
+fn ball3_feature(radius: f32, d: Vector3<f32>) -> Vec<(u32, Vector3<f32>)> {
+    if d.magnitude() <= FEATURE_TOLERANCE {
+        // the six axis directions and the eight diagonals
+        let mut points = Vec::with_capacity(14);
+        for axis in 0..3 {
+            for &sign in [-1.0, 1.0].iter() {
+                let mut v = Vector3::zero();
+                v[axis] = sign * radius;
+                points.push(v);
+            }
+        }
+        for i in 0..8 {
+            let sign = |bit: usize| if i & bit == 0 { -1.0 } else { 1.0 };
+            points.push(
+                Vector3::new(sign(1), sign(2), sign(4)) * radius / 3f32.sqrt(),
+            );
+        }
+        points
+            .into_iter()
+            .enumerate()
+            .map(|(i, v)| (i as u32, v))
+            .collect()
+    } else {
+        vec![(14, d.normalize() * radius)]
+    }
+}
+
+fn slab_interval(start: f32, dir: f32, half_height: f32) -> Option<(f32, f32)> {
+    if dir.abs() < EPSILON {
+        if start.abs() <= half_height {
+            Some((f32::NEG_INFINITY, f32::INFINITY))
+        } else {
+            None
+        }
+    } else {
+        let t0 = (-half_height - start) / dir;
+        let t1 = (half_height - start) / dir;
+        Some((t0.min(t1), t0.max(t1)))
+    }
+}
+
+// The interval along a ray (given as projections onto some subspace) which
+// lies within a radius of the origin.
+fn round_interval(
+    start_dot_start: f32,
+    start_dot_dir: f32,
+    dir_dot_dir: f32,
+    radius: f32,
+) -> Option<(f32, f32)> {
+    let c = start_dot_start - radius * radius;
+    if dir_dot_dir < EPSILON {
+        return if c <= 0.0 {
+            Some((f32::NEG_INFINITY, f32::INFINITY))
+        } else {
+            None
+        };
+    }
+
+    let b = 2.0 * start_dot_dir;
+    let discriminant = b * b - 4.0 * dir_dot_dir * c;
+    if discriminant < 0.0 {
+        return None;
+    }
+    let root = discriminant.sqrt();
+    Some((
+        (-b - root) / (2.0 * dir_dot_dir),
+        (-b + root) / (2.0 * dir_dot_dir),
+    ))
+}
+
+fn intersect_intervals(intervals: &[Option<(f32, f32)>]) -> Option<(f32, f32)> {
+    let mut result = (f32::NEG_INFINITY, f32::INFINITY);
+    for interval in intervals.iter() {
+        let (t0, t1) = (*interval)?;
+        result = (result.0.max(t0), result.1.min(t1));
+    }
+    if result.0 <= result.1 {
+        Some(result)
+    } else {
+        None
+    }
+}
+
+fn xzw(v: Vector4<f32>) -> Vector3<f32> {
+    Vector3::new(v.x, v.z, v.w)
+}
+
+fn child_to_parent(child: &ChildCollider, p: Vector4<f32>) -> Vector4<f32> {
+    let rotated: Vector4<f32> = child.rotation.rotate(&p.into()).into();
+    rotated + child.offset
+}
+
+fn parent_to_child(child: &ChildCollider, p: Vector4<f32>) -> Vector4<f32> {
+    parent_to_child_vec(child, p - child.offset)
+}
+
+fn parent_to_child_vec(child: &ChildCollider, v: Vector4<f32>) -> Vector4<f32> {
+    child.rotation.reverse().rotate(&v.into()).into()
+}
+
+// Adds up the mass properties of the children, moving each one's second
+// moment to the centroid of the whole compound with the parallel axis theorem.
+fn compound_mass_properties(
+    children: &[ChildCollider],
+    density: f32,
+) -> Option<MassProperties> {
+    let mut parts = Vec::with_capacity(children.len());
+    for child in children.iter() {
+        let props = child.collider.mass_properties(density)?;
+        let rotation = child.rotation.to_matrix();
+        parts.push((
+            props,
+            child_to_parent(child, props.centroid),
+            rotation * props.second_moment * rotation.transpose(),
+        ));
+    }
+
+    let hypervolume = parts.iter().map(|(props, ..)| props.hypervolume).sum();
+    let mass: f32 = parts.iter().map(|(props, ..)| props.mass).sum();
+    let centroid = if mass > 0.0 {
+        parts
+            .iter()
+            .fold(Vector4::zero(), |acc, (props, centroid, _)| {
+                acc + centroid * props.mass
+            })
+            / mass
+    } else {
+        Vector4::zero()
+    };
+
+    let mut second_moment = Matrix4::zero();
+    for (props, child_centroid, child_second_moment) in parts.iter() {
+        let d = child_centroid - centroid;
+        let shift = Matrix4::from_cols(d * d.x, d * d.y, d * d.z, d * d.w);
+        second_moment += child_second_moment + shift * props.mass;
+    }
+
+    Some(MassProperties {
+        hypervolume,
+        mass,
+        centroid,
+        second_moment,
+        inertia: Inertia::from_second_moment(second_moment),
+    })
+}
+
+fn diagonal_mass_properties(
+    hypervolume: f32,
+    density: f32,
+    // the second moment per unit mass along each axis
+    second_moment: Vector4<f32>,
+) -> MassProperties {
+    let mass = hypervolume * density;
+    let second_moment = Matrix4::from_diagonal(second_moment * mass);
+    MassProperties {
+        hypervolume,
+        mass,
+        centroid: Vector4::zero(),
+        second_moment,
+        inertia: Inertia::from_second_moment(second_moment),
+    }
+}
+
+impl Collider {
+    // Builds a compound collider, checking that it has a support point in
+    // every direction, which anything with bounds needs.
+    pub fn compound(
+        children: Vec<ChildCollider>,
+    ) -> Result<Collider, CompoundError> {
+        fn check(children: &[ChildCollider]) -> Result<(), CompoundError> {
+            if children.is_empty() {
+                return Err(CompoundError::NoChildren);
+            }
+            for (i, child) in children.iter().enumerate() {
+                match &child.collider {
+                    Collider::HalfSpace { .. } => {
+                        return Err(CompoundError::HalfSpaceChild(i))
+                    }
+                    Collider::Compound { children } => check(children)?,
+                    _ => (),
+                }
+            }
+            Ok(())
+        }
+
+        check(&children)?;
+        Ok(Collider::Compound { children })
+    }
+
+    // The radius of a hypersphere about the body's position which contains
+    // the whole collider.
+    pub fn bounding_radius(&self) -> f32 {
+        match self {
+            Collider::HalfSpace { .. } => f32::INFINITY,
+            Collider::Mesh { mesh } => mesh.radius,
+            Collider::Sphere { radius } => *radius,
+            Collider::Hyperbox { half_extents } => half_extents.magnitude(),
+            Collider::Spherinder {
+                radius,
+                half_height,
+            } => radius.hypot(*half_height),
+            Collider::Cubinder {
+                radius,
+                half_height,
+            } => (radius.powi(2) + 2.0 * half_height.powi(2)).sqrt(),
+            Collider::Capsule {
+                radius,
+                half_height,
+            } => radius + half_height,
+            Collider::Duocylinder {
+                radius_xy,
+                radius_zw,
+            } => radius_xy.hypot(*radius_zw),
+            Collider::Compound { children } => children
+                .iter()
+                .map(|child| {
+                    child.offset.magnitude() + child.collider.bounding_radius()
+                })
+                .fold(0.0, f32::max),
+        }
+    }
+
+    // The radius of a hypersphere about the body's position which fits
+    // inside the collider. For compounds, this is the smallest such radius of
+    // any child about its own position.
+    pub fn inner_radius(&self) -> f32 {
+        match self {
+            Collider::HalfSpace { .. } => f32::INFINITY,
+            Collider::Mesh { mesh } => mesh
+                .cells
+                .iter()
+                .map(|cell| {
+                    let v0 = mesh.vertices[mesh.edges
+                        [mesh.faces[cell.faces[0]].edges[0]]
+                        .hd_vertex];
+                    v0.dot(cell.normal)
+                })
+                .fold(f32::INFINITY, f32::min)
+                .max(0.0),
+            Collider::Sphere { radius } => *radius,
+            Collider::Hyperbox { half_extents } => half_extents
+                .x
+                .min(half_extents.y)
+                .min(half_extents.z)
+                .min(half_extents.w),
+            Collider::Spherinder {
+                radius,
+                half_height,
+            }
+            | Collider::Cubinder {
+                radius,
+                half_height,
+            } => radius.min(*half_height),
+            Collider::Capsule { radius, .. } => *radius,
+            Collider::Duocylinder {
+                radius_xy,
+                radius_zw,
+            } => radius_xy.min(*radius_zw),
+            Collider::Compound { children } => children
+                .iter()
+                .map(|child| child.collider.inner_radius())
+                .fold(f32::INFINITY, f32::min),
+        }
+    }
+
+    // How many children the collider gets split into for collision
+    // detection, once any nested compounds are flattened out.
+    pub fn leaf_count(&self) -> usize {
+        match self {
+            Collider::Compound { children } => children
+                .iter()
+                .map(|child| child.collider.leaf_count())
+                .sum(),
+            _ => 1,
+        }
+    }
+
+    // The collider as a polytope, if it is one.
+    pub fn polytope(&self) -> Option<PolytopeMesh<'_>> {
+        match self {
+            Collider::Mesh { mesh } => Some(PolytopeMesh::Borrowed(mesh)),
+            Collider::Hyperbox { half_extents } => {
+                Some(PolytopeMesh::Shared(hyperbox_mesh(*half_extents)))
+            }
+            _ => None,
+        }
+    }
+
+    // The furthest point of the collider along the given direction, or None
+    // for half-spaces which go on forever.
+    pub fn local_support(&self, d: Vector4<f32>) -> Option<Vector4<f32>> {
+        let sign = |x: f32| if x < 0.0 { -1.0 } else { 1.0 };
+        Some(match self {
+            Collider::HalfSpace { .. } => return None,
+            Collider::Mesh { mesh } => *mesh
+                .vertices
+                .iter()
+                .max_by_key(|v| NotNaN::new(v.dot(d)).unwrap())?,
+            Collider::Sphere { radius } => safe_normalize4(d) * *radius,
+            Collider::Hyperbox { half_extents } => Vector4::new(
+                sign(d.x) * half_extents.x,
+                sign(d.y) * half_extents.y,
+                sign(d.z) * half_extents.z,
+                sign(d.w) * half_extents.w,
+            ),
+            Collider::Spherinder {
+                radius,
+                half_height,
+            } => {
+                let ball = safe_normalize3(xzw(d)) * *radius;
+                Vector4::new(ball.x, sign(d.y) * half_height, ball.y, ball.z)
+            }
+            Collider::Cubinder {
+                radius,
+                half_height,
+            } => {
+                let disk = safe_normalize2(Vector2::new(d.x, d.z)) * *radius;
+                Vector4::new(
+                    disk.x,
+                    sign(d.y) * half_height,
+                    disk.y,
+                    sign(d.w) * half_height,
+                )
+            }
+            Collider::Capsule {
+                radius,
+                half_height,
+            } => {
+                Vector4::unit_y() * sign(d.y) * *half_height
+                    + safe_normalize4(d) * *radius
+            }
+            Collider::Duocylinder {
+                radius_xy,
+                radius_zw,
+            } => {
+                let xy = safe_normalize2(Vector2::new(d.x, d.y)) * *radius_xy;
+                let zw = safe_normalize2(Vector2::new(d.z, d.w)) * *radius_zw;
+                Vector4::new(xy.x, xy.y, zw.x, zw.y)
+            }
+            // The support of the convex hull of the children.
+            Collider::Compound { children } => {
+                let mut supports = Vec::with_capacity(children.len());
+                for child in children.iter() {
+                    supports.push(child_to_parent(
+                        child,
+                        child
+                            .collider
+                            .local_support(parent_to_child_vec(child, d))?,
+                    ));
+                }
+                supports
+                    .into_iter()
+                    .max_by_key(|v| NotNaN::new(v.dot(d)).unwrap())?
+            }
+        })
+    }
+
+    // All the points of the collider which are (nearly) furthest along the
+    // given direction, e.g. the corners of a face facing that way. Each point
+    // comes with an id which stays the same for as long as that part of the
+    // collider is facing that way.
+    pub fn support_feature(&self, d: Vector4<f32>) -> Vec<(u32, Vector4<f32>)> {
+        let d = safe_normalize4(d);
+        match self {
+            Collider::HalfSpace { .. } => Vec::new(),
+            Collider::Mesh { mesh } => {
+                let max = mesh
+                    .vertices
+                    .iter()
+                    .map(|v| v.dot(d))
+                    .fold(f32::NEG_INFINITY, f32::max);
+                let threshold = max - FEATURE_TOLERANCE * mesh.radius;
+                mesh.vertices
+                    .iter()
+                    .enumerate()
+                    .filter(|(_, v)| v.dot(d) >= threshold)
+                    .map(|(i, v)| (i as u32, *v))
+                    .collect()
+            }
+            Collider::Sphere { radius } => vec![(0, d * *radius)],
+            Collider::Hyperbox { half_extents } => {
+                let mut points = vec![(0, Vector4::zero())];
+                for axis in 0..4 {
+                    let mut next = Vec::new();
+                    for (id, point) in points.iter() {
+                        for (segment_id, x) in
+                            segment_feature(half_extents[axis], d[axis])
+                        {
+                            let mut point = *point;
+                            point[axis] = x;
+                            next.push((id * SEGMENT_IDS + segment_id, point));
+                        }
+                    }
+                    points = next;
+                }
+                points
+            }
+            Collider::Spherinder {
+                radius,
+                half_height,
+            } => {
+                let mut points = Vec::new();
+                for (ball_id, ball) in ball3_feature(*radius, xzw(d)) {
+                    for (segment_id, y) in segment_feature(*half_height, d.y) {
+                        points.push((
+                            ball_id * SEGMENT_IDS + segment_id,
+                            Vector4::new(ball.x, y, ball.y, ball.z),
+                        ));
+                    }
+                }
+                points
+            }
+            Collider::Cubinder {
+                radius,
+                half_height,
+            } => {
+                let mut points = Vec::new();
+                for (disk_id, disk) in
+                    disk_feature(*radius, Vector2::new(d.x, d.z))
+                {
+                    for (y_id, y) in segment_feature(*half_height, d.y) {
+                        for (w_id, w) in segment_feature(*half_height, d.w) {
+                            points.push((
+                                (disk_id * SEGMENT_IDS + y_id) * SEGMENT_IDS
+                                    + w_id,
+                                Vector4::new(disk.x, y, disk.y, w),
+                            ));
+                        }
+                    }
+                }
+                points
+            }
+            Collider::Capsule {
+                radius,
+                half_height,
+            } => segment_feature(*half_height, d.y)
+                .into_iter()
+                .map(|(id, y)| (id, Vector4::unit_y() * y + d * *radius))
+                .collect(),
+            Collider::Duocylinder {
+                radius_xy,
+                radius_zw,
+            } => {
+                let mut points = Vec::new();
+                for (xy_id, xy) in
+                    disk_feature(*radius_xy, Vector2::new(d.x, d.y))
+                {
+                    for (zw_id, zw) in
+                        disk_feature(*radius_zw, Vector2::new(d.z, d.w))
+                    {
+                        points.push((
+                            xy_id * DISK_IDS + zw_id,
+                            Vector4::new(xy.x, xy.y, zw.x, zw.y),
+                        ));
+                    }
+                }
+                points
+            }
+            // Compounds are split into their children before they get here.
+            Collider::Compound { .. } => Vec::new(),
+        }
+    }
+
+    pub fn contains_local(&self, p: Vector4<f32>) -> bool {
+        let within =
+            |value: f32, limit: f32| value <= limit + CONTAINS_TOLERANCE;
+        match self {
+            Collider::HalfSpace { normal } => within(p.dot(*normal), 0.0),
+            Collider::Mesh { mesh } => mesh.cells.iter().all(|cell| {
+                let v0 = mesh.vertices
+                    [mesh.edges[mesh.faces[cell.faces[0]].edges[0]].hd_vertex];
+                within(p.dot(cell.normal), v0.dot(cell.normal))
+            }),
+            Collider::Sphere { radius } => within(p.magnitude(), *radius),
+            Collider::Hyperbox { half_extents } => {
+                (0..4).all(|i| within(p[i].abs(), half_extents[i]))
+            }
+            Collider::Spherinder {
+                radius,
+                half_height,
+            } => {
+                within(xzw(p).magnitude(), *radius)
+                    && within(p.y.abs(), *half_height)
+            }
+            Collider::Cubinder {
+                radius,
+                half_height,
+            } => {
+                within(p.x.hypot(p.z), *radius)
+                    && within(p.y.abs(), *half_height)
+                    && within(p.w.abs(), *half_height)
+            }
+            Collider::Capsule {
+                radius,
+                half_height,
+            } => {
+                let y = p.y.clamp(-half_height, *half_height);
+                within((p - Vector4::unit_y() * y).magnitude(), *radius)
+            }
+            Collider::Duocylinder {
+                radius_xy,
+                radius_zw,
+            } => {
+                within(p.x.hypot(p.y), *radius_xy)
+                    && within(p.z.hypot(p.w), *radius_zw)
+            }
+            Collider::Compound { children } => children.iter().any(|child| {
+                child.collider.contains_local(parent_to_child(child, p))
+            }),
+        }
+    }
+
+    // The outward normal of the part of the surface nearest to a point, which
+    // should be on or near the surface.
+    pub fn local_normal(&self, p: Vector4<f32>) -> Vector4<f32> {
+        self.surface_distance(p).1
+    }
+
+    // Roughly how far a point is outside the collider (negative inside),
+    // along with the normal of the surface it's measured to. The primitives
+    // are intersections of simpler shapes, so the furthest one wins.
+    fn surface_distance(&self, p: Vector4<f32>) -> (f32, Vector4<f32>) {
+        let slab = |axis: usize, half_height: f32| {
+            let mut normal = Vector4::zero();
+            normal[axis] = p[axis].signum();
+            (p[axis].abs() - half_height, normal)
+        };
+        let round = |axes: &[usize], radius: f32| {
+            let mut offset = Vector4::zero();
+            for &i in axes.iter() {
+                offset[i] = p[i];
+            }
+            (offset.magnitude() - radius, safe_normalize4(offset))
+        };
+        let furthest = |candidates: &[(f32, Vector4<f32>)]| {
+            candidates
+                .iter()
+                .copied()
+                .max_by_key(|(distance, _)| NotNaN::new(*distance).unwrap())
+                .unwrap()
+        };
+
+        match self {
+            Collider::HalfSpace { normal } => (p.dot(*normal), *normal),
+            Collider::Mesh { mesh } => {
+                let cells: Vec<_> = mesh
+                    .cells
+                    .iter()
+                    .map(|cell| {
+                        let v0 = mesh.vertices[mesh.edges
+                            [mesh.faces[cell.faces[0]].edges[0]]
+                            .hd_vertex];
+                        ((p - v0).dot(cell.normal), cell.normal)
+                    })
+                    .collect();
+                furthest(&cells)
+            }
+            Collider::Sphere { radius } => round(&[0, 1, 2, 3], *radius),
+            Collider::Hyperbox { half_extents } => furthest(&[
+                slab(0, half_extents.x),
+                slab(1, half_extents.y),
+                slab(2, half_extents.z),
+                slab(3, half_extents.w),
+            ]),
+            Collider::Spherinder {
+                radius,
+                half_height,
+            } => furthest(&[round(&[0, 2, 3], *radius), slab(1, *half_height)]),
+            Collider::Cubinder {
+                radius,
+                half_height,
+            } => furthest(&[
+                round(&[0, 2], *radius),
+                slab(1, *half_height),
+                slab(3, *half_height),
+            ]),
+            Collider::Capsule {
+                radius,
+                half_height,
+            } => {
+                let y = p.y.clamp(-half_height, *half_height);
+                let offset = p - Vector4::unit_y() * y;
+                (offset.magnitude() - radius, safe_normalize4(offset))
+            }
+            Collider::Duocylinder {
+                radius_xy,
+                radius_zw,
+            } => furthest(&[
+                round(&[0, 1], *radius_xy),
+                round(&[2, 3], *radius_zw),
+            ]),
+            // Compounds are unions, so the nearest child wins instead.
+            Collider::Compound { children } => children
+                .iter()
+                .map(|child| {
+                    let (distance, normal) = child
+                        .collider
+                        .surface_distance(parent_to_child(child, p));
+                    let normal = child.rotation.rotate(&normal.into()).into();
+                    (distance, normal)
+                })
+                .min_by_key(|(distance, _)| NotNaN::new(*distance).unwrap())
+                .unwrap_or((f32::INFINITY, Vector4::unit_x())),
+        }
+    }
+
+    // The interval along a ray which lies within the collider, as multiples
+    // of `dir`. Only handles the analytic primitives, not meshes or
+    // half-spaces.
+    pub(crate) fn primitive_ray_intersect(
+        &self,
+        start: Vector4<f32>,
+        dir: Vector4<f32>,
+    ) -> Option<(f32, f32)> {
+        let slab = |axis: usize, half_height: f32| {
+            slab_interval(start[axis], dir[axis], half_height)
+        };
+        let round = |axes: &[usize], radius: f32| {
+            let (mut ss, mut sd, mut dd) = (0.0, 0.0, 0.0);
+            for &i in axes.iter() {
+                ss += start[i] * start[i];
+                sd += start[i] * dir[i];
+                dd += dir[i] * dir[i];
+            }
+            round_interval(ss, sd, dd, radius)
+        };
+
+        match self {
+            Collider::Hyperbox { half_extents } => intersect_intervals(&[
+                slab(0, half_extents.x),
+                slab(1, half_extents.y),
+                slab(2, half_extents.z),
+                slab(3, half_extents.w),
+            ]),
+            Collider::Spherinder {
+                radius,
+                half_height,
+            } => intersect_intervals(&[
+                round(&[0, 2, 3], *radius),
+                slab(1, *half_height),
+            ]),
+            Collider::Cubinder {
+                radius,
+                half_height,
+            } => intersect_intervals(&[
+                round(&[0, 2], *radius),
+                slab(1, *half_height),
+                slab(3, *half_height),
+            ]),
+            Collider::Capsule {
+                radius,
+                half_height,
+            } => {
+                // The capsule is the union of a spherinder and the two balls
+                // on its ends. It's convex, so the ray is inside it from
+                // when it enters the first piece until it leaves the last.
+                let ball = |y: f32| {
+                    let start = start - Vector4::unit_y() * y;
+                    round_interval(
+                        start.magnitude2(),
+                        start.dot(dir),
+                        dir.magnitude2(),
+                        *radius,
+                    )
+                };
+                [
+                    intersect_intervals(&[
+                        round(&[0, 2, 3], *radius),
+                        slab(1, *half_height),
+                    ]),
+                    ball(*half_height),
+                    ball(-half_height),
+                ]
+                .iter()
+                .flatten()
+                .copied()
+                .reduce(|(a0, a1), (b0, b1)| (a0.min(b0), a1.max(b1)))
+            }
+            Collider::Duocylinder {
+                radius_xy,
+                radius_zw,
+            } => intersect_intervals(&[
+                round(&[0, 1], *radius_xy),
+                round(&[2, 3], *radius_zw),
+            ]),
+            _ => None,
+        }
+    }
+
+    // The mass properties of the collider with the given uniform density, or
+    // None for half-spaces which are infinitely big.
+    pub fn mass_properties(&self, density: f32) -> Option<MassProperties> {
+        Some(match self {
+            Collider::HalfSpace { .. } => return None,
+            Collider::Mesh { mesh } => mesh.mass_properties(density),
+            Collider::Sphere { radius } => {
+                let r2 = radius * radius;
+                diagonal_mass_properties(
+                    PI * PI * r2 * r2 / 2.0,
+                    density,
+                    Vector4::new(1.0, 1.0, 1.0, 1.0) * r2 / 6.0,
+                )
+            }
+            Collider::Hyperbox { half_extents } => {
+                let h = half_extents;
+                diagonal_mass_properties(
+                    16.0 * h.x * h.y * h.z * h.w,
+                    density,
+                    Vector4::new(h.x * h.x, h.y * h.y, h.z * h.z, h.w * h.w)
+                        / 3.0,
+                )
+            }
+            Collider::Spherinder {
+                radius,
+                half_height,
+            } => {
+                let (r2, h2) = (radius * radius, half_height * half_height);
+                diagonal_mass_properties(
+                    4.0 / 3.0 * PI * r2 * radius * 2.0 * half_height,
+                    density,
+                    Vector4::new(r2 / 5.0, h2 / 3.0, r2 / 5.0, r2 / 5.0),
+                )
+            }
+            Collider::Cubinder {
+                radius,
+                half_height,
+            } => {
+                let (r2, h2) = (radius * radius, half_height * half_height);
+                diagonal_mass_properties(
+                    PI * r2 * 4.0 * h2,
+                    density,
+                    Vector4::new(r2 / 4.0, h2 / 3.0, r2 / 4.0, h2 / 3.0),
+                )
+            }
+            Collider::Capsule {
+                radius,
+                half_height,
+            } => {
+                let (r, h) = (*radius, *half_height);
+                let cylinder = 4.0 / 3.0 * PI * r.powi(3) * 2.0 * h;
+                let ball = PI * PI * r.powi(4) / 2.0;
+                let hypervolume = cylinder + ball;
+
+                // The ends are two halves of a ball, offset from the centre.
+                // The centroid of a half 4-ball is 16r / 15pi from its centre.
+                let across = cylinder * r * r / 5.0 + ball * r * r / 6.0;
+                let along = cylinder * h * h / 3.0
+                    + ball
+                        * (h * h
+                            + 2.0 * h * 16.0 * r / (15.0 * PI)
+                            + r * r / 6.0);
+                diagonal_mass_properties(
+                    hypervolume,
+                    density,
+                    Vector4::new(across, along, across, across) / hypervolume,
+                )
+            }
+            Collider::Duocylinder {
+                radius_xy,
+                radius_zw,
+            } => {
+                let (a2, b2) = (radius_xy * radius_xy, radius_zw * radius_zw);
+                diagonal_mass_properties(
+                    PI * PI * a2 * b2,
+                    density,
+                    Vector4::new(a2 / 4.0, a2 / 4.0, b2 / 4.0, b2 / 4.0),
+                )
+            }
+            Collider::Compound { children } => {
+                compound_mass_properties(children, density)?
+            }
+        })
+    }
+
+    // Moves the collider so that its centroid is at the origin, which is
+    // what bodies rotate about, and returns its mass properties from there.
+    // Only meshes and compounds can be off-centre; everything else already is.
+    pub fn recentre(&mut self, density: f32) -> Option<MassProperties> {
+        match self {
+            Collider::Mesh { mesh } => Some(mesh.recentre(density)),
+            Collider::Compound { children } => {
+                let mut props = compound_mass_properties(children, density)?;
+                for child in children.iter_mut() {
+                    child.offset -= props.centroid;
+                }
+                props.centroid = Vector4::zero();
+                Some(props)
+            }
+            _ => self.mass_properties(density),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::alg::Rotor4;
+    use crate::mesh::Mesh;
+    use crate::physics::{Aabb, PhysicsWorld};
+
+    fn primitives() -> Vec<(Collider, Mesh)> {
+        vec![
+            (
+                Collider::Hyperbox {
+                    half_extents: Vector4::new(0.5, 1.0, 0.75, 0.25),
+                },
+                Mesh::hyperbox(Vector4::new(0.5, 1.0, 0.75, 0.25)),
+            ),
+            (
+                Collider::Spherinder {
+                    radius: 0.5,
+                    half_height: 1.0,
+                },
+                Mesh::spherinder(0.5, 1.0),
+            ),
+            (
+                Collider::Cubinder {
+                    radius: 0.5,
+                    half_height: 1.0,
+                },
+                Mesh::cubinder(0.5, 1.0),
+            ),
+            (
+                Collider::Capsule {
+                    radius: 0.5,
+                    half_height: 1.0,
+                },
+                Mesh::capsule(0.5, 1.0),
+            ),
+            (
+                Collider::Duocylinder {
+                    radius_xy: 0.5,
+                    radius_zw: 1.0,
+                },
+                Mesh::duocylinder(0.5, 1.0),
+            ),
+        ]
+    }
+
+    #[test]
+    fn render_meshes_approximate_colliders() {
+        for (collider, mesh) in primitives() {
+            // every vertex of the render mesh is on the surface
+            for v in mesh.vertices.iter() {
+                assert!(collider.contains_local(*v));
+                assert!(!collider.contains_local(v * 1.01));
+            }
+
+            // and the mass properties are close to those of the polytope,
+            // which is a bit smaller since it's inscribed in the collider
+            let exact = collider.mass_properties(1.0).unwrap();
+            let approx = mesh.mass_properties(1.0);
+            let error = (exact.hypervolume - approx.hypervolume).abs();
+            assert!(error < 0.1 * exact.hypervolume, "{}", error);
+            for i in 0..4 {
+                let (a, b) =
+                    (exact.second_moment[i][i], approx.second_moment[i][i]);
+                assert!((a - b).abs() < 0.15 * a, "{} {}", a, b);
+            }
+        }
+    }
+
+    #[test]
+    fn support_is_on_the_surface() {
+        let directions = [
+            Vector4::new(1.0, 0.0, 0.0, 0.0),
+            Vector4::new(0.0, -1.0, 0.0, 0.0),
+            Vector4::new(0.3, 0.5, -0.7, 0.2),
+            Vector4::new(-0.1, 0.0, 0.4, -0.9),
+        ];
+        for (collider, _) in primitives() {
+            for d in directions.iter() {
+                let support = collider.local_support(*d).unwrap();
+                assert!(collider.contains_local(support));
+                assert!(!collider.contains_local(support + d * 0.01));
+
+                for (_, point) in collider.support_feature(*d) {
+                    assert!(collider.contains_local(point));
+                    assert!(support.dot(*d) - point.dot(*d) < 0.1);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn unbounded_compounds() {
+        let child = |collider: Collider| ChildCollider {
+            offset: Vector4::zero(),
+            rotation: Rotor4::identity(),
+            collider,
+        };
+        let sphere = || child(Collider::Sphere { radius: 0.5 });
+        let floor = || {
+            child(Collider::HalfSpace {
+                normal: Vector4::unit_y(),
+            })
+        };
+
+        assert_eq!(
+            Collider::compound(Vec::new()).err(),
+            Some(CompoundError::NoChildren)
+        );
+        assert_eq!(
+            Collider::compound(vec![sphere(), floor()]).err(),
+            Some(CompoundError::HalfSpaceChild(1))
+        );
+        let nested = Collider::Compound {
+            children: vec![floor()],
+        };
+        assert_eq!(
+            Collider::compound(vec![sphere(), child(nested)]).err(),
+            Some(CompoundError::HalfSpaceChild(0))
+        );
+        assert!(Collider::compound(vec![sphere()]).is_ok());
+
+        // built by hand instead, they're bounded by everything rather than
+        // panicking
+        for children in [Vec::new(), vec![sphere(), floor()]] {
+            let body = Body::new(
+                Collider::Compound { children },
+                1.0,
+                Inertia::isotropic(1.0),
+                Vector4::zero(),
+            );
+            let aabb = body.aabb();
+            assert_eq!(aabb.min, Aabb::infinite().min);
+            assert_eq!(aabb.max, Aabb::infinite().max);
+
+            let mut world = PhysicsWorld::new();
+            world.bodies.insert(body);
+            world.bodies.insert(Body::new(
+                Collider::Sphere { radius: 0.5 },
+                1.0,
+                Inertia::isotropic(0.1),
+                Vector4::unit_x(),
+            ));
+            world.step(1.0 / 60.0);
+        }
+    }
+
+    #[test]
+    fn compound_mass_properties_match_single_box() {
+        // two unit boxes side by side, one of them rotated a quarter turn in
+        // the yz plane which maps it onto itself
+        let half = Vector4::new(0.5, 0.5, 0.5, 0.5);
+        let child = |x: f32, rotation: Rotor4| ChildCollider {
+            offset: Vector4::unit_x() * x,
+            rotation,
+            collider: Collider::Hyperbox { half_extents: half },
+        };
+        let quarter_turn = crate::alg::Bivec4::new(
+            0.0,
+            0.0,
+            0.0,
+            std::f32::consts::FRAC_PI_2,
+            0.0,
+            0.0,
+        )
+        .exp();
+        let compound = Collider::compound(vec![
+            child(-0.5, Rotor4::identity()),
+            child(0.5, quarter_turn),
+        ])
+        .unwrap();
+        let single = Collider::Hyperbox {
+            half_extents: Vector4::new(1.0, 0.5, 0.5, 0.5),
+        };
+
+        let a = compound.mass_properties(2.0).unwrap();
+        let b = single.mass_properties(2.0).unwrap();
+        assert!((a.hypervolume - b.hypervolume).abs() < 1e-4);
+        assert!((a.mass - b.mass).abs() < 1e-4);
+        assert!(a.centroid.magnitude() < 1e-4);
+        for i in 0..4 {
+            for j in 0..4 {
+                let (x, y) = (a.second_moment[i][j], b.second_moment[i][j]);
+                assert!((x - y).abs() < 1e-4, "{} {}", x, y);
+            }
+        }
+
+        assert!(
+            (compound.bounding_radius() - 0.5 - half.magnitude()).abs() < 1e-4
+        );
+        assert!(compound.contains_local(Vector4::new(0.9, 0.4, 0.0, 0.0)));
+        assert!(!compound.contains_local(Vector4::new(1.1, 0.0, 0.0, 0.0)));
+        let support = compound.local_support(Vector4::unit_x()).unwrap();
+        assert!((support.x - 1.0).abs() < 1e-4);
+    }
+
+    #[test]
+    fn recentred_compound() {
+        // an L-shape, whose centroid is pulled towards the corner box
+        let child = |x: f32, y: f32| ChildCollider {
+            offset: Vector4::new(x, y, 0.0, 0.0),
+            rotation: Rotor4::identity(),
+            collider: Collider::Hyperbox {
+                half_extents: Vector4::new(0.5, 0.5, 0.5, 0.5),
+            },
+        };
+        let mut compound = Collider::compound(vec![
+            child(0.0, 0.0),
+            child(1.0, 0.0),
+            child(0.0, 1.0),
+        ])
+        .unwrap();
+        let before = compound.mass_properties(1.0).unwrap();
+        let expected = Vector4::new(1.0 / 3.0, 1.0 / 3.0, 0.0, 0.0);
+        assert!((before.centroid - expected).magnitude() < 1e-4);
+
+        let props = compound.recentre(1.0).unwrap();
+        let after = compound.mass_properties(1.0).unwrap();
+        assert!(props.centroid.magnitude() < 1e-4);
+        assert!(after.centroid.magnitude() < 1e-4);
+        assert!((props.mass - before.mass).abs() < 1e-4);
+        for i in 0..4 {
+            for j in 0..4 {
+                let (x, y) =
+                    (props.second_moment[i][j], before.second_moment[i][j]);
+                assert!((x - y).abs() < 1e-4, "{} {}", x, y);
+            }
+        }
+        assert!(compound.contains_local(-expected));
+        assert!(!compound.contains_local(Vector4::new(0.5, 0.5, 0.0, 0.0)));
+    }
+
+    #[test]
+    fn hyperbox_meshes_are_shared() {
+        let half_extents = Vector4::new(0.5, 1.0, 0.75, 0.25);
+        let collider = Collider::Hyperbox { half_extents };
+        let mesh = |collider: &Collider| match collider.polytope() {
+            Some(PolytopeMesh::Shared(mesh)) => mesh,
+            _ => panic!("hyperboxes should have a shared mesh"),
+        };
+        let first = mesh(&collider);
+        assert!(Rc::ptr_eq(&first, &mesh(&collider.clone())));
+        assert_eq!(first.vertices, Mesh::hyperbox(half_extents).vertices);
+
+        let other = Collider::Hyperbox {
+            half_extents: half_extents * 2.0,
+        };
+        assert!(!Rc::ptr_eq(&first, &mesh(&other)));
+    }
+
+    #[test]
+    fn ray_hits_surface() {
+        for (collider, _) in primitives() {
+            let start = Vector4::new(-3.0, 0.1, 0.2, 0.05);
+            let (t, _) = collider
+                .primitive_ray_intersect(start, Vector4::unit_x())
+                .unwrap();
+            let hit = start + Vector4::unit_x() * t;
+            assert!(collider.contains_local(hit));
+            assert!(!collider.contains_local(hit - Vector4::unit_x() * 0.01));
+            let normal = collider.local_normal(hit);
+            assert!((normal.magnitude() - 1.0).abs() < 1e-4);
+            assert!(normal.x < 0.0);
+            assert!(collider.contains_local(hit - normal * 0.01));
+            assert!(!collider.contains_local(hit + normal * 0.01));
+
+            assert!(collider
+                .primitive_ray_intersect(start, Vector4::unit_y())
+                .is_none());
+        }
+    }
+
+    #[test]
+    fn ray_from_inside_capsule() {
+        let capsule = Body::new(
+            Collider::Capsule {
+                radius: 0.5,
+                half_height: 1.0,
+            },
+            1.0,
+            Inertia::isotropic(1.0),
+            Vector4::zero(),
+        );
+        for &y in [0.0, 0.6, 1.2].iter() {
+            let start = Vector4::new(0.0, y, 0.0, 0.0);
+            let hit = capsule.ray_intersect(start, Vector4::unit_y()).unwrap();
+            assert_eq!(hit.lambda, 0.0);
+            assert!((hit.normal + Vector4::unit_y()).magnitude() < 1e-4);
+        }
+
+        // the ray leaves the end ball rather than the cylinder
+        let (t0, t1) = capsule
+            .collider
+            .primitive_ray_intersect(Vector4::zero(), Vector4::unit_y())
+            .unwrap();
+        assert!((t0 + 1.5).abs() < 1e-4 && (t1 - 1.5).abs() < 1e-4);
+    }
+}
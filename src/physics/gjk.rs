@@ -1,20 +1,26 @@
-use std::collections::HashMap;
-
-use super::{CollisionManifold, MeshRef, SphereRef};
+use super::{CollisionManifold, Contact, ContactId, MeshRef, SphereRef};
 use crate::alg::triple_cross_product;
-use crate::mesh::Mesh;
 use crate::util::{NotNaN, EPSILON};
-use cgmath::{InnerSpace, Matrix2, SquareMatrix, Vector2, Vector4, Zero};
-
+use cgmath::{
+    Array, InnerSpace, Matrix2, Matrix3, SquareMatrix, Vector2, Vector3,
+    Vector4, Zero,
+};
+use smallvec::SmallVec;
+
+// Bounds on the number of iterations, in case of degenerate shapes which
+// never quite converge.
+const MAX_GJK_ITERATIONS: usize = 64;
+const MAX_EPA_ITERATIONS: usize = 128;
+// GJK distance stops once a step gets the closest point no closer than this
+// fraction of the distance.
+const DISTANCE_TOLERANCE: f32 = 1e-5;
+
+// Anything convex which can report its furthest point in world space along a
+// direction. This is all GJK and EPA need to know about a shape.
 pub trait Support {
     fn support(&self, direction: Vector4<f32>) -> Vector4<f32>;
 }
 
-pub struct OffsetMeshRef<'a> {
-    pub mesh_ref: MeshRef<'a>,
-    pub offset: Vector4<f32>,
-}
-
 impl<'a> Support for MeshRef<'a> {
     fn support(&self, direction: Vector4<f32>) -> Vector4<f32> {
         let body_d = self.body.world_vec_to_body(direction);
@@ -31,16 +37,17 @@ impl<'a> Support for MeshRef<'a> {
 
 impl<'a> Support for SphereRef<'a> {
     fn support(&self, direction: Vector4<f32>) -> Vector4<f32> {
+        if direction.magnitude2() < EPSILON * EPSILON {
+            // any point on the surface will do
+            return self.body.pos + Vector4::unit_x() * self.radius;
+        }
         direction.normalize() * self.radius + self.body.pos
     }
 }
 
-impl<'a> Support for OffsetMeshRef<'a> {
-    fn support(&self, direction: Vector4<f32>) -> Vector4<f32> {
-        self.mesh_ref.support(direction) - self.offset
-    }
-}
-
+// The configuration space obstacle, i.e. the Minkowski difference a - b. The
+// two shapes intersect exactly when this contains the origin.
+#[allow(clippy::upper_case_acronyms)]
 #[derive(Debug, Clone, Copy)]
 pub struct CSO<A, B>
 where
@@ -57,45 +64,106 @@ where
     B: Support,
 {
     fn support(&self, direction: Vector4<f32>) -> Vector4<f32> {
-        self.a.support(direction) - self.b.support(-direction)
+        self.support_point(direction).point
     }
 }
 
-fn support(m: MeshRef, direction: Vector4<f32>) -> Vector4<f32> {
-    let body_d = m.body.world_vec_to_body(direction);
-    m.body.body_pos_to_world(
-        *m.mesh
-            .vertices
-            .iter()
-            .max_by_key(|v| NotNaN::new(v.dot(body_d)).unwrap())
-            .unwrap(),
-    )
+impl<A, B> CSO<A, B>
+where
+    A: Support,
+    B: Support,
+{
+    pub fn new(a: A, b: B) -> Self {
+        Self { a, b }
+    }
+
+    fn support_point(&self, direction: Vector4<f32>) -> SupportPoint {
+        let a = self.a.support(direction);
+        let b = self.b.support(-direction);
+        SupportPoint { point: a - b, a, b }
+    }
+}
+
+// A point on the CSO, along with the points on each shape it came from so
+// that contact points can be recovered afterwards.
+#[derive(Debug, Clone, Copy)]
+struct SupportPoint {
+    point: Vector4<f32>,
+    a: Vector4<f32>,
+    b: Vector4<f32>,
+}
+
+impl SupportPoint {
+    fn zero() -> Self {
+        Self {
+            point: Vector4::zero(),
+            a: Vector4::zero(),
+            b: Vector4::zero(),
+        }
+    }
 }
 
 pub struct Simplex {
     vertices: [Vector4<f32>; 5],
+    support_points: [SupportPoint; 5],
     length: usize,
 }
 
 impl Simplex {
-    fn new(initial_point: Vector4<f32>) -> Self {
+    fn new(initial_point: SupportPoint) -> Self {
         let mut vertices = [Vector4::zero(); 5];
-        vertices[0] = initial_point;
+        let mut support_points = [SupportPoint::zero(); 5];
+        vertices[0] = initial_point.point;
+        support_points[0] = initial_point;
         Self {
             vertices,
+            support_points,
             length: 1,
         }
     }
 
-    fn push(&mut self, point: Vector4<f32>) {
+    fn push(&mut self, point: SupportPoint) {
         if self.length >= 5 {
             panic!("Simplex is already full!");
         }
 
-        self.vertices[self.length] = point;
+        self.vertices[self.length] = point.point;
+        self.support_points[self.length] = point;
         self.length += 1;
     }
 
+    // Returns a unit vector perpendicular to every edge of the simplex.
+    fn orthogonal_direction(&self) -> Vector4<f32> {
+        let mut basis: SmallVec<[Vector4<f32>; 4]> = SmallVec::new();
+        for i in 1..self.length {
+            let mut v = self.vertices[i] - self.vertices[0];
+            for b in basis.iter() {
+                v -= *b * v.dot(*b);
+            }
+            if v.magnitude2() > EPSILON {
+                basis.push(v.normalize());
+            }
+        }
+
+        [
+            Vector4::unit_x(),
+            Vector4::unit_y(),
+            Vector4::unit_z(),
+            Vector4::unit_w(),
+        ]
+        .iter()
+        .map(|axis| {
+            let mut v = *axis;
+            for b in basis.iter() {
+                v -= *b * v.dot(*b);
+            }
+            v
+        })
+        .max_by_key(|v| NotNaN::new(v.magnitude2()).unwrap())
+        .unwrap()
+        .normalize()
+    }
+
     fn remove(&mut self, index: usize) {
         if index >= self.length {
             panic!("Invalid simplex index provided to remove_at");
@@ -106,6 +174,7 @@ impl Simplex {
 
         for i in (index + 1)..self.length {
             self.vertices[i - 1] = self.vertices[i];
+            self.support_points[i - 1] = self.support_points[i];
         }
         self.length -= 1;
     }
@@ -124,6 +193,11 @@ impl Simplex {
                 // line case, return a direction perpendicular
                 let (a, b) = (self.vertices[0], self.vertices[1]);
                 let ab = b - a;
+                if ab.magnitude2() < EPSILON * EPSILON {
+                    // the new point is the same as the old one
+                    self.remove(1);
+                    return self.nearest_simplex();
+                }
                 let lambda = -a.dot(ab) / ab.magnitude2();
                 // lambda is now such that a + lambda * (b - a) is the point on
                 // the defined by a and b closest to the origin.
@@ -143,7 +217,14 @@ impl Simplex {
                          ab.dot(ac), ac.magnitude2(),
                 );
                 let y = Vector2::new(-a.dot(ab), -a.dot(ac));
-                let x = mat.invert().unwrap() * y;
+                let x = match mat.invert() {
+                    Some(inverse) => inverse * y,
+                    None => {
+                        // the triangle is degenerate, drop the new point
+                        self.remove(2);
+                        return self.nearest_simplex();
+                    }
+                };
 
                 let (lambda, mu) = (x.x, x.y);
 
@@ -160,6 +241,11 @@ impl Simplex {
                 // We can use the triple cross product to just grab a normal to
                 // the tetrahedron
                 let n = triple_cross_product(ab, ac, ad);
+                if n.magnitude2() < EPSILON * EPSILON {
+                    // the tetrahedron is degenerate, drop the new point
+                    self.remove(3);
+                    return self.nearest_simplex();
+                }
                 let k = a.dot(n) / n.magnitude2();
 
                 Some(k * n)
@@ -218,21 +304,39 @@ impl Simplex {
     }
 }
 
-pub fn gjk_intersection<S>(
-    object: S,
+// Returns a simplex inside the CSO which contains the origin if the shapes
+// intersect, or a separating direction if they don't.
+pub fn gjk_intersection<A, B>(
+    object: &CSO<A, B>,
     initial_direction: Vector4<f32>,
 ) -> Result<Simplex, Vector4<f32>>
 where
-    S: Support,
+    A: Support,
+    B: Support,
 {
-    let mut a = object.support(initial_direction);
+    let mut a = object.support_point(initial_direction);
     let mut s = Simplex::new(a);
-    let mut d = -a;
-
-    loop {
-        a = object.support(d);
-        if a.dot(d) < EPSILON {
-            return Err(-d);
+    let mut d = -a.point;
+
+    for _ in 0..MAX_GJK_ITERATIONS {
+        if d.magnitude2() < EPSILON * EPSILON {
+            // The origin lies in the span of the simplex, so it has to be
+            // grown into another dimension before it can enclose the origin.
+            // If the CSO is flat in both directions then the shapes are only
+            // just touching.
+            let n = s.orthogonal_direction();
+            a = object.support_point(n);
+            if a.point.dot(n) < EPSILON {
+                a = object.support_point(-n);
+                if a.point.dot(-n) < EPSILON {
+                    break;
+                }
+            }
+        } else {
+            a = object.support_point(d);
+            if a.point.dot(d) < EPSILON {
+                return Err(-d);
+            }
         }
         s.push(a);
         match s.nearest_simplex() {
@@ -240,18 +344,164 @@ where
             None => return Ok(s),
         }
     }
+
+    Err(-d)
+}
+
+// Returns the closest points on a and b respectively if the shapes are apart,
+// or None if they intersect. Unlike gjk_intersection, this keeps reducing the
+// simplex to the feature nearest the origin, which is slower but converges on
+// the actual closest points rather than any separating direction.
+pub fn gjk_distance<A, B>(
+    object: &CSO<A, B>,
+    initial_direction: Vector4<f32>,
+) -> Option<(Vector4<f32>, Vector4<f32>)>
+where
+    A: Support,
+    B: Support,
+{
+    let mut points: SmallVec<[SupportPoint; 5]> = SmallVec::new();
+    points.push(object.support_point(initial_direction));
+    let mut weights: SmallVec<[f32; 5]> = SmallVec::new();
+    weights.push(1.0);
+    let mut closest = points[0].point;
+
+    for _ in 0..MAX_GJK_ITERATIONS {
+        let distance2 = closest.magnitude2();
+        if distance2 < EPSILON * EPSILON {
+            return None;
+        }
+
+        let next = object.support_point(-closest);
+        let progress = distance2 - next.point.dot(closest);
+        let repeated = points
+            .iter()
+            .any(|p| (p.point - next.point).magnitude2() < EPSILON * EPSILON);
+        if progress <= DISTANCE_TOLERANCE * distance2 || repeated {
+            break;
+        }
+
+        points.push(next);
+        let vertices: SmallVec<[Vector4<f32>; 5]> =
+            points.iter().map(|p| p.point).collect();
+        let (subset, subset_weights) = closest_on_simplex(&vertices);
+        points = subset.iter().map(|&i| points[i]).collect();
+        weights = subset_weights;
+        closest = Vector4::zero();
+        for (p, w) in points.iter().zip(weights.iter()) {
+            closest += p.point * *w;
+        }
+    }
+
+    let (mut a, mut b) = (Vector4::zero(), Vector4::zero());
+    for (p, w) in points.iter().zip(weights.iter()) {
+        a += p.a * *w;
+        b += p.b * *w;
+    }
+    Some((a, b))
+}
+
+// The indices of some of a simplex's vertices, along with barycentric weights
+// for a point on the face they span.
+type WeightedFace = (SmallVec<[usize; 5]>, SmallVec<[f32; 5]>);
+
+// Finds the face of a simplex closest to the origin, along with the weights
+// of the closest point on it. Simplices have at most five vertices, so every
+// face is simply tried in turn.
+fn closest_on_simplex(vertices: &[Vector4<f32>]) -> WeightedFace {
+    let mut best: Option<(f32, WeightedFace)> = None;
+    for mask in 1..(1 << vertices.len()) {
+        let face: SmallVec<[usize; 5]> = (0..vertices.len())
+            .filter(|i| mask & (1 << i) != 0)
+            .collect();
+        let face_vertices: SmallVec<[Vector4<f32>; 5]> =
+            face.iter().map(|&i| vertices[i]).collect();
+        // The closest point on the face's affine hull is only on the face
+        // itself if it's inside every edge.
+        let weights = match affine_closest_weights(&face_vertices) {
+            Some(weights) if weights.iter().all(|&w| w > 0.0) => weights,
+            _ => continue,
+        };
+
+        let mut point = Vector4::zero();
+        for (v, w) in face_vertices.iter().zip(weights.iter()) {
+            point += v * *w;
+        }
+        let distance2 = point.magnitude2();
+        if !matches!(&best, Some((d, _)) if *d <= distance2) {
+            best = Some((distance2, (face, weights)));
+        }
+    }
+
+    // Single vertices always have a positive weight, so something's found.
+    best.unwrap().1
+}
+
+// The barycentric weights of the point on the affine hull of the vertices
+// closest to the origin, or None if the vertices are degenerate.
+fn affine_closest_weights(
+    vertices: &[Vector4<f32>],
+) -> Option<SmallVec<[f32; 5]>> {
+    let a = vertices[0];
+    let edges: SmallVec<[Vector4<f32>; 4]> =
+        vertices[1..].iter().map(|v| v - a).collect();
+    let n = edges.len();
+
+    // Solve the normal equations for a + sum(lambda_i * edge_i) by Gaussian
+    // elimination, with the right hand side in the last column.
+    let mut rows = [[0.0; 5]; 4];
+    for i in 0..n {
+        for j in 0..n {
+            rows[i][j] = edges[i].dot(edges[j]);
+        }
+        rows[i][n] = -a.dot(edges[i]);
+    }
+    for col in 0..n {
+        let pivot = (col..n)
+            .max_by_key(|&row| NotNaN::new(rows[row][col].abs()).unwrap())
+            .unwrap();
+        if rows[pivot][col].abs() < EPSILON {
+            return None;
+        }
+        rows.swap(col, pivot);
+        for row in 0..n {
+            if row != col {
+                let pivot_row = rows[col];
+                let factor = rows[row][col] / pivot_row[col];
+                for (value, p) in
+                    rows[row][col..=n].iter_mut().zip(&pivot_row[col..=n])
+                {
+                    *value -= factor * p;
+                }
+            }
+        }
+    }
+
+    let mut weights: SmallVec<[f32; 5]> = SmallVec::new();
+    weights.push(1.0);
+    for (i, row) in rows.iter().take(n).enumerate() {
+        let lambda = row[n] / row[i];
+        weights[0] -= lambda;
+        weights.push(lambda);
+    }
+    Some(weights)
 }
 
 struct EPCell {
     faces: [[usize; 3]; 4],
+    vertices: [usize; 4],
     normal: Vector4<f32>,
     distance: f32,
 }
 
 impl EPCell {
+    // `interior` is any point strictly inside the polytope, which is used to
+    // make the normal face outwards. The origin can't be used for this as it
+    // may lie on the boundary when the shapes are only just touching.
     fn from_faces(
-        vertices: &Vec<Vector4<f32>>,
+        vertices: &[Vector4<f32>],
         faces: [[usize; 3]; 4],
+        interior: Vector4<f32>,
     ) -> Self {
         let mut unique_vertices = [0; 4];
         let mut unique_vertices_count = 0;
@@ -275,33 +525,67 @@ impl EPCell {
         let c = vertices[unique_vertices[2]];
         let d = vertices[unique_vertices[3]];
 
-        let mut normal = triple_cross_product(b - a, c - a, d - a);
-        let mut distance = normal.dot(a);
-        if distance < 0.0 {
+        let mut normal = triple_cross_product(b - a, c - a, d - a).normalize();
+        if normal.dot(a - interior) < 0.0 {
             normal = -normal;
-            distance = -distance;
         }
+        let distance = normal.dot(a);
 
         Self {
             faces,
+            vertices: unique_vertices,
             normal,
             distance,
         }
     }
+
+    fn is_degenerate(&self) -> bool {
+        !self.normal.is_finite()
+    }
+
+    // Finds the barycentric coordinates of the point on the cell closest to
+    // the origin.
+    fn closest_point_weights(&self, vertices: &[Vector4<f32>]) -> [f32; 4] {
+        let a = vertices[self.vertices[0]];
+        let ab = vertices[self.vertices[1]] - a;
+        let ac = vertices[self.vertices[2]] - a;
+        let ad = vertices[self.vertices[3]] - a;
+        let p = self.normal * self.distance - a;
+
+        #[rustfmt::skip]
+        let mat = Matrix3::new(
+            ab.dot(ab), ab.dot(ac), ab.dot(ad),
+            ac.dot(ab), ac.dot(ac), ac.dot(ad),
+            ad.dot(ab), ad.dot(ac), ad.dot(ad),
+        );
+        let y = Vector3::new(p.dot(ab), p.dot(ac), p.dot(ad));
+        match mat.invert() {
+            Some(inverse) => {
+                let x = inverse * y;
+                [1.0 - x.x - x.y - x.z, x.x, x.y, x.z]
+            }
+            None => [0.25; 4],
+        }
+    }
 }
 
 struct ExpandingPolytope {
     vertices: Vec<Vector4<f32>>,
+    support_points: Vec<SupportPoint>,
     cells: Vec<EPCell>,
+    interior: Vector4<f32>,
 }
 
 impl ExpandingPolytope {
     fn from_simplex(simplex: Simplex) -> Self {
         assert!(simplex.length == 5);
         let vertices = simplex.vertices.to_vec();
+        let support_points = simplex.support_points.to_vec();
+        // The polytope only ever grows, so the centre of the initial simplex
+        // stays inside it.
+        let interior = vertices.iter().sum::<Vector4<f32>>() / 5.0;
 
-        let mut faces = Vec::new();
-        faces.reserve(10);
+        let mut faces = Vec::with_capacity(10);
         for i in 0..5 {
             for j in (i + 1)..5 {
                 for k in (j + 1)..5 {
@@ -310,8 +594,7 @@ impl ExpandingPolytope {
             }
         }
 
-        let mut cells = Vec::new();
-        cells.reserve(5);
+        let mut cells = Vec::with_capacity(5);
         for i in 0..5 {
             let mut cell_faces = [[0, 0, 0]; 4];
             let mut cell_face_count = 0;
@@ -326,21 +609,28 @@ impl ExpandingPolytope {
                 }
             }
 
-            cells.push(EPCell::from_faces(&vertices, cell_faces));
+            cells.push(EPCell::from_faces(&vertices, cell_faces, interior));
         }
 
-        Self { vertices, cells }
+        Self {
+            vertices,
+            support_points,
+            cells,
+            interior,
+        }
     }
 
-    fn expand(&mut self, extend_point: Vector4<f32>) {
+    fn expand(&mut self, extend_support_point: SupportPoint) {
+        let extend_point = extend_support_point.point;
         let mut removed_faces = Vec::new();
 
         let cells = &mut self.cells;
         let vertices = &self.vertices;
 
+        // remove every cell which can be seen from the new point
         cells.retain(|cell| {
             let v0 = vertices[cell.faces[0][0]];
-            if cell.normal.dot(extend_point - v0) > 0.0 {
+            if cell.normal.dot(extend_point - v0) <= 0.0 {
                 true
             } else {
                 // Before returning, we need to add all the faces to the removed
@@ -362,6 +652,7 @@ impl ExpandingPolytope {
         // push the new vertex
         let vertex_idx = self.vertices.len();
         self.vertices.push(extend_point);
+        self.support_points.push(extend_support_point);
 
         // now add a new cell for each face
         for face in removed_faces {
@@ -375,31 +666,234 @@ impl ExpandingPolytope {
                 f.sort_unstable();
             }
 
-            self.cells
-                .push(EPCell::from_faces(&self.vertices, cell_faces));
+            self.cells.push(EPCell::from_faces(
+                &self.vertices,
+                cell_faces,
+                self.interior,
+            ));
         }
     }
 }
 
-fn epa<S>(object: S, simplex: Simplex) -> Vector4<f32>
+// The minimum translation needed to separate two intersecting shapes, along
+// with the point where they're touching.
+#[derive(Debug, Clone, Copy)]
+pub struct Penetration {
+    // Points from a to b.
+    pub normal: Vector4<f32>,
+    pub depth: f32,
+    pub contact: Vector4<f32>,
+}
+
+fn epa<A, B>(object: &CSO<A, B>, simplex: Simplex) -> Option<Penetration>
 where
-    S: Support,
+    A: Support,
+    B: Support,
 {
     let mut polytope = ExpandingPolytope::from_simplex(simplex);
 
-    loop {
+    for iteration in 0..=MAX_EPA_ITERATIONS {
         let min_cell = polytope
             .cells
             .iter()
-            .min_by_key(|i| NotNaN::new(i.distance).unwrap())
-            .unwrap();
+            .filter(|cell| !cell.is_degenerate())
+            .min_by_key(|cell| NotNaN::new(cell.distance).unwrap())?;
         let normal = min_cell.normal;
-        let extend_point = object.support(normal);
+        let extend_point = object.support_point(normal);
 
-        if extend_point.dot(normal) - min_cell.distance > EPSILON {
+        if extend_point.point.dot(normal) - min_cell.distance > EPSILON
+            && iteration < MAX_EPA_ITERATIONS
+        {
             polytope.expand(extend_point);
         } else {
-            return normal;
+            let weights = min_cell.closest_point_weights(&polytope.vertices);
+            let mut contact = Vector4::zero();
+            for (weight, vertex_idx) in weights.iter().zip(&min_cell.vertices) {
+                let support_point = &polytope.support_points[*vertex_idx];
+                contact += (support_point.a + support_point.b) * (weight / 2.0);
+            }
+
+            return Some(Penetration {
+                normal,
+                depth: min_cell.distance,
+                contact,
+            });
         }
     }
+
+    None
+}
+
+// Finds how far two convex shapes are overlapping, if at all.
+pub fn gjk_epa<A, B>(
+    a: A,
+    b: B,
+    initial_direction: Vector4<f32>,
+) -> Option<Penetration>
+where
+    A: Support,
+    B: Support,
+{
+    let cso = CSO::new(a, b);
+    let simplex = gjk_intersection(&cso, initial_direction).ok()?;
+    epa(&cso, simplex)
+}
+
+// Collision detection for any pair of convex shapes, at the cost of only
+// producing a single contact point.
+pub fn convex_collision<A, B>(
+    a: A,
+    b: B,
+    initial_direction: Vector4<f32>,
+) -> Option<CollisionManifold>
+where
+    A: Support,
+    B: Support,
+{
+    let penetration = gjk_epa(a, b, initial_direction)?;
+    if !penetration.normal.is_finite() || !penetration.contact.is_finite() {
+        return None;
+    }
+
+    Some(CollisionManifold {
+        normal: penetration.normal,
+        depth: penetration.depth,
+        contacts: vec![Contact::new(penetration.contact, ContactId::Single)],
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mesh::Mesh;
+    use crate::physics::{Body, Collider, ColliderRef, Inertia};
+
+    fn body(collider: Collider, pos: Vector4<f32>) -> Body {
+        Body::new(collider, 1.0, Inertia::isotropic(1.0 / 6.0), pos)
+    }
+
+    fn assert_close(a: Vector4<f32>, b: Vector4<f32>) {
+        assert!((a - b).magnitude() < 1e-3, "{:?} != {:?}", a, b);
+    }
+
+    #[test]
+    fn spheres() {
+        let a = body(Collider::Sphere { radius: 1.0 }, Vector4::zero());
+        let b = body(
+            Collider::Sphere { radius: 1.0 },
+            Vector4::new(1.5, 0.0, 0.0, 0.0),
+        );
+        let sphere = |body| SphereRef {
+            body: ColliderRef::new(body),
+            radius: 1.0,
+        };
+
+        let manifold =
+            convex_collision(sphere(&a), sphere(&b), Vector4::unit_y())
+                .unwrap();
+        assert_close(manifold.normal, Vector4::unit_x());
+        assert!((manifold.depth - 0.5).abs() < 1e-2, "{:?}", manifold);
+        assert_close(manifold.contacts[0].point, Vector4::unit_x() * 0.75);
+
+        let c = body(
+            Collider::Sphere { radius: 1.0 },
+            Vector4::new(0.0, 0.0, 2.5, 0.0),
+        );
+        assert!(convex_collision(sphere(&a), sphere(&c), Vector4::unit_x())
+            .is_none());
+    }
+
+    #[test]
+    fn tesseracts_match_sat() {
+        let mesh = Mesh::from_schlafli_symbol(&[4, 3, 3]);
+        let a = body(Collider::Mesh { mesh: mesh.clone() }, Vector4::zero());
+        let b = body(
+            Collider::Mesh { mesh: mesh.clone() },
+            Vector4::new(0.1, 0.9, 0.2, -0.1),
+        );
+        let mesh_ref = |body| MeshRef {
+            body: ColliderRef::new(body),
+            mesh: &mesh,
+        };
+
+        let manifold =
+            convex_collision(mesh_ref(&a), mesh_ref(&b), Vector4::unit_x())
+                .unwrap();
+        assert_close(manifold.normal, Vector4::unit_y());
+        assert!((manifold.depth - 0.1).abs() < 1e-3, "{:?}", manifold);
+        // the contact should be somewhere in the overlapping region
+        let contact = manifold.contacts[0].point;
+        assert!((contact.y - 0.45).abs() < 0.05 + 1e-3, "{:?}", contact);
+
+        let mut world = crate::physics::PhysicsWorld::new();
+        world.bodies.insert(a);
+        world.bodies.insert(b);
+        let (_, _, sat) = world.find_collisions().pop().unwrap();
+        world.collision.sat_feature_limit = 0;
+        let (_, _, gjk) = world.find_collisions().pop().unwrap();
+        assert_close(sat.normal, gjk.normal);
+        assert!((sat.depth - gjk.depth).abs() < 1e-3);
+    }
+
+    #[test]
+    fn distance_between_shapes() {
+        let mesh = Mesh::from_schlafli_symbol(&[4, 3, 3]);
+        let a = body(Collider::Mesh { mesh: mesh.clone() }, Vector4::zero());
+        let mesh_ref = |body| MeshRef {
+            body: ColliderRef::new(body),
+            mesh: &mesh,
+        };
+        let sphere = |body| SphereRef {
+            body: ColliderRef::new(body),
+            radius: 0.5,
+        };
+
+        // a sphere off one corner of the tesseract
+        let corner = Vector4::new(0.5, 0.5, 0.5, 0.5);
+        let b = body(Collider::Sphere { radius: 0.5 }, corner * 3.0);
+        let cso = CSO::new(mesh_ref(&a), sphere(&b));
+        let (p, q) = gjk_distance(&cso, Vector4::unit_x()).unwrap();
+        assert_close(p, corner);
+        // round shapes converge slowly along their surface, but the distance
+        // is still accurate
+        assert!(((q - p).magnitude() - 1.5).abs() < 1e-3);
+        let expected = corner * 3.0 - corner.normalize() * 0.5;
+        assert!((q - expected).magnitude() < 1e-2, "{:?}", q);
+
+        // a tesseract next to one of its faces, off to one side
+        let c = body(
+            Collider::Mesh { mesh: mesh.clone() },
+            Vector4::new(0.2, 1.5, 0.0, 0.0),
+        );
+        let cso = CSO::new(mesh_ref(&a), mesh_ref(&c));
+        let (p, q) = gjk_distance(&cso, Vector4::unit_x()).unwrap();
+        assert!(((q - p).magnitude() - 0.5).abs() < 1e-3);
+        assert_close((q - p).normalize(), Vector4::unit_y());
+
+        let d = body(
+            Collider::Sphere { radius: 0.5 },
+            Vector4::new(0.8, 0.0, 0.0, 0.0),
+        );
+        let cso = CSO::new(mesh_ref(&a), sphere(&d));
+        assert!(gjk_distance(&cso, Vector4::unit_x()).is_none());
+    }
+
+    #[test]
+    fn sphere_deep_inside_mesh() {
+        let mut world = crate::physics::PhysicsWorld::new();
+        let mesh = Mesh::from_schlafli_symbol(&[4, 3, 3]);
+        let a = world
+            .bodies
+            .insert(body(Collider::Mesh { mesh }, Vector4::zero()));
+        let b = world.bodies.insert(body(
+            Collider::Sphere { radius: 0.5 },
+            Vector4::new(0.3, 0.0, 0.0, 0.0),
+        ));
+
+        let (ka, _, manifold) = world.find_collisions().pop().unwrap();
+        let normal = if ka == a { 1.0 } else { -1.0 } * manifold.normal;
+        assert_ne!(a, b);
+        assert_close(normal, Vector4::unit_x());
+        assert!((manifold.depth - 0.7).abs() < 1e-2, "{:?}", manifold);
+    }
 }
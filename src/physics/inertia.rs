@@ -0,0 +1,230 @@
+use cgmath::Matrix4;
+
+use crate::alg::Bivec4;
+use crate::mesh::Mesh;
+
+// The basis bivectors in the same order as the fields of Bivec4, as pairs of
+// axes, i.e. xy = (0, 1).
+const BASIS: [(usize, usize); 6] =
+    [(0, 1), (0, 2), (0, 3), (1, 2), (1, 3), (2, 3)];
+
+fn to_array(b: &Bivec4) -> [f32; 6] {
+    [b.xy, b.xz, b.xw, b.yz, b.yw, b.zw]
+}
+
+fn from_array(a: [f32; 6]) -> Bivec4 {
+    Bivec4::new(a[0], a[1], a[2], a[3], a[4], a[5])
+}
+
+fn mul(matrix: &[[f32; 6]; 6], b: &Bivec4) -> Bivec4 {
+    let b = to_array(b);
+    let mut result = [0.0; 6];
+    for (i, row) in matrix.iter().enumerate() {
+        result[i] = row.iter().zip(b.iter()).map(|(m, b)| m * b).sum();
+    }
+    from_array(result)
+}
+
+// Gauss-Jordan elimination with partial pivoting. Returns None if the matrix is
+// singular.
+fn invert(matrix: &[[f32; 6]; 6]) -> Option<[[f32; 6]; 6]> {
+    let mut a = [[0.0f64; 12]; 6];
+    for i in 0..6 {
+        for j in 0..6 {
+            a[i][j] = matrix[i][j] as f64;
+        }
+        a[i][6 + i] = 1.0;
+    }
+
+    for col in 0..6 {
+        let pivot = (col..6)
+            .max_by(|&i, &j| {
+                a[i][col].abs().partial_cmp(&a[j][col].abs()).unwrap()
+            })
+            .unwrap();
+        if a[pivot][col].abs() < 1e-12 {
+            return None;
+        }
+        a.swap(col, pivot);
+
+        let scale = a[col][col];
+        for x in a[col].iter_mut() {
+            *x /= scale;
+        }
+
+        for row in 0..6 {
+            if row != col {
+                let factor = a[row][col];
+                let pivot_row = a[col];
+                for (x, p) in a[row].iter_mut().zip(pivot_row.iter()) {
+                    *x -= factor * p;
+                }
+            }
+        }
+    }
+
+    let mut inverse = [[0.0; 6]; 6];
+    for i in 0..6 {
+        for j in 0..6 {
+            inverse[i][j] = a[i][6 + j] as f32;
+        }
+    }
+    Some(inverse)
+}
+
+// The moment of inertia of a body, as a linear operator from angular velocity
+// to angular momentum (both bivectors in the body frame), i.e.
+//   L = I(w) = sum over particles of m r /\ (r . w)
+#[derive(Debug, Clone, Copy)]
+pub struct Inertia {
+    matrix: [[f32; 6]; 6],
+    inverse: [[f32; 6]; 6],
+}
+
+impl Inertia {
+    // The inertia of an immovable body. Its inverse is also zero, so impulses
+    // don't affect it.
+    pub fn zero() -> Self {
+        Self {
+            matrix: [[0.0; 6]; 6],
+            inverse: [[0.0; 6]; 6],
+        }
+    }
+
+    // The same moment of inertia in every plane of rotation.
+    pub fn isotropic(moment: f32) -> Self {
+        if moment <= 0.0 {
+            return Self::zero();
+        }
+
+        let mut matrix = [[0.0; 6]; 6];
+        let mut inverse = [[0.0; 6]; 6];
+        for i in 0..6 {
+            matrix[i][i] = moment;
+            inverse[i][i] = 1.0 / moment;
+        }
+        Self { matrix, inverse }
+    }
+
+    // Builds the inertia operator from the second moment of the mass
+    // distribution, S_ij = sum of m x_i x_j, which determines it completely:
+    //   I(e_ab)_cd = S_ca d_db - S_cb d_da - S_da d_cb + S_db d_ca
+    pub fn from_second_moment(s: Matrix4<f32>) -> Self {
+        let delta = |i: usize, j: usize| if i == j { 1.0 } else { 0.0 };
+
+        let mut matrix = [[0.0; 6]; 6];
+        for (row, &(c, d)) in BASIS.iter().enumerate() {
+            for (col, &(a, b)) in BASIS.iter().enumerate() {
+                matrix[row][col] = s[c][a] * delta(d, b)
+                    - s[c][b] * delta(d, a)
+                    - s[d][a] * delta(c, b)
+                    + s[d][b] * delta(c, a);
+            }
+        }
+
+        match invert(&matrix) {
+            Some(inverse) => Self { matrix, inverse },
+            None => Self::zero(),
+        }
+    }
+
+    // A solid hypersphere. Each coordinate has a second moment of
+    // m r^2 / (n + 2) = m r^2 / 6, so every plane gets twice that.
+    pub fn sphere(mass: f32, radius: f32) -> Self {
+        Self::isotropic(mass * radius * radius / 3.0)
+    }
+
+    // A solid convex polytope of uniform density, about its centroid.
+    pub fn from_mesh(mesh: &Mesh, mass: f32) -> Self {
+        mesh.mass_properties(1.0).inertia_with_mass(mass)
+    }
+
+    pub fn apply(&self, b: &Bivec4) -> Bivec4 {
+        mul(&self.matrix, b)
+    }
+
+    pub fn apply_inverse(&self, b: &Bivec4) -> Bivec4 {
+        mul(&self.inverse, b)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::alg::Vec4;
+    use cgmath::{Vector4, Zero};
+
+    fn assert_bivec_eq(a: Bivec4, b: Bivec4) {
+        for (x, y) in to_array(&a).iter().zip(to_array(&b).iter()) {
+            assert!((x - y).abs() < 1e-4, "{:?} != {:?}", a, b);
+        }
+    }
+
+    #[test]
+    fn matches_point_masses() {
+        let points = [
+            (1.0, Vector4::new(1.0, 0.5, -0.2, 0.3)),
+            (2.0, Vector4::new(-0.4, 0.1, 0.7, -1.0)),
+            (0.5, Vector4::new(0.2, -0.9, 0.3, 0.6)),
+            (1.5, Vector4::new(0.0, 0.4, -0.8, -0.1)),
+            (1.0, Vector4::new(-0.6, 0.3, 0.2, 0.9)),
+        ];
+
+        let mut s = Matrix4::zero();
+        for (m, p) in points.iter() {
+            for i in 0..4 {
+                for j in 0..4 {
+                    s[i][j] += m * p[i] * p[j];
+                }
+            }
+        }
+        let inertia = Inertia::from_second_moment(s);
+
+        let w = Bivec4::new(0.3, -1.0, 0.5, 0.2, 0.8, -0.4);
+        let mut expected = Bivec4::zero();
+        for (m, p) in points.iter() {
+            let r = Vec4::from(*p);
+            expected = expected + *m * r.wedge_v(&r.left_contract_bv(&w));
+        }
+
+        assert_bivec_eq(inertia.apply(&w), expected);
+        assert_bivec_eq(inertia.apply_inverse(&inertia.apply(&w)), w);
+    }
+
+    #[test]
+    fn tesseract() {
+        // a tesseract with unit edges has a second moment of m / 12 along
+        // each axis
+        let inertia =
+            Inertia::from_mesh(&Mesh::from_schlafli_symbol(&[4, 3, 3]), 3.0);
+        let w = Bivec4::new(1.0, 2.0, 3.0, 4.0, 5.0, 6.0);
+        assert_bivec_eq(inertia.apply(&w), 0.5 * w);
+    }
+
+    #[test]
+    fn regular_polytopes_are_isotropic() {
+        for symbol in [[3, 3, 3], [3, 3, 4], [3, 4, 3]].iter() {
+            let inertia =
+                Inertia::from_mesh(&Mesh::from_schlafli_symbol(symbol), 1.0);
+            let moment = inertia.matrix[0][0];
+            assert!(moment > 0.0);
+            for i in 0..6 {
+                for j in 0..6 {
+                    let expected = if i == j { moment } else { 0.0 };
+                    assert!((inertia.matrix[i][j] - expected).abs() < 1e-4);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn zero_is_immovable() {
+        let w = Bivec4::new(1.0, 2.0, 3.0, 4.0, 5.0, 6.0);
+        assert_bivec_eq(Inertia::zero().apply_inverse(&w), Bivec4::zero());
+        assert_bivec_eq(
+            Inertia::isotropic(0.0).apply_inverse(&w),
+            Bivec4::zero(),
+        );
+        assert_bivec_eq(Inertia::sphere(3.0, 1.0).apply(&w), w);
+    }
+}
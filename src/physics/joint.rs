@@ -0,0 +1,742 @@
+use cgmath::{InnerSpace, Matrix, Matrix4, Vector4};
+use slotmap::{new_key_type, DenseSlotMap};
+
+use super::{Body, BodyKey, WorldSettings};
+use crate::alg::{Bivec4, Quadvec4, Rotor4, Vec4};
+use crate::util::EPSILON;
+
+new_key_type! { pub struct JointKey; }
+
+// Drives a joint's free motion at a target speed, using no more than
+// `max_force` to do so. Angular motors measure speed in radians per second.
+#[derive(Debug, Clone, Copy)]
+pub struct Motor {
+    pub speed: f32,
+    pub max_force: f32,
+}
+
+// Keeps a joint's free motion between two positions: an angle in radians for
+// ball and hinge joints, and a length for distance joints.
+#[derive(Debug, Clone, Copy)]
+pub struct Limit {
+    pub min: f32,
+    pub max: f32,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub enum JointKind {
+    // Keeps the anchors together while letting the bodies turn freely. A limit
+    // bounds how far the second body can turn away from `rotation`, its
+    // starting orientation relative to the first, and a motor turns it back
+    // towards there at up to the motor's speed.
+    Ball { rotation: Rotor4 },
+    // Keeps the anchors together and the second body at `rotation` relative
+    // to the first. Welded bodies have nothing left to limit or drive, so
+    // motors and limits are ignored.
+    Fixed { rotation: Rotor4 },
+    // Keeps the anchors `length` apart. With a limit the anchors are instead
+    // free to move anywhere between the limit's lengths, which makes a rope
+    // when the minimum is zero. Motors push the anchors apart at their speed,
+    // so they need a limit to have any room to work with.
+    Distance { length: f32 },
+    // Keeps the anchors together and only lets the second body turn in
+    // `plane`, a unit simple bivector in the first body's frame. Limits and
+    // motors work on the angle it has turned through in that plane since the
+    // joint was made.
+    Hinge { plane: Bivec4, rotation: Rotor4 },
+}
+
+#[derive(Debug, Clone)]
+pub struct Joint {
+    pub bodies: (BodyKey, BodyKey),
+    // Where the joint is attached to each body, in that body's own frame.
+    pub anchors: (Vector4<f32>, Vector4<f32>),
+    pub kind: JointKind,
+    pub motor: Option<Motor>,
+    pub limit: Option<Limit>,
+    // Jointed bodies usually overlap around the joint, so contacts between
+    // them are skipped unless this is set.
+    pub collide_connected: bool,
+}
+
+impl Joint {
+    fn new(
+        bodies: &DenseSlotMap<BodyKey, Body>,
+        (a, b): (BodyKey, BodyKey),
+        (anchor_a, anchor_b): (Vector4<f32>, Vector4<f32>),
+        kind: JointKind,
+    ) -> Self {
+        assert!(a != b, "a joint needs two different bodies");
+        Self {
+            bodies: (a, b),
+            anchors: (
+                bodies[a].world_pos_to_body(anchor_a),
+                bodies[b].world_pos_to_body(anchor_b),
+            ),
+            kind,
+            motor: None,
+            limit: None,
+            collide_connected: false,
+        }
+    }
+
+    // The constructors below take anchors in world space, with the bodies
+    // where they are now.
+    pub fn ball(
+        bodies: &DenseSlotMap<BodyKey, Body>,
+        a: BodyKey,
+        b: BodyKey,
+        anchor: Vector4<f32>,
+    ) -> Self {
+        let rotation = relative_rotation(&bodies[a], &bodies[b]);
+        Self::new(
+            bodies,
+            (a, b),
+            (anchor, anchor),
+            JointKind::Ball { rotation },
+        )
+    }
+
+    pub fn fixed(
+        bodies: &DenseSlotMap<BodyKey, Body>,
+        a: BodyKey,
+        b: BodyKey,
+        anchor: Vector4<f32>,
+    ) -> Self {
+        let rotation = relative_rotation(&bodies[a], &bodies[b]);
+        Self::new(
+            bodies,
+            (a, b),
+            (anchor, anchor),
+            JointKind::Fixed { rotation },
+        )
+    }
+
+    pub fn distance(
+        bodies: &DenseSlotMap<BodyKey, Body>,
+        a: BodyKey,
+        b: BodyKey,
+        anchor_a: Vector4<f32>,
+        anchor_b: Vector4<f32>,
+    ) -> Self {
+        let length = (anchor_b - anchor_a).magnitude();
+        Self::new(
+            bodies,
+            (a, b),
+            (anchor_a, anchor_b),
+            JointKind::Distance { length },
+        )
+    }
+
+    // A distance joint that can go slack, but never stretch past `length`.
+    pub fn rope(
+        bodies: &DenseSlotMap<BodyKey, Body>,
+        a: BodyKey,
+        b: BodyKey,
+        anchor_a: Vector4<f32>,
+        anchor_b: Vector4<f32>,
+        length: f32,
+    ) -> Self {
+        Self::distance(bodies, a, b, anchor_a, anchor_b).with_limit(Limit {
+            min: 0.0,
+            max: length,
+        })
+    }
+
+    // The plane is given in world space, and should be simple, e.g. the wedge
+    // of two vectors.
+    pub fn hinge(
+        bodies: &DenseSlotMap<BodyKey, Body>,
+        a: BodyKey,
+        b: BodyKey,
+        anchor: Vector4<f32>,
+        plane: Bivec4,
+    ) -> Self {
+        let mag = plane.mag();
+        assert!(mag > EPSILON, "hinge plane must not be zero");
+        let plane = bodies[a].rotation.reverse().rotate_bv(&plane);
+        let rotation = relative_rotation(&bodies[a], &bodies[b]);
+        Self::new(
+            bodies,
+            (a, b),
+            (anchor, anchor),
+            JointKind::Hinge {
+                plane: (1.0 / mag) * plane,
+                rotation,
+            },
+        )
+    }
+
+    pub fn with_motor(mut self, motor: Motor) -> Self {
+        self.motor = Some(motor);
+        self
+    }
+
+    pub fn with_limit(mut self, limit: Limit) -> Self {
+        self.limit = Some(limit);
+        self
+    }
+}
+
+// The rotation taking the first body's frame to the second's.
+fn relative_rotation(a: &Body, b: &Body) -> Rotor4 {
+    a.rotation.reverse() * b.rotation
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Axis {
+    // A direction the anchors move apart in.
+    Linear(Vector4<f32>),
+    // A plane the second body turns in relative to the first.
+    Angular(Bivec4),
+}
+
+// A single degree of freedom being constrained, solved the same way as the
+// normal of a contact: impulses along the axis are accumulated and clamped
+// to [min, max], with bias being the relative velocity the row aims for.
+#[derive(Debug, Clone)]
+struct Row {
+    axis: Axis,
+    mass: f32,
+    bias: f32,
+    impulse: f32,
+    min: f32,
+    max: f32,
+}
+
+// A joint set up for a single step, with everything in world space.
+pub struct JointConstraint {
+    anchors: (Vector4<f32>, Vector4<f32>),
+    rows: Vec<Row>,
+}
+
+impl JointConstraint {
+    pub fn new(
+        joint: &Joint,
+        a: &Body,
+        b: &Body,
+        settings: &WorldSettings,
+        dt: f32,
+    ) -> Self {
+        let mut constraint = Self {
+            anchors: (
+                a.body_pos_to_world(joint.anchors.0),
+                b.body_pos_to_world(joint.anchors.1),
+            ),
+            rows: Vec::new(),
+        };
+        let correction = settings.baumgarte / dt;
+        let separation = constraint.anchors.1 - constraint.anchors.0;
+        // How far the second body has turned from where it should be relative
+        // to the first, as a rotation matrix in world space.
+        let turned = |rotation: Rotor4| {
+            b.rotation.to_matrix()
+                * (a.rotation * rotation).to_matrix().transpose()
+        };
+
+        let mut locked = Vec::new();
+        let lock_point = |locked: &mut Vec<(Axis, f32)>| {
+            for i in 0..4 {
+                let mut axis = Vector4::new(0.0, 0.0, 0.0, 0.0);
+                axis[i] = 1.0;
+                locked.push((Axis::Linear(axis), separation[i]));
+            }
+        };
+
+        match joint.kind {
+            JointKind::Ball { rotation } => {
+                lock_point(&mut locked);
+
+                let error = rotation_error(&turned(rotation));
+                if error.mag() > EPSILON {
+                    let relative =
+                        b.rotation * (a.rotation * rotation).reverse();
+                    let (angle, plane) = largest_rotation(&relative, &error);
+                    let axis = Axis::Angular(plane);
+                    let motor = joint.motor.map(|motor| Motor {
+                        speed: -motor.speed.min(angle / dt),
+                        ..motor
+                    });
+                    constraint.free(a, b, axis, angle, motor, joint.limit, dt);
+                }
+            }
+            JointKind::Fixed { rotation } => {
+                lock_point(&mut locked);
+
+                let error = rotation_error(&turned(rotation));
+                for i in 0..6 {
+                    let plane = unit_bivec(i);
+                    locked.push((Axis::Angular(plane), error.dot(&plane)));
+                }
+            }
+            JointKind::Distance { length } => {
+                let distance = separation.magnitude();
+                if distance > EPSILON {
+                    let axis = Axis::Linear(separation / distance);
+                    if joint.limit.is_none() {
+                        locked.push((axis, distance - length));
+                    }
+                    constraint.free(
+                        a,
+                        b,
+                        axis,
+                        distance,
+                        joint.motor,
+                        joint.limit,
+                        dt,
+                    );
+                }
+            }
+            JointKind::Hinge { plane, rotation } => {
+                lock_point(&mut locked);
+
+                let plane = a.rotation.rotate_bv(&plane);
+                let turned = turned(rotation);
+                let (u, v) = plane_basis(&plane);
+                let angle = (turned * u).dot(v).atan2((turned * u).dot(u));
+                // Whatever is left after undoing the turn in the hinge plane
+                // is error.
+                let error = rotation_error(
+                    &(turned * plane_rotation(&plane, angle).transpose()),
+                );
+                for other in complement(&plane) {
+                    locked.push((Axis::Angular(other), error.dot(&other)));
+                }
+
+                constraint.free(
+                    a,
+                    b,
+                    Axis::Angular(plane),
+                    angle,
+                    joint.motor,
+                    joint.limit,
+                    dt,
+                );
+            }
+        }
+
+        // Locked rows go last, so that they get the final say each
+        // iteration.
+        for (axis, error) in locked {
+            constraint.push(
+                a,
+                b,
+                axis,
+                -correction * error,
+                f32::NEG_INFINITY,
+                f32::INFINITY,
+            );
+        }
+
+        constraint
+    }
+
+    // Adds the motor and limits for the joint's free motion along an axis,
+    // which is currently at `position`.
+    #[allow(clippy::too_many_arguments)]
+    fn free(
+        &mut self,
+        a: &Body,
+        b: &Body,
+        axis: Axis,
+        position: f32,
+        motor: Option<Motor>,
+        limit: Option<Limit>,
+        dt: f32,
+    ) {
+        if let Some(motor) = motor {
+            let max_impulse = motor.max_force * dt;
+            self.push(a, b, axis, motor.speed, -max_impulse, max_impulse);
+        }
+
+        // Limits can only push one way. Until the limit is reached, they
+        // allow whatever velocity would just reach it by the end of the step,
+        // which stops bodies from bouncing off limits.
+        if let Some(limit) = limit {
+            let below = position - limit.min;
+            self.push(a, b, axis, -below / dt, 0.0, f32::INFINITY);
+            let above = position - limit.max;
+            self.push(a, b, axis, -above / dt, f32::NEG_INFINITY, 0.0);
+        }
+    }
+
+    fn push(
+        &mut self,
+        a: &Body,
+        b: &Body,
+        axis: Axis,
+        bias: f32,
+        min: f32,
+        max: f32,
+    ) {
+        let inverse_mass = inverse_mass(a, self.anchors.0, axis)
+            + inverse_mass(b, self.anchors.1, axis);
+        if inverse_mass > 0.0 {
+            self.rows.push(Row {
+                axis,
+                mass: 1.0 / inverse_mass,
+                bias,
+                impulse: 0.0,
+                min,
+                max,
+            });
+        }
+    }
+
+    pub fn solve(&mut self, a: &mut Body, b: &mut Body) {
+        for row in self.rows.iter_mut() {
+            let velocity = match row.axis {
+                Axis::Linear(direction) => (b.vel_at(self.anchors.1)
+                    - a.vel_at(self.anchors.0))
+                .dot(direction),
+                Axis::Angular(plane) => (world_angular_vel(b)
+                    + world_angular_vel(a).reverse())
+                .dot(&plane),
+            };
+
+            let lambda = row.mass * (row.bias - velocity);
+            let previous = row.impulse;
+            row.impulse = (previous + lambda).clamp(row.min, row.max);
+            let delta = row.impulse - previous;
+
+            match row.axis {
+                Axis::Linear(direction) => {
+                    a.resolve_impulse(-delta * direction, self.anchors.0);
+                    b.resolve_impulse(delta * direction, self.anchors.1);
+                }
+                Axis::Angular(plane) => {
+                    a.resolve_angular_impulse(&(-delta * plane));
+                    b.resolve_angular_impulse(&(delta * plane));
+                }
+            }
+        }
+    }
+}
+
+// How much a unit impulse along the axis changes the body's velocity along
+// the same axis.
+fn inverse_mass(body: &Body, anchor: Vector4<f32>, axis: Axis) -> f32 {
+    if body.stationary {
+        return 0.0;
+    }
+
+    let (linear, angular) = match axis {
+        Axis::Linear(direction) => (
+            1.0 / body.mass,
+            Vec4::from(anchor - body.pos).wedge_v(&direction.into()),
+        ),
+        Axis::Angular(plane) => (0.0, plane),
+    };
+    let angular = body.rotation.reverse().rotate_bv(&angular);
+
+    linear + angular.dot(&body.inverse_moment_of_inertia(&angular))
+}
+
+fn world_angular_vel(body: &Body) -> Bivec4 {
+    body.rotation.rotate_bv(&body.vel.angular)
+}
+
+fn unit_bivec(i: usize) -> Bivec4 {
+    let mut c = [0.0; 6];
+    c[i] = 1.0;
+    Bivec4::new(c[0], c[1], c[2], c[3], c[4], c[5])
+}
+
+// The small rotation that a rotation matrix is close to, as the bivector of
+// its antisymmetric part. Exact for small angles, and still points the right
+// way for larger ones.
+fn rotation_error(m: &Matrix4<f32>) -> Bivec4 {
+    let c = |i: usize, j: usize| 0.5 * (m[i][j] - m[j][i]);
+    Bivec4::new(c(0, 1), c(0, 2), c(0, 3), c(1, 2), c(1, 3), c(2, 3))
+}
+
+// The two angles a rotor turns through in a pair of perpendicular planes,
+// larger first. A simple rotation has a second angle of zero.
+fn rotation_angles(r: &Rotor4) -> (f32, f32) {
+    // a rotor turning through a and b has a scalar part of cos(a/2) cos(b/2)
+    // and a quadvector part of sin(a/2) sin(b/2), up to sign
+    let (s, q) = (r.s.abs(), r.q.xyzw.abs());
+    let half_sum = (s - q).clamp(-1.0, 1.0).acos();
+    let half_difference = (s + q).clamp(-1.0, 1.0).acos();
+    (half_sum + half_difference, half_sum - half_difference)
+}
+
+// The larger of a rotor's two rotation angles, and the unit plane it turns
+// through that angle in, facing the same way as the rotation's `error`. If
+// both angles are the same the rotation has no single plane, so the error's
+// own direction is used instead.
+fn largest_rotation(r: &Rotor4, error: &Bivec4) -> (f32, Bivec4) {
+    let (larger, smaller) = rotation_angles(r);
+    // the rotor's bivector part is c1 P1 + c2 P2 for the planes P1 and P2 of
+    // the larger and smaller angles, and P2 is the dual of P1 up to sign
+    let c1 = (0.5 * larger).sin() * (0.5 * smaller).cos();
+    let c2 = (0.5 * larger).cos() * (0.5 * smaller).sin();
+    let dual = Quadvec4::new(1.0).mul_bv(&r.b);
+    let sign = r.b.dot(&dual).signum();
+    let plane = c1 * r.b + (-sign * c2) * dual;
+    if c1 * c1 - c2 * c2 < EPSILON {
+        return (larger, (1.0 / error.mag()) * *error);
+    }
+    let plane = (1.0 / plane.mag()) * plane;
+    if plane.dot(error) < 0.0 {
+        (larger, -1.0 * plane)
+    } else {
+        (larger, plane)
+    }
+}
+
+// Two orthonormal vectors in a unit simple bivector's plane, with the second
+// a quarter turn from the first in the direction of the bivector.
+fn plane_basis(plane: &Bivec4) -> (Vector4<f32>, Vector4<f32>) {
+    let u = (0..4)
+        .map(|i| {
+            let mut e = Vector4::new(0.0, 0.0, 0.0, 0.0);
+            e[i] = 1.0;
+            Vec4::from(e).left_contract_bv(plane)
+        })
+        .map(Vector4::from)
+        .max_by(|p, q| p.magnitude2().partial_cmp(&q.magnitude2()).unwrap())
+        .unwrap()
+        .normalize();
+    let v = Vector4::from(Vec4::from(u).left_contract_bv(plane)).normalize();
+    (u, v)
+}
+
+// Turns through `angle` in a unit simple bivector's plane.
+fn plane_rotation(plane: &Bivec4, angle: f32) -> Matrix4<f32> {
+    let column = |i: usize| {
+        let mut e = Vector4::new(0.0, 0.0, 0.0, 0.0);
+        e[i] = 1.0;
+        let once = Vec4::from(e).left_contract_bv(plane);
+        let twice = once.left_contract_bv(plane);
+        e + angle.sin() * Vector4::from(once)
+            + (1.0 - angle.cos()) * Vector4::from(twice)
+    };
+    Matrix4::from_cols(column(0), column(1), column(2), column(3))
+}
+
+// An orthonormal basis for the five planes perpendicular to a unit bivector.
+fn complement(plane: &Bivec4) -> Vec<Bivec4> {
+    let mut basis = vec![*plane];
+    while basis.len() < 6 {
+        let best = (0..6)
+            .map(|i| {
+                basis
+                    .iter()
+                    .fold(unit_bivec(i), |c, b| c + (-c.dot(b)) * *b)
+            })
+            .max_by(|p, q| p.mag().partial_cmp(&q.mag()).unwrap())
+            .unwrap();
+        basis.push((1.0 / best.mag()) * best);
+    }
+    basis.split_off(1)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::physics::{Collider, Inertia, PhysicsWorld};
+    use cgmath::Zero;
+
+    fn ball(pos: Vector4<f32>, stationary: bool) -> Body {
+        let collider = Collider::Sphere { radius: 0.25 };
+        Body {
+            stationary,
+            ..Body::new(collider, 1.0, Inertia::isotropic(0.1), pos)
+        }
+    }
+
+    // The angle between the body's x axis and where it started.
+    fn turned_angle(body: &Body) -> f32 {
+        body.rotation.to_matrix().x.x.clamp(-1.0, 1.0).acos()
+    }
+
+    #[test]
+    fn pendulum_keeps_its_length() {
+        let mut world = PhysicsWorld::new();
+        let pivot = world.bodies.insert(ball(Vector4::zero(), true));
+        let bob = world
+            .bodies
+            .insert(ball(Vector4::new(1.0, 0.0, 0.5, 0.0), false));
+        let joint = Joint::ball(&world.bodies, pivot, bob, Vector4::zero());
+        world.joints.insert(joint);
+
+        let mut lowest: f32 = 0.0;
+        for _ in 0..120 {
+            world.step(1.0 / 60.0);
+            let pos = world.bodies[bob].pos;
+            let length = pos.magnitude();
+            assert!((length - 1.118).abs() < 0.05, "length = {}", length);
+            lowest = lowest.min(pos.y);
+        }
+        assert!(lowest < -1.0, "lowest = {}", lowest);
+    }
+
+    #[test]
+    fn welded_body_holds_still() {
+        let mut world = PhysicsWorld::new();
+        let wall = world.bodies.insert(ball(Vector4::zero(), true));
+        let mut arm = ball(Vector4::unit_x(), false);
+        arm.vel.angular = Bivec4::new(1.0, 0.0, 2.0, 0.0, 0.0, -1.0);
+        let arm = world.bodies.insert(arm);
+        world.joints.insert(Joint::fixed(
+            &world.bodies,
+            wall,
+            arm,
+            Vector4::zero(),
+        ));
+
+        for _ in 0..120 {
+            world.step(1.0 / 60.0);
+        }
+
+        let arm = &world.bodies[arm];
+        assert!((arm.pos - Vector4::unit_x()).magnitude() < 0.05);
+        assert!(turned_angle(arm) < 0.05, "angle = {}", turned_angle(arm));
+    }
+
+    #[test]
+    fn hinge_only_turns_in_its_plane() {
+        let mut world = PhysicsWorld::new();
+        world.settings.gravity = Vector4::zero();
+        let frame = world.bodies.insert(ball(Vector4::zero(), true));
+        let mut wheel = ball(Vector4::zero(), false);
+        wheel.vel.angular = Bivec4::new(0.0, 0.0, 0.0, 0.0, 3.0, 0.0);
+        let wheel = world.bodies.insert(wheel);
+        let plane =
+            Vec4::from(Vector4::unit_x()).wedge_v(&Vector4::unit_z().into());
+        let joint =
+            Joint::hinge(&world.bodies, frame, wheel, Vector4::zero(), plane)
+                .with_motor(Motor {
+                    speed: 2.0,
+                    max_force: 100.0,
+                });
+        world.joints.insert(joint);
+
+        for _ in 0..60 {
+            world.step(1.0 / 60.0);
+        }
+
+        let vel = world_angular_vel(&world.bodies[wheel]);
+        assert!((vel.xz - 2.0).abs() < 0.05, "{:?}", vel);
+        assert!((vel + (-2.0 * plane)).mag() < 0.05, "{:?}", vel);
+        // the y and w axes are perpendicular to the hinge, so shouldn't move
+        let m = world.bodies[wheel].rotation.to_matrix();
+        assert!((m.y - Vector4::unit_y()).magnitude() < 0.01);
+        assert!((m.w - Vector4::unit_w()).magnitude() < 0.01);
+    }
+
+    #[test]
+    fn hinge_stops_at_limit() {
+        let mut world = PhysicsWorld::new();
+        world.settings.gravity = Vector4::zero();
+        let frame = world.bodies.insert(ball(Vector4::zero(), true));
+        let wheel = world.bodies.insert(ball(Vector4::zero(), false));
+        let plane =
+            Vec4::from(Vector4::unit_x()).wedge_v(&Vector4::unit_w().into());
+        let joint =
+            Joint::hinge(&world.bodies, frame, wheel, Vector4::zero(), plane)
+                .with_motor(Motor {
+                    speed: -2.0,
+                    max_force: 10.0,
+                })
+                .with_limit(Limit {
+                    min: -0.5,
+                    max: 0.5,
+                });
+        world.joints.insert(joint);
+
+        for _ in 0..120 {
+            world.step(1.0 / 60.0);
+        }
+
+        let angle = turned_angle(&world.bodies[wheel]);
+        assert!((angle - 0.5).abs() < 0.02, "angle = {}", angle);
+        // turning backwards in the plane takes x away from w
+        assert!(world.bodies[wheel].rotation.to_matrix().x.w < 0.0);
+    }
+
+    #[test]
+    fn ball_joint_limits_turning() {
+        let mut world = PhysicsWorld::new();
+        world.settings.gravity = Vector4::zero();
+        let socket = world.bodies.insert(ball(Vector4::zero(), true));
+        let mut arm = ball(Vector4::unit_y(), false);
+        arm.vel.angular = Bivec4::new(0.0, 0.0, 0.0, 0.0, 0.0, 4.0);
+        let arm = world.bodies.insert(arm);
+        let joint = Joint::ball(&world.bodies, socket, arm, Vector4::zero())
+            .with_limit(Limit { min: 0.0, max: 0.3 });
+        world.joints.insert(joint);
+
+        for _ in 0..60 {
+            world.step(1.0 / 60.0);
+        }
+
+        let m = world.bodies[arm].rotation.to_matrix();
+        let angle = m.z.z.clamp(-1.0, 1.0).acos();
+        assert!((angle - 0.3).abs() < 0.02, "angle = {}", angle);
+    }
+
+    #[test]
+    fn double_rotation_angles() {
+        let turn = Bivec4::new(0.9, 0.0, 0.0, 0.0, 0.0, 0.4);
+        let r = (-0.5 * turn).exp();
+        let (larger, smaller) = rotation_angles(&r);
+        assert!((larger - 0.9).abs() < 1e-4, "larger = {}", larger);
+        assert!((smaller - 0.4).abs() < 1e-4, "smaller = {}", smaller);
+
+        let error = rotation_error(&r.to_matrix());
+        let (angle, plane) = largest_rotation(&r, &error);
+        assert!((angle - 0.9).abs() < 1e-4, "angle = {}", angle);
+        assert!((plane.xy.abs() - 1.0).abs() < 1e-4, "plane = {:?}", plane);
+        assert!(plane.dot(&error) > 0.0);
+    }
+
+    #[test]
+    fn ball_joint_limits_double_rotations() {
+        let mut world = PhysicsWorld::new();
+        world.settings.gravity = Vector4::zero();
+        let socket = world.bodies.insert(ball(Vector4::zero(), true));
+        let mut arm = ball(Vector4::unit_y(), false);
+        arm.vel.angular = Bivec4::new(3.0, 0.0, 0.0, 0.0, 0.0, 4.0);
+        let arm = world.bodies.insert(arm);
+        let joint = Joint::ball(&world.bodies, socket, arm, Vector4::zero())
+            .with_limit(Limit { min: 0.0, max: 0.3 });
+        world.joints.insert(joint);
+
+        for _ in 0..60 {
+            world.step(1.0 / 60.0);
+        }
+
+        let (larger, _) = rotation_angles(&world.bodies[arm].rotation);
+        assert!((larger - 0.3).abs() < 0.02, "angle = {}", larger);
+    }
+
+    #[test]
+    fn rope_goes_slack_but_doesnt_stretch() {
+        let mut world = PhysicsWorld::new();
+        let hook = world.bodies.insert(ball(Vector4::zero(), true));
+        let weight = world.bodies.insert(ball(-Vector4::unit_y(), false));
+        world.joints.insert(Joint::rope(
+            &world.bodies,
+            hook,
+            weight,
+            Vector4::zero(),
+            -Vector4::unit_y(),
+            2.0,
+        ));
+
+        // falls freely while the rope is slack
+        for _ in 0..10 {
+            world.step(1.0 / 60.0);
+        }
+        let y = world.bodies[weight].pos.y;
+        assert!(y < -1.05 && y > -1.5, "y = {}", y);
+
+        for _ in 0..120 {
+            world.step(1.0 / 60.0);
+        }
+        let y = world.bodies[weight].pos.y;
+        assert!((y + 2.0).abs() < 0.05, "y = {}", y);
+    }
+}
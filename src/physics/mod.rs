@@ -1,8 +1,21 @@
 mod body;
+mod broadphase;
 mod collider;
 mod collision;
+mod convex;
 mod gjk;
+mod inertia;
+mod joint;
+mod query;
+mod world;
 
 pub use body::*;
+pub use broadphase::*;
 pub use collider::*;
 pub use collision::*;
+pub use convex::*;
+pub use gjk::*;
+pub use inertia::*;
+pub use joint::*;
+pub use query::*;
+pub use world::*;
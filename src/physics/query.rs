@@ -0,0 +1,847 @@
+// Questions about what's where in the world, for game logic and tools rather
+// than the simulation itself. Directions don't need to be normalized, and all
+// distances are measured along them in world units.
+
+use cgmath::{InnerSpace, Vector4, Zero};
+
+use super::{
+    gjk_distance, Aabb, Body, BodyKey, Collider, ColliderRef,
+    CollisionDetection, CollisionManifold, Inertia, PhysicsWorld, Support, CSO,
+};
+use crate::alg::Rotor4;
+use crate::util::{NotNaN, EPSILON};
+
+// A cast shape stops when it gets this close to something.
+const CAST_TOLERANCE: f32 = 1e-3;
+// Bounds the number of times a body is moved towards another while finding
+// when they'll touch.
+const MAX_ADVANCEMENT_STEPS: usize = 64;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct QueryHit {
+    pub key: BodyKey,
+    // Which child of a compound collider was hit, and 0 otherwise.
+    pub child: usize,
+    pub distance: f32,
+    pub point: Vector4<f32>,
+    // The outward normal of the surface that was hit.
+    pub normal: Vector4<f32>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ClosestPoints {
+    pub distance: f32,
+    // The nearest points on the first and second body to each other.
+    pub points: (Vector4<f32>, Vector4<f32>),
+}
+
+// How close two bodies are, from PhysicsWorld::closest_points.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Proximity {
+    Apart(ClosestPoints),
+    Touching,
+    // One of the keys isn't in the world, e.g. because its body has been
+    // removed, or one of the bodies is a compound with nothing in it.
+    Missing,
+}
+
+impl PhysicsWorld {
+    // Finds the first body along a ray, out to `max_distance`, for which
+    // `filter` returns true.
+    pub fn raycast<F>(
+        &self,
+        origin: Vector4<f32>,
+        dir: Vector4<f32>,
+        max_distance: f32,
+        filter: F,
+    ) -> Option<QueryHit>
+    where
+        F: FnMut(BodyKey, &Body) -> bool,
+    {
+        self.raycast_all(origin, dir, max_distance, filter)
+            .into_iter()
+            .next()
+    }
+
+    // Like raycast, but returns every body along the ray, nearest first.
+    pub fn raycast_all<F>(
+        &self,
+        origin: Vector4<f32>,
+        dir: Vector4<f32>,
+        max_distance: f32,
+        mut filter: F,
+    ) -> Vec<QueryHit>
+    where
+        F: FnMut(BodyKey, &Body) -> bool,
+    {
+        let dir = dir.normalize();
+        let mut hits: Vec<QueryHit> = self
+            .bodies
+            .iter()
+            .filter(|&(key, body)| filter(key, body))
+            .filter_map(|(key, body)| {
+                let hit = body.ray_intersect(origin, dir)?;
+                Some(QueryHit {
+                    key,
+                    child: hit.child,
+                    distance: hit.lambda,
+                    point: origin + dir * hit.lambda,
+                    normal: hit.normal,
+                })
+            })
+            .filter(|hit| hit.distance <= max_distance)
+            .collect();
+        hits.sort_by_key(|hit| NotNaN::new(hit.distance).unwrap());
+        hits
+    }
+
+    pub fn sphere_cast<F>(
+        &self,
+        origin: Vector4<f32>,
+        radius: f32,
+        dir: Vector4<f32>,
+        max_distance: f32,
+        filter: F,
+    ) -> Option<QueryHit>
+    where
+        F: FnMut(BodyKey, &Body) -> bool,
+    {
+        self.shape_cast(
+            &Collider::Sphere { radius },
+            origin,
+            Rotor4::identity(),
+            dir,
+            max_distance,
+            filter,
+        )
+    }
+
+    // Moves a collider from `origin` along `dir` without turning it, and
+    // finds the first body it would touch. The shape stops just short of that
+    // body, and the hit point and normal are those of the body's surface
+    // nearest to it. A shape which starts off touching something hits it at
+    // a distance of 0, with the point and normal of the contact. Half-spaces
+    // can't be cast, but a `max_distance` of infinity can be used to cast as
+    // far as there's anything to hit.
+    pub fn shape_cast<F>(
+        &self,
+        collider: &Collider,
+        origin: Vector4<f32>,
+        rotation: Rotor4,
+        dir: Vector4<f32>,
+        max_distance: f32,
+        mut filter: F,
+    ) -> Option<QueryHit>
+    where
+        F: FnMut(BodyKey, &Body) -> bool,
+    {
+        assert!(
+            !matches!(collider, Collider::HalfSpace { .. }),
+            "half-spaces can't be cast"
+        );
+        let dir = dir.normalize();
+        // Casting no further than the far side of the farthest body keeps an
+        // infinite cast from checking positions at infinity.
+        let max_distance = if max_distance.is_finite() {
+            max_distance
+        } else {
+            let radius = collider.bounding_radius();
+            self.bodies
+                .values()
+                .map(|body| reach(body, radius, origin, dir))
+                .fold(0.0, f32::max)
+        };
+        let mut shape = query_body(collider.clone(), origin, rotation);
+
+        shape.pos = origin + dir * max_distance;
+        let swept = shape.aabb();
+        shape.pos = origin;
+        let swept = swept.union(&shape.aabb());
+
+        let candidates: Vec<(BodyKey, &Body)> = self
+            .bodies
+            .iter()
+            .filter(|&(key, body)| {
+                swept.intersects(&body.aabb()) && filter(key, body)
+            })
+            .collect();
+        let collision = &self.collision;
+        // The deepest contact with anything, for the shape at the given
+        // distance along the cast.
+        let hit_at = |shape: &Body, distance: f32| {
+            candidates
+                .iter()
+                .flat_map(|&(key, body)| {
+                    touching(collision, body, shape)
+                        .into_iter()
+                        .map(move |manifold| (key, manifold))
+                })
+                .max_by_key(|(_, manifold)| {
+                    NotNaN::new(manifold.depth).unwrap()
+                })
+                .map(|(key, manifold)| {
+                    let mut point = Vector4::zero();
+                    for contact in manifold.contacts.iter() {
+                        point += contact.point;
+                    }
+                    QueryHit {
+                        key,
+                        child: manifold.contacts[0].children.0,
+                        distance,
+                        point: point / manifold.contacts.len() as f32,
+                        normal: manifold.normal,
+                    }
+                })
+        };
+
+        if let Some(hit) = hit_at(&shape, 0.0) {
+            return Some(hit);
+        }
+
+        let (key, impact) = candidates
+            .iter()
+            .filter_map(|&(key, body)| {
+                let impact = time_of_impact(
+                    &shape,
+                    dir,
+                    max_distance,
+                    body,
+                    CAST_TOLERANCE,
+                )?;
+                Some((key, impact))
+            })
+            .min_by_key(|(_, impact)| NotNaN::new(impact.distance).unwrap())?;
+        match impact.points {
+            Some((on_shape, on_body)) => Some(QueryHit {
+                key,
+                child: impact.child,
+                distance: impact.distance,
+                point: on_body,
+                normal: (on_shape - on_body).normalize(),
+            }),
+            // Only rounding error can take it all the way into contact, in
+            // which case there's a manifold to get the hit from instead.
+            None => {
+                shape.pos = origin + dir * impact.distance;
+                hit_at(&shape, impact.distance)
+            }
+        }
+    }
+
+    // Finds every body touching the collider where it's placed, for which
+    // `filter` returns true.
+    pub fn overlap_shape<F>(
+        &self,
+        collider: &Collider,
+        pos: Vector4<f32>,
+        rotation: Rotor4,
+        mut filter: F,
+    ) -> Vec<BodyKey>
+    where
+        F: FnMut(BodyKey, &Body) -> bool,
+    {
+        let shape = query_body(collider.clone(), pos, rotation);
+        let aabb = shape.aabb();
+        let collision = &self.collision;
+        self.bodies
+            .iter()
+            .filter(|&(key, body)| {
+                aabb.intersects(&body.aabb()) && filter(key, body)
+            })
+            .filter(|&(_, body)| !touching(collision, body, &shape).is_empty())
+            .map(|(key, _)| key)
+            .collect()
+    }
+
+    pub fn overlap_sphere<F>(
+        &self,
+        center: Vector4<f32>,
+        radius: f32,
+        filter: F,
+    ) -> Vec<BodyKey>
+    where
+        F: FnMut(BodyKey, &Body) -> bool,
+    {
+        self.overlap_shape(
+            &Collider::Sphere { radius },
+            center,
+            Rotor4::identity(),
+            filter,
+        )
+    }
+
+    // Bodies are checked against the box itself, not just their own bounding
+    // boxes.
+    pub fn overlap_aabb<F>(&self, aabb: &Aabb, filter: F) -> Vec<BodyKey>
+    where
+        F: FnMut(BodyKey, &Body) -> bool,
+    {
+        self.overlap_shape(
+            &Collider::Hyperbox {
+                half_extents: (aabb.max - aabb.min) / 2.0,
+            },
+            (aabb.min + aabb.max) / 2.0,
+            Rotor4::identity(),
+            filter,
+        )
+    }
+
+    // How close two bodies are to each other.
+    pub fn closest_points(&self, a: BodyKey, b: BodyKey) -> Proximity {
+        let (a, b) = match (self.bodies.get(a), self.bodies.get(b)) {
+            (Some(a), Some(b)) => (a, b),
+            _ => return Proximity::Missing,
+        };
+        let mut closest: Option<ClosestPoints> = None;
+        let children_a = ColliderRef::new(a).leaves();
+        let children_b = ColliderRef::new(b).leaves();
+        for &child_a in children_a.iter() {
+            for &child_b in children_b.iter() {
+                let points = match leaf_closest_points(child_a, child_b) {
+                    Some(points) => points,
+                    None => return Proximity::Touching,
+                };
+                let distance = (points.1 - points.0).magnitude();
+                if !matches!(closest, Some(c) if c.distance <= distance) {
+                    closest = Some(ClosestPoints { distance, points });
+                }
+            }
+        }
+        closest.map_or(Proximity::Missing, Proximity::Apart)
+    }
+
+    // How far apart two bodies are, 0 if they're touching, or None if either
+    // is missing.
+    pub fn distance(&self, a: BodyKey, b: BodyKey) -> Option<f32> {
+        match self.closest_points(a, b) {
+            Proximity::Apart(closest) => Some(closest.distance),
+            Proximity::Touching => Some(0.0),
+            Proximity::Missing => None,
+        }
+    }
+}
+
+// The manifolds of any contacts between a body and a query shape. The query
+// shape isn't a real body, so this leaves the SAT cache alone.
+fn touching(
+    collision: &CollisionDetection,
+    body: &Body,
+    shape: &Body,
+) -> Vec<CollisionManifold> {
+    collision
+        .detect_all_collisions_uncached(body, shape)
+        .into_iter()
+        .filter(|manifold| !manifold.contacts.is_empty())
+        .collect()
+}
+
+// How far a shape with the given bounding radius can move from `origin` along
+// `dir` before it's gone past every point of the body.
+fn reach(
+    body: &Body,
+    radius: f32,
+    origin: Vector4<f32>,
+    dir: Vector4<f32>,
+) -> f32 {
+    match &body.collider {
+        Collider::HalfSpace { normal } => {
+            let approach = -dir.dot(*normal);
+            if approach < EPSILON {
+                // moving along or away from it, so it's either touched at the
+                // start or not at all
+                0.0
+            } else {
+                ((origin - body.pos).dot(*normal) + radius) / approach
+            }
+        }
+        collider => {
+            (body.pos - origin).dot(dir) + collider.bounding_radius() + radius
+        }
+    }
+    .max(0.0)
+}
+
+// The closest points between two bodies which aren't compounds, or None if
+// they're touching. Half-spaces, spheres and meshes against spheres are
+// handled exactly, and anything else goes through GJK.
+fn leaf_closest_points(
+    a: ColliderRef,
+    b: ColliderRef,
+) -> Option<(Vector4<f32>, Vector4<f32>)> {
+    let swap = |points: Option<(Vector4<f32>, Vector4<f32>)>| {
+        points.map(|(p, q)| (q, p))
+    };
+
+    match (a.collider, b.collider) {
+        (Collider::HalfSpace { .. }, Collider::HalfSpace { .. }) => None,
+        (Collider::HalfSpace { normal }, _) => {
+            let deepest = b.support(-*normal);
+            let height = (deepest - a.pos).dot(*normal);
+            if height > 0.0 {
+                Some((deepest - *normal * height, deepest))
+            } else {
+                None
+            }
+        }
+        (_, Collider::HalfSpace { .. }) => swap(leaf_closest_points(b, a)),
+        (Collider::Sphere { radius: ra }, Collider::Sphere { radius: rb }) => {
+            let offset = b.pos - a.pos;
+            let distance = offset.magnitude();
+            if distance > ra + rb {
+                let dir = offset / distance;
+                Some((a.pos + dir * *ra, b.pos - dir * *rb))
+            } else {
+                None
+            }
+        }
+        (Collider::Mesh { mesh }, Collider::Sphere { radius }) => {
+            let nearest = a.body_pos_to_world(
+                mesh.closest_point_to(a.world_pos_to_body(b.pos)),
+            );
+            let offset = b.pos - nearest;
+            let distance = offset.magnitude();
+            if distance > *radius {
+                Some((nearest, b.pos - offset * (radius / distance)))
+            } else {
+                None
+            }
+        }
+        (Collider::Sphere { .. }, Collider::Mesh { .. }) => {
+            swap(leaf_closest_points(b, a))
+        }
+        _ => {
+            let direction = b.pos - a.pos;
+            let direction = if direction.magnitude2() > 0.0 {
+                direction
+            } else {
+                Vector4::unit_x()
+            };
+            let cso = CSO::new(a, b);
+            gjk_distance(&cso, direction)
+        }
+    }
+}
+
+// Where a body moving in a straight line first gets close to another.
+pub(super) struct Impact {
+    // How far along its path the moving body has got.
+    pub distance: f32,
+    // The child of the other body which is closest, numbered in the same
+    // order as ColliderRef::leaves.
+    pub child: usize,
+    // The closest points on the moving body and the other body, or None if
+    // they've ended up touching.
+    pub points: Option<(Vector4<f32>, Vector4<f32>)>,
+}
+
+// Moves a body along the unit vector `dir`, without turning it, until it's
+// within `tolerance` of another body, or returns None if that doesn't happen
+// in `max_distance`. This uses conservative advancement: the gap between two
+// convex shapes closes no faster than it does between their closest points,
+// so the body can always move by the gap divided by that closing speed
+// without touching anything, however thin.
+pub(super) fn time_of_impact(
+    moving: &Body,
+    dir: Vector4<f32>,
+    max_distance: f32,
+    other: &Body,
+    tolerance: f32,
+) -> Option<Impact> {
+    let others = ColliderRef::new(other).leaves();
+    let mut distance = 0.0;
+    for steps in 1..=MAX_ADVANCEMENT_STEPS {
+        let placed = ColliderRef {
+            pos: moving.pos + dir * distance,
+            ..ColliderRef::new(moving)
+        };
+        // The nearest pair of leaves, and how far the body can move before
+        // any pair could touch.
+        let mut nearest = (f32::INFINITY, 0, None);
+        let mut advance = f32::INFINITY;
+        for leaf in placed.leaves() {
+            for (child, &other_leaf) in others.iter().enumerate() {
+                let points = match leaf_closest_points(leaf, other_leaf) {
+                    Some(points) => points,
+                    None => {
+                        return Some(Impact {
+                            distance,
+                            child,
+                            points: None,
+                        })
+                    }
+                };
+                let gap = (points.1 - points.0).magnitude();
+                if gap < nearest.0 {
+                    nearest = (gap, child, Some(points));
+                }
+                // Aiming for half the tolerance rather than contact means
+                // the body stops short even when this estimate is exact.
+                if gap > EPSILON {
+                    let closing = dir.dot(points.1 - points.0) / gap;
+                    if closing > EPSILON {
+                        let target = gap - 0.5 * tolerance;
+                        advance = advance.min(target / closing);
+                    }
+                }
+            }
+        }
+
+        // Stopping short if this is taking too long is safer than going
+        // through something.
+        let (gap, child, points) = nearest;
+        if gap < tolerance || steps == MAX_ADVANCEMENT_STEPS {
+            return Some(Impact {
+                distance,
+                child,
+                points,
+            });
+        }
+        // Nothing's getting any closer.
+        if advance == f32::INFINITY {
+            return None;
+        }
+        distance += advance;
+        if distance > max_distance {
+            return None;
+        }
+    }
+    None
+}
+
+// A body to stand in for a cast shape while checking it for collisions.
+fn query_body(collider: Collider, pos: Vector4<f32>, rotation: Rotor4) -> Body {
+    Body {
+        stationary: true,
+        rotation,
+        ..Body::new(collider, 0.0, Inertia::zero(), pos)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mesh::Mesh;
+
+    fn body(collider: Collider, pos: Vector4<f32>) -> Body {
+        Body::new(collider, 1.0, Inertia::isotropic(1.0), pos)
+    }
+
+    // A floor, with a tesseract and a sphere standing on it side by side.
+    fn scene() -> (PhysicsWorld, [BodyKey; 3]) {
+        let mut world = PhysicsWorld::new();
+        let floor = world.bodies.insert(query_body(
+            Collider::HalfSpace {
+                normal: Vector4::unit_y(),
+            },
+            Vector4::zero(),
+            Rotor4::identity(),
+        ));
+        let tesseract = world.bodies.insert(body(
+            Collider::Mesh {
+                mesh: Mesh::from_schlafli_symbol(&[4, 3, 3]),
+            },
+            Vector4::new(0.0, 0.5, 0.0, 0.0),
+        ));
+        let sphere = world.bodies.insert(body(
+            Collider::Sphere { radius: 0.5 },
+            Vector4::new(3.0, 0.5, 0.0, 0.0),
+        ));
+        (world, [floor, tesseract, sphere])
+    }
+
+    #[test]
+    fn raycast_hits_nearest() {
+        let (world, [floor, tesseract, sphere]) = scene();
+        let down = -Vector4::unit_y();
+
+        let hit = world
+            .raycast(Vector4::new(0.1, 5.0, 0.0, 0.0), down, 10.0, |_, _| true)
+            .unwrap();
+        assert_eq!(hit.key, tesseract);
+        assert!((hit.distance - 4.0).abs() < 1e-4);
+        assert!((hit.point.y - 1.0).abs() < 1e-4);
+        assert!((hit.normal - Vector4::unit_y()).magnitude() < 1e-4);
+
+        // half-spaces can be hit too
+        let hit = world
+            .raycast(
+                Vector4::new(1.5, 5.0, 0.0, 0.0),
+                down * 2.0,
+                10.0,
+                |_, _| true,
+            )
+            .unwrap();
+        assert_eq!(hit.key, floor);
+        assert!((hit.distance - 5.0).abs() < 1e-4);
+        assert!((hit.normal - Vector4::unit_y()).magnitude() < 1e-4);
+
+        let start = Vector4::new(3.0, 5.0, 0.0, 0.0);
+        assert!(world.raycast(start, down, 3.0, |_, _| true).is_none());
+        let hit = world
+            .raycast(start, down, 10.0, |key, _| key != sphere)
+            .unwrap();
+        assert_eq!(hit.key, floor);
+    }
+
+    #[test]
+    fn raycast_all_is_sorted() {
+        let (world, [floor, tesseract, sphere]) = scene();
+        let hits = world.raycast_all(
+            Vector4::new(-5.0, 0.5, 0.0, 0.0),
+            Vector4::unit_x(),
+            f32::INFINITY,
+            |_, _| true,
+        );
+
+        let keys: Vec<BodyKey> = hits.iter().map(|hit| hit.key).collect();
+        assert_eq!(keys, vec![tesseract, sphere]);
+        assert!((hits[0].distance - 4.5).abs() < 1e-4);
+        assert!((hits[1].distance - 7.5).abs() < 1e-4);
+        assert!((hits[1].normal + Vector4::unit_x()).magnitude() < 1e-4);
+
+        // starting inside the floor hits it straight away
+        let hits = world.raycast_all(
+            Vector4::new(0.0, -1.0, 0.0, 0.0),
+            Vector4::unit_y(),
+            f32::INFINITY,
+            |_, _| true,
+        );
+        let keys: Vec<BodyKey> = hits.iter().map(|hit| hit.key).collect();
+        assert_eq!(keys, vec![floor, tesseract]);
+        assert_eq!(hits[0].distance, 0.0);
+        assert!((hits[0].normal + Vector4::unit_y()).magnitude() < 1e-4);
+    }
+
+    #[test]
+    fn sphere_cast_stops_at_contact() {
+        let (world, [floor, tesseract, sphere]) = scene();
+
+        let hit = world
+            .sphere_cast(
+                Vector4::new(-5.0, 0.75, 0.0, 0.0),
+                0.5,
+                Vector4::unit_x(),
+                20.0,
+                |key, _| key != floor,
+            )
+            .unwrap();
+        assert_eq!(hit.key, tesseract);
+        // the sphere's centre stops half a radius short of the tesseract
+        assert!((hit.distance - 4.0).abs() < 0.02, "{}", hit.distance);
+        assert!((hit.normal + Vector4::unit_x()).magnitude() < 0.05);
+        assert!((hit.point.x + 0.5).abs() < 0.02);
+
+        let hit = world
+            .sphere_cast(
+                Vector4::new(3.0, 5.0, 0.0, 0.0),
+                0.25,
+                -Vector4::unit_y(),
+                20.0,
+                |_, _| true,
+            )
+            .unwrap();
+        assert_eq!(hit.key, sphere);
+        assert!((hit.distance - 3.75).abs() < 0.02, "{}", hit.distance);
+    }
+
+    #[test]
+    fn overlaps() {
+        let (world, [floor, tesseract, sphere]) = scene();
+        let all = |_, _: &Body| true;
+
+        // reaches both shapes, and the floor too if it weren't filtered out
+        let mut found = world.overlap_sphere(
+            Vector4::new(1.5, 0.8, 0.0, 0.0),
+            1.1,
+            |key, _| key != floor,
+        );
+        found.sort();
+        let mut expected = vec![tesseract, sphere];
+        expected.sort();
+        assert_eq!(found, expected);
+
+        // only just out of reach of the tesseract's corner
+        let corner = Vector4::new(0.5, 1.0, 0.5, 0.5);
+        let found = world.overlap_sphere(corner * 1.5, 0.4, all);
+        assert!(found.is_empty(), "{:?}", found);
+
+        // the box's corner pokes into the sphere's bounding box, but not the
+        // sphere itself
+        let aabb = Aabb {
+            min: Vector4::new(-1.0, -1.0, -1.0, -1.0),
+            max: Vector4::new(2.6, 0.1, 0.1, 0.1),
+        };
+        let mut found = world.overlap_aabb(&aabb, all);
+        found.sort();
+        let mut expected = vec![floor, tesseract];
+        expected.sort();
+        assert_eq!(found, expected);
+
+        let found = world.overlap_shape(
+            &Collider::Capsule {
+                radius: 0.1,
+                half_height: 2.0,
+            },
+            Vector4::new(3.0, 2.0, 0.0, 0.0),
+            Rotor4::identity(),
+            |key, _| key != floor,
+        );
+        assert_eq!(found, vec![sphere]);
+    }
+
+    #[test]
+    fn closest_points_between_bodies() {
+        let (mut world, [floor, tesseract, sphere]) = scene();
+        let apart = |proximity: Proximity| match proximity {
+            Proximity::Apart(closest) => closest,
+            other => panic!("expected the bodies apart, not {:?}", other),
+        };
+
+        // tesseract to sphere, from the tesseract's face to the sphere
+        let closest = apart(world.closest_points(tesseract, sphere));
+        assert!((closest.distance - 2.0).abs() < 1e-4);
+        assert!((closest.points.0.x - 0.5).abs() < 1e-4);
+        assert!(
+            (closest.points.1 - Vector4::new(2.5, 0.5, 0.0, 0.0)).magnitude()
+                < 1e-4
+        );
+        let reverse = apart(world.closest_points(sphere, tesseract));
+        assert_eq!(reverse.points, (closest.points.1, closest.points.0));
+
+        // resting on the floor counts as touching
+        assert_eq!(world.closest_points(floor, sphere), Proximity::Touching);
+        assert_eq!(world.distance(tesseract, floor), Some(0.0));
+
+        let box_key = world.bodies.insert(body(
+            Collider::Hyperbox {
+                half_extents: Vector4::new(0.25, 0.25, 0.25, 0.25),
+            },
+            Vector4::new(0.0, 3.0, 0.0, 0.0),
+        ));
+        assert!((world.distance(box_key, floor).unwrap() - 2.75).abs() < 1e-4);
+        let closest = apart(world.closest_points(tesseract, box_key));
+        assert!((closest.distance - 1.75).abs() < 1e-3);
+        assert!((closest.points.1.y - 2.75).abs() < 1e-3);
+
+        // removed bodies and empty compounds aren't touching anything
+        world.bodies.remove(box_key);
+        assert_eq!(world.closest_points(box_key, floor), Proximity::Missing);
+        assert_eq!(world.distance(floor, box_key), None);
+        let empty = world.bodies.insert(body(
+            Collider::Compound {
+                children: Vec::new(),
+            },
+            Vector4::zero(),
+        ));
+        assert_eq!(world.closest_points(empty, sphere), Proximity::Missing);
+    }
+
+    #[test]
+    fn shape_cast_finds_floor() {
+        let (world, [floor, ..]) = scene();
+        let shape = Collider::Hyperbox {
+            half_extents: Vector4::new(0.25, 0.25, 0.25, 0.25),
+        };
+
+        let hit = world
+            .shape_cast(
+                &shape,
+                Vector4::new(-3.0, 2.0, 0.0, 0.0),
+                Rotor4::identity(),
+                -Vector4::unit_y(),
+                10.0,
+                |_, _| true,
+            )
+            .unwrap();
+        assert_eq!(hit.key, floor);
+        assert!((hit.distance - 1.75).abs() < 0.02, "{}", hit.distance);
+        assert!((hit.normal - Vector4::unit_y()).magnitude() < 1e-4);
+
+        // already touching the floor
+        let hit = world
+            .shape_cast(
+                &shape,
+                Vector4::new(-3.0, 0.2, 0.0, 0.0),
+                Rotor4::identity(),
+                Vector4::unit_x(),
+                1.0,
+                |_, _| true,
+            )
+            .unwrap();
+        assert_eq!(hit.key, floor);
+        assert_eq!(hit.distance, 0.0);
+    }
+
+    #[test]
+    fn cast_hits_thin_target() {
+        let mut world = PhysicsWorld::new();
+        let slab = |x: f32, half_thickness: f32| {
+            query_body(
+                Collider::Hyperbox {
+                    half_extents: Vector4::new(half_thickness, 1.0, 1.0, 1.0),
+                },
+                Vector4::unit_x() * x,
+                Rotor4::identity(),
+            )
+        };
+        // the thin slab falls between any 256 evenly spaced checks along
+        // the cast, which would find the wall instead
+        let thin = world.bodies.insert(slab(10.05, 0.002));
+        world.bodies.insert(slab(50.0, 1.0));
+
+        let hit = world
+            .sphere_cast(
+                Vector4::zero(),
+                0.01,
+                Vector4::unit_x(),
+                100.0,
+                |_, _| true,
+            )
+            .unwrap();
+        assert_eq!(hit.key, thin);
+        assert!((hit.distance - 10.038).abs() < 2e-3, "{}", hit.distance);
+        assert!((hit.normal + Vector4::unit_x()).magnitude() < 1e-3);
+        assert!((hit.point.x - 10.048).abs() < 1e-3, "{:?}", hit.point);
+    }
+
+    #[test]
+    fn infinite_casts() {
+        let (world, [floor, tesseract, _]) = scene();
+
+        let hit = world
+            .sphere_cast(
+                Vector4::new(0.0, 5.0, 0.0, 0.0),
+                0.25,
+                -Vector4::unit_y(),
+                f32::INFINITY,
+                |key, _| key != tesseract,
+            )
+            .unwrap();
+        assert_eq!(hit.key, floor);
+        assert!((hit.distance - 4.75).abs() < 0.02, "{}", hit.distance);
+        assert!(hit.point.magnitude().is_finite());
+
+        let hit = world
+            .sphere_cast(
+                Vector4::new(-5.0, 0.75, 0.0, 0.0),
+                0.5,
+                Vector4::unit_x(),
+                f32::INFINITY,
+                |key, _| key != floor,
+            )
+            .unwrap();
+        assert_eq!(hit.key, tesseract);
+        assert!((hit.distance - 4.0).abs() < 0.02, "{}", hit.distance);
+
+        // nothing above the scene to hit
+        assert!(world
+            .sphere_cast(
+                Vector4::new(0.0, 5.0, 0.0, 0.0),
+                0.25,
+                Vector4::unit_y(),
+                f32::INFINITY,
+                |_, _| true,
+            )
+            .is_none());
+    }
+}
@@ -0,0 +1,1048 @@
+use cgmath::{InnerSpace, Vector4};
+use serde::{Deserialize, Serialize};
+use slotmap::{new_key_type, DenseSlotMap};
+use std::collections::{HashMap, HashSet};
+
+use super::query::time_of_impact;
+use super::{
+    Body, Broadphase, Collider, CollisionConstraint, CollisionDetection,
+    CollisionManifold, ContactImpulse, Joint, JointConstraint, JointKey,
+};
+
+new_key_type! { pub struct BodyKey; }
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct WorldSettings {
+    pub gravity: Vector4<f32>,
+    pub solver_iterations: usize,
+    // Whether to start solving each contact from the impulses it ended up
+    // with in the previous step. This converges much faster for resting
+    // contacts, such as stacks.
+    pub warm_starting: bool,
+    // The fraction of the penetration depth beyond `slop` that gets corrected
+    // each step.
+    pub baumgarte: f32,
+    // How far bodies are allowed to sink into each other before position
+    // correction kicks in, which stops resting contacts from jittering.
+    pub slop: f32,
+    // Restitution is only applied to contacts approaching faster than this,
+    // so that resting bodies don't keep bouncing.
+    pub restitution_threshold: f32,
+    pub broadphase: Broadphase,
+    // Whether spheres and bullets are swept along their path each step, so
+    // that they stop at the first thing they hit rather than tunnelling
+    // through it.
+    pub continuous_collision: bool,
+    // Bodies (or rather, whole islands of touching bodies) which stay below
+    // both velocity thresholds for `time_to_sleep` seconds are put to sleep.
+    pub sleeping: bool,
+    pub sleep_linear_velocity: f32,
+    pub sleep_angular_velocity: f32,
+    pub time_to_sleep: f32,
+}
+
+impl Default for WorldSettings {
+    fn default() -> Self {
+        Self {
+            gravity: Vector4::new(0.0, -9.8, 0.0, 0.0),
+            solver_iterations: 20,
+            warm_starting: true,
+            baumgarte: 0.2,
+            slop: 0.01,
+            restitution_threshold: 1.0,
+            broadphase: Broadphase::default(),
+            continuous_collision: true,
+            sleeping: true,
+            sleep_linear_velocity: 0.05,
+            sleep_angular_velocity: 0.05,
+            time_to_sleep: 0.5,
+        }
+    }
+}
+
+// The simulation side of a world: just the bodies and the state needed to step
+// them forward. Doesn't know anything about rendering, so this can be run
+// headless.
+pub struct PhysicsWorld {
+    pub bodies: DenseSlotMap<BodyKey, Body>,
+    // Joints between bodies that have since been removed are dropped at the
+    // start of the next step.
+    pub joints: DenseSlotMap<JointKey, Joint>,
+    pub collision: CollisionDetection,
+    pub settings: WorldSettings,
+    // The impulses from the last step, for warm starting.
+    contact_impulses: HashMap<(BodyKey, BodyKey), Vec<ContactImpulse>>,
+}
+
+fn slotmap_get_mut2<K, V>(
+    map: &mut DenseSlotMap<K, V>,
+    i: K,
+    j: K,
+) -> (&mut V, &mut V)
+where
+    K: slotmap::Key + std::cmp::Eq,
+{
+    assert!(i != j);
+
+    unsafe {
+        let a = std::mem::transmute::<&mut V, &mut V>(map.get_mut(i).unwrap());
+        let b = std::mem::transmute::<&mut V, &mut V>(map.get_mut(j).unwrap());
+
+        (a, b)
+    }
+}
+
+impl Default for PhysicsWorld {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl PhysicsWorld {
+    pub fn new() -> Self {
+        Self {
+            bodies: DenseSlotMap::with_key(),
+            joints: DenseSlotMap::with_key(),
+            collision: CollisionDetection::new(),
+            settings: WorldSettings::default(),
+            contact_impulses: HashMap::new(),
+        }
+    }
+
+    // Runs the broadphase and then the narrowphase on every candidate pair,
+    // returning the pairs of bodies which are actually in contact. Bodies with
+    // compound colliders can appear more than once, once for each pair of
+    // children in contact.
+    pub fn find_collisions(
+        &mut self,
+    ) -> Vec<(BodyKey, BodyKey, CollisionManifold)> {
+        let pairs = self.settings.broadphase.candidate_pairs(&self.bodies);
+        let jointed: HashSet<(BodyKey, BodyKey)> = self
+            .joints
+            .values()
+            .filter(|joint| !joint.collide_connected)
+            .flat_map(|joint| {
+                let (ka, kb) = joint.bodies;
+                [(ka, kb), (kb, ka)]
+            })
+            .collect();
+
+        let mut collisions = Vec::new();
+        for (ka, kb) in pairs {
+            if jointed.contains(&(ka, kb)) {
+                continue;
+            }
+            let a = &self.bodies[ka];
+            let b = &self.bodies[kb];
+
+            for manifold in self.collision.detect_all_collisions((ka, kb), a, b)
+            {
+                if !manifold.contacts.is_empty() {
+                    collisions.push((ka, kb, manifold));
+                }
+            }
+        }
+
+        collisions
+    }
+
+    pub fn step(&mut self, dt: f32) {
+        // The constraints push bodies apart over a step, which can't be done
+        // if no time passes.
+        if dt.is_nan() || dt <= 0.0 {
+            return;
+        }
+
+        let bodies = &self.bodies;
+        self.joints.retain(|_, joint| {
+            bodies.contains_key(joint.bodies.0)
+                && bodies.contains_key(joint.bodies.1)
+        });
+
+        // Sleeping bodies rely on whatever they're resting on staying put, so
+        // everything wakes up if any body in contact with something has been
+        // removed, or if sleeping has been turned off.
+        let bodies = &self.bodies;
+        let removed = self
+            .contact_impulses
+            .keys()
+            .any(|(i, j)| !bodies.contains_key(*i) || !bodies.contains_key(*j));
+        if removed || !self.settings.sleeping {
+            for body in self.bodies.values_mut() {
+                if body.asleep {
+                    body.wake();
+                }
+            }
+        }
+
+        let collisions = self.find_collisions();
+        let islands = self.find_islands(&collisions);
+
+        // A sleeping body touched by anything awake gets woken up along with
+        // the rest of its island.
+        for island in islands.iter() {
+            if island.iter().any(|&key| !self.bodies[key].asleep) {
+                for &key in island.iter() {
+                    if self.bodies[key].asleep {
+                        self.bodies[key].wake();
+                    }
+                }
+            }
+        }
+
+        // Gravity goes in before solving, so that the contact constraints can
+        // cancel it out for bodies at rest, and after waking bodies up so
+        // that they don't miss out on it.
+        for body in self.bodies.values_mut() {
+            body.apply_gravity(dt, self.settings.gravity);
+        }
+
+        let mut mass_adjustments = HashMap::new();
+        for (ka, kb, _) in collisions.iter() {
+            *mass_adjustments.entry(*ka).or_insert(0) += 1;
+            *mass_adjustments.entry(*kb).or_insert(0) += 1;
+        }
+
+        let mut constraints = Vec::new();
+        for (i, j, manifold) in collisions {
+            constraints.push((
+                i,
+                j,
+                CollisionConstraint::new(
+                    manifold,
+                    &self.bodies[i],
+                    mass_adjustments[&i] as f32,
+                    &self.bodies[j],
+                    mass_adjustments[&j] as f32,
+                    &self.settings,
+                    dt,
+                ),
+            ));
+        }
+
+        // Joints between two inactive bodies have nothing to do.
+        let mut joint_constraints = Vec::new();
+        for joint in self.joints.values() {
+            let (i, j) = joint.bodies;
+            let (a, b) = (&self.bodies[i], &self.bodies[j]);
+            if a.is_active() || b.is_active() {
+                joint_constraints.push((
+                    i,
+                    j,
+                    JointConstraint::new(joint, a, b, &self.settings, dt),
+                ));
+            }
+        }
+
+        if self.settings.warm_starting {
+            for (i, j, constraint) in constraints.iter_mut() {
+                if let Some(previous) = self.contact_impulses.get(&(*i, *j)) {
+                    let (a, b) = slotmap_get_mut2(&mut self.bodies, *i, *j);
+                    constraint.warm_start(previous, a, b);
+                }
+            }
+        }
+
+        for _ in 0..self.settings.solver_iterations {
+            for (i, j, constraint) in constraints.iter_mut() {
+                let (a, b) = slotmap_get_mut2(&mut self.bodies, *i, *j);
+                constraint.solve(a, b);
+            }
+            for (i, j, constraint) in joint_constraints.iter_mut() {
+                let (a, b) = slotmap_get_mut2(&mut self.bodies, *i, *j);
+                constraint.solve(a, b);
+            }
+        }
+
+        // Contacts between sleeping bodies don't get solved, but should still
+        // be warm started once the bodies wake up again.
+        let bodies = &self.bodies;
+        self.contact_impulses.retain(|(i, j), _| {
+            match (bodies.get(*i), bodies.get(*j)) {
+                (Some(a), Some(b)) => !a.is_active() && !b.is_active(),
+                _ => false,
+            }
+        });
+        // Compound colliders can have several constraints between the same
+        // pair of bodies, told apart by the children in the contact impulses.
+        for (i, j, constraint) in constraints.iter() {
+            self.contact_impulses
+                .entry((*i, *j))
+                .or_default()
+                .extend(constraint.impulses());
+        }
+
+        let times_of_impact = if self.settings.continuous_collision {
+            self.times_of_impact(dt)
+        } else {
+            HashMap::new()
+        };
+        for (key, body) in self.bodies.iter_mut() {
+            body.step(dt * times_of_impact.get(&key).copied().unwrap_or(1.0));
+        }
+
+        if self.settings.sleeping {
+            self.update_sleep(dt, &islands);
+        }
+    }
+
+    // Finds the fraction of the step which each fast-moving sphere or bullet
+    // can move through before hitting something it isn't already touching.
+    // Only linear motion is swept, and everything else is treated as if it
+    // were standing still.
+    fn times_of_impact(&self, dt: f32) -> HashMap<BodyKey, f32> {
+        let keys: Vec<BodyKey> = self
+            .bodies
+            .iter()
+            .filter(|(_, body)| {
+                body.is_active()
+                    && (body.bullet
+                        || matches!(body.collider, Collider::Sphere { .. }))
+            })
+            .map(|(key, _)| key)
+            .collect();
+
+        keys.into_iter()
+            .filter_map(|key| Some((key, self.time_of_impact(key, dt)?)))
+            .collect()
+    }
+
+    fn time_of_impact(&self, key: BodyKey, dt: f32) -> Option<f32> {
+        let body = &self.bodies[key];
+        let motion = body.vel.linear * dt;
+        let distance = motion.magnitude();
+        let slop = self.settings.slop;
+        if distance <= slop {
+            return None;
+        }
+
+        let mut moved = body.clone();
+        moved.pos += motion;
+        let swept = body.aabb().union(&moved.aabb());
+
+        // Anything already being touched is left to the contact solver.
+        let collision = &self.collision;
+        let hit = self
+            .bodies
+            .iter()
+            .filter(|&(other_key, other)| {
+                other_key != key
+                    && swept.intersects(&other.aabb())
+                    && collision
+                        .detect_all_collisions_uncached(body, other)
+                        .iter()
+                        .all(|manifold| manifold.contacts.is_empty())
+            })
+            .filter_map(|(_, other)| {
+                time_of_impact(body, motion / distance, distance, other, slop)
+            })
+            .map(|impact| impact.distance)
+            .fold(f32::INFINITY, f32::min);
+        if hit > distance {
+            return None;
+        }
+        // The body is left just inside whatever it hit, so that the contact
+        // gets picked up and solved in the next step.
+        Some(((hit + slop) / distance).min(1.0))
+    }
+
+    // Groups the non-stationary bodies into islands of bodies which are
+    // touching or jointed to each other, directly or indirectly. Stationary
+    // bodies don't join islands together, otherwise everything on the floor
+    // would end up in one big island.
+    fn find_islands(
+        &self,
+        collisions: &[(BodyKey, BodyKey, CollisionManifold)],
+    ) -> Vec<Vec<BodyKey>> {
+        let keys: Vec<BodyKey> = self
+            .bodies
+            .iter()
+            .filter(|(_, body)| !body.stationary)
+            .map(|(key, _)| key)
+            .collect();
+        let index: HashMap<BodyKey, usize> =
+            keys.iter().enumerate().map(|(i, &key)| (key, i)).collect();
+
+        fn find(parent: &mut [usize], mut i: usize) -> usize {
+            while parent[i] != i {
+                parent[i] = parent[parent[i]];
+                i = parent[i];
+            }
+            i
+        }
+
+        let mut parent: Vec<usize> = (0..keys.len()).collect();
+        let joined = self.joints.values().map(|joint| joint.bodies);
+        let touching = collisions.iter().map(|(ka, kb, _)| (*ka, *kb));
+        for (ka, kb) in touching.chain(joined) {
+            if let (Some(&i), Some(&j)) = (index.get(&ka), index.get(&kb)) {
+                let (ri, rj) = (find(&mut parent, i), find(&mut parent, j));
+                parent[ri] = rj;
+            }
+        }
+
+        let mut islands: HashMap<usize, Vec<BodyKey>> = HashMap::new();
+        for (i, &key) in keys.iter().enumerate() {
+            let root = find(&mut parent, i);
+            islands.entry(root).or_default().push(key);
+        }
+        islands.into_values().collect()
+    }
+
+    fn update_sleep(&mut self, dt: f32, islands: &[Vec<BodyKey>]) {
+        for body in self.bodies.values_mut() {
+            if body.is_active() {
+                let slow = body.vel.linear.magnitude()
+                    < self.settings.sleep_linear_velocity
+                    && body.vel.angular.mag()
+                        < self.settings.sleep_angular_velocity;
+                if slow {
+                    body.idle_time += dt;
+                } else {
+                    body.idle_time = 0.0;
+                }
+            }
+        }
+
+        // Islands only go to sleep all at once, so that a body never gets
+        // frozen while something it's resting on is still moving.
+        for island in islands.iter() {
+            let ready = island.iter().all(|&key| {
+                let body = &self.bodies[key];
+                body.asleep || body.idle_time >= self.settings.time_to_sleep
+            });
+            if ready {
+                for &key in island.iter() {
+                    self.bodies[key].sleep();
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::alg::Rotor4;
+    use crate::mesh::Mesh;
+    use crate::physics::{
+        ChildCollider, Collider, CombineMode, Inertia, Material,
+    };
+    use cgmath::{InnerSpace, Vector4, Zero};
+    use std::collections::HashSet;
+
+    fn floor() -> Body {
+        let collider = Collider::HalfSpace {
+            normal: Vector4::unit_y(),
+        };
+        Body {
+            material: Material {
+                restitution: 0.4,
+                ..Material::default()
+            },
+            stationary: true,
+            ..Body::new(collider, 0.0, Inertia::zero(), Vector4::zero())
+        }
+    }
+
+    fn dynamic_body(collider: Collider, pos: Vector4<f32>) -> Body {
+        Body::new(collider, 1.0, Inertia::isotropic(1.0 / 6.0), pos)
+    }
+
+    #[test]
+    fn free_fall() {
+        let mut world = PhysicsWorld::new();
+        let key = world.bodies.insert(dynamic_body(
+            Collider::Sphere { radius: 0.5 },
+            Vector4::unit_y() * 100.0,
+        ));
+
+        let dt = 1.0 / 60.0;
+        for _ in 0..60 {
+            world.step(dt);
+        }
+
+        // semi-implicit euler should get us within a step of 1/2 g t^2
+        let fallen = 100.0 - world.bodies[key].pos.y;
+        assert!((fallen - 4.9).abs() < 9.8 * dt, "fell {}", fallen);
+        assert!((world.bodies[key].vel.linear.y + 9.8).abs() < 1e-3);
+    }
+
+    #[test]
+    fn sphere_rests_on_floor() {
+        let mut world = PhysicsWorld::new();
+        world.bodies.insert(floor());
+        let key = world.bodies.insert(dynamic_body(
+            Collider::Sphere { radius: 0.5 },
+            Vector4::unit_y() * 2.0,
+        ));
+
+        for _ in 0..300 {
+            world.step(1.0 / 60.0);
+        }
+
+        let sphere = &world.bodies[key];
+        assert!((sphere.pos.y - 0.5).abs() < 0.05, "y = {}", sphere.pos.y);
+        assert!(sphere.vel.linear.magnitude() < 0.2);
+    }
+
+    #[test]
+    fn tesseract_rests_on_floor() {
+        let mut world = PhysicsWorld::new();
+        world.bodies.insert(floor());
+        let key = world.bodies.insert(dynamic_body(
+            Collider::Mesh {
+                mesh: Mesh::from_schlafli_symbol(&[4, 3, 3]),
+            },
+            Vector4::unit_y() * 2.0,
+        ));
+
+        for _ in 0..300 {
+            world.step(1.0 / 60.0);
+        }
+
+        // the tesseract has unit circumradius, so its half-width is 0.5
+        let tesseract = &world.bodies[key];
+        assert!(
+            (tesseract.pos.y - 0.5).abs() < 0.05,
+            "y = {}",
+            tesseract.pos.y
+        );
+    }
+
+    fn slide_tesseract(material: Material) -> f32 {
+        let mut world = PhysicsWorld::new();
+        world.bodies.insert(floor());
+        let mut body = dynamic_body(
+            Collider::Mesh {
+                mesh: Mesh::from_schlafli_symbol(&[4, 3, 3]),
+            },
+            Vector4::unit_y() * 0.5,
+        );
+        body.material = material;
+        body.vel.linear = Vector4::unit_x() * 2.0;
+        let key = world.bodies.insert(body);
+
+        for _ in 0..120 {
+            world.step(1.0 / 60.0);
+        }
+
+        world.bodies[key].vel.linear.x
+    }
+
+    #[test]
+    fn friction_depends_on_material() {
+        let ice = Material {
+            static_friction: 0.0,
+            dynamic_friction: 0.0,
+            friction_combine: CombineMode::Min,
+            ..Material::default()
+        };
+        let rubber = Material {
+            static_friction: 1.0,
+            dynamic_friction: 0.8,
+            friction_combine: CombineMode::Max,
+            ..Material::default()
+        };
+
+        let ice_vel = slide_tesseract(ice);
+        let rubber_vel = slide_tesseract(rubber);
+        assert!((ice_vel - 2.0).abs() < 0.05, "ice vel = {}", ice_vel);
+        assert!(rubber_vel.abs() < 0.05, "rubber vel = {}", rubber_vel);
+    }
+
+    #[test]
+    fn gravity_can_point_anywhere() {
+        let mut world = PhysicsWorld::new();
+        world.settings.gravity = Vector4::new(0.0, 0.0, 0.0, -2.0);
+        let key = world.bodies.insert(dynamic_body(
+            Collider::Sphere { radius: 0.5 },
+            Vector4::zero(),
+        ));
+
+        for _ in 0..60 {
+            world.step(1.0 / 60.0);
+        }
+
+        let vel = world.bodies[key].vel.linear;
+        assert!((vel - Vector4::new(0.0, 0.0, 0.0, -2.0)).magnitude() < 1e-4);
+    }
+
+    #[test]
+    fn resting_contact_is_independent_of_dt() {
+        for &steps_per_second in [30.0, 60.0, 240.0].iter() {
+            let mut world = PhysicsWorld::new();
+            world.bodies.insert(floor());
+            let key = world.bodies.insert(dynamic_body(
+                Collider::Mesh {
+                    mesh: Mesh::from_schlafli_symbol(&[4, 3, 3]),
+                },
+                Vector4::unit_y() * 0.45,
+            ));
+
+            let dt = 1.0 / steps_per_second;
+            for _ in 0..(3.0 * steps_per_second) as usize {
+                world.step(dt);
+            }
+
+            // the sunken tesseract gets pushed back out to the slop distance
+            let y = world.bodies[key].pos.y;
+            assert!(
+                (y - 0.5).abs() < 0.03,
+                "{} Hz: y = {}",
+                steps_per_second,
+                y
+            );
+        }
+    }
+
+    #[test]
+    fn zero_dt_does_nothing() {
+        let mut world = PhysicsWorld::new();
+        world.bodies.insert(floor());
+        let key = world.bodies.insert(dynamic_body(
+            Collider::Mesh {
+                mesh: Mesh::from_schlafli_symbol(&[4, 3, 3]),
+            },
+            Vector4::unit_y() * 0.45,
+        ));
+
+        world.step(0.0);
+        world.step(-1.0 / 60.0);
+        let body = &world.bodies[key];
+        assert_eq!(body.pos, Vector4::unit_y() * 0.45);
+        assert_eq!(body.vel.linear, Vector4::zero());
+        assert_eq!(body.vel.angular.mag(), 0.0);
+    }
+
+    fn settle_tesseract(world: &mut PhysicsWorld) -> BodyKey {
+        world.bodies.insert(floor());
+        let key = world.bodies.insert(dynamic_body(
+            Collider::Mesh {
+                mesh: Mesh::from_schlafli_symbol(&[4, 3, 3]),
+            },
+            Vector4::unit_y() * 0.5,
+        ));
+        for _ in 0..180 {
+            world.step(1.0 / 60.0);
+        }
+        key
+    }
+
+    #[test]
+    fn resting_body_falls_asleep() {
+        let mut world = PhysicsWorld::new();
+        let key = settle_tesseract(&mut world);
+        assert!(world.bodies[key].asleep);
+
+        // sleeping bodies don't move, not even under gravity
+        let pos = world.bodies[key].pos;
+        world.step(1.0 / 60.0);
+        assert_eq!(world.bodies[key].pos, pos);
+
+        let mut world = PhysicsWorld::new();
+        world.settings.sleeping = false;
+        let key = settle_tesseract(&mut world);
+        assert!(!world.bodies[key].asleep);
+    }
+
+    #[test]
+    fn impulse_wakes_body() {
+        let mut world = PhysicsWorld::new();
+        let key = settle_tesseract(&mut world);
+        assert!(world.bodies[key].asleep);
+
+        let body = &mut world.bodies[key];
+        let pos = body.pos;
+        body.resolve_impulse(Vector4::unit_x(), pos);
+        assert!(!body.asleep);
+
+        world.step(1.0 / 60.0);
+        assert!(world.bodies[key].pos.x > 0.0);
+    }
+
+    #[test]
+    fn falling_body_wakes_sleeping_body() {
+        let mut world = PhysicsWorld::new();
+        let key = settle_tesseract(&mut world);
+        assert!(world.bodies[key].asleep);
+
+        let sphere = world.bodies.insert(dynamic_body(
+            Collider::Sphere { radius: 0.5 },
+            Vector4::new(0.3, 2.0, 0.0, 0.0),
+        ));
+
+        let mut woken = false;
+        for _ in 0..60 {
+            world.step(1.0 / 60.0);
+            woken |= !world.bodies[key].asleep;
+        }
+        assert!(woken);
+        assert!(world.bodies[sphere].pos.y > 1.0);
+    }
+
+    #[test]
+    fn turning_sleeping_off_wakes_bodies() {
+        let mut world = PhysicsWorld::new();
+        let key = settle_tesseract(&mut world);
+        assert!(world.bodies[key].asleep);
+
+        world.settings.sleeping = false;
+        world.step(1.0 / 60.0);
+        assert!(!world.bodies[key].asleep);
+    }
+
+    #[test]
+    fn removing_support_wakes_bodies() {
+        let mut world = PhysicsWorld::new();
+        let key = settle_tesseract(&mut world);
+        assert!(world.bodies[key].asleep);
+
+        let floor = world
+            .bodies
+            .iter()
+            .find(|(_, body)| body.stationary)
+            .map(|(key, _)| key)
+            .unwrap();
+        world.bodies.remove(floor);
+        for _ in 0..30 {
+            world.step(1.0 / 60.0);
+        }
+        assert!(world.bodies[key].pos.y < 0.0);
+    }
+
+    #[test]
+    fn woken_bodies_feel_gravity() {
+        // a sleeping tesseract in mid air, with a sphere resting against its
+        // side, both of which should start to fall together
+        let mut world = PhysicsWorld::new();
+        let mut tesseract = dynamic_body(
+            Collider::Mesh {
+                mesh: Mesh::from_schlafli_symbol(&[4, 3, 3]),
+            },
+            Vector4::unit_y() * 5.0,
+        );
+        tesseract.sleep();
+        let tesseract = world.bodies.insert(tesseract);
+        let sphere = world.bodies.insert(dynamic_body(
+            Collider::Sphere { radius: 0.5 },
+            Vector4::new(0.995, 5.0, 0.0, 0.0),
+        ));
+
+        let dt = 1.0 / 60.0;
+        world.step(dt);
+        assert!(!world.bodies[tesseract].asleep);
+        let expected = world.settings.gravity.y * dt;
+        for &key in [tesseract, sphere].iter() {
+            let vel = world.bodies[key].vel.linear;
+            assert!((vel.y - expected).abs() < 1e-4, "{:?}", vel);
+        }
+    }
+
+    fn tesseract_tower(settings: WorldSettings, height: usize) -> Vector4<f32> {
+        let mut world = PhysicsWorld::new();
+        world.settings = settings;
+        world.bodies.insert(floor());
+
+        let mesh = Mesh::from_schlafli_symbol(&[4, 3, 3]);
+        let mut top = None;
+        for i in 0..height {
+            let mut body = dynamic_body(
+                Collider::Mesh { mesh: mesh.clone() },
+                Vector4::unit_y() * (0.5 + i as f32),
+            );
+            body.inertia = Inertia::from_mesh(&mesh, 1.0);
+            top = Some(world.bodies.insert(body));
+        }
+
+        for _ in 0..300 {
+            world.step(1.0 / 60.0);
+        }
+        world.bodies[top.unwrap()].pos
+    }
+
+    #[test]
+    fn warm_started_tower_is_stable() {
+        let settings = WorldSettings {
+            sleeping: false,
+            ..WorldSettings::default()
+        };
+        let pos = tesseract_tower(settings, 4);
+
+        let expected = Vector4::unit_y() * 3.5;
+        let drift = Vector4::new(pos.x, 0.0, pos.z, pos.w).magnitude();
+        assert!((pos.y - expected.y).abs() < 0.05, "top = {:?}", pos);
+        assert!(drift < 0.05, "top = {:?}", pos);
+    }
+
+    #[test]
+    fn warm_starting_beats_cold_starting() {
+        // With only a few iterations, a tall tower only stays up if each
+        // step starts from the impulses of the last one.
+        let settings = |warm_starting| WorldSettings {
+            sleeping: false,
+            warm_starting,
+            solver_iterations: 6,
+            ..WorldSettings::default()
+        };
+
+        let warm = tesseract_tower(settings(true), 6);
+        assert!((warm.y - 5.5).abs() < 0.1, "top = {:?}", warm);
+        let cold = tesseract_tower(settings(false), 6);
+        assert!(cold.y < 5.0, "top = {:?}", cold);
+    }
+
+    #[test]
+    fn resting_contacts_keep_their_ids() {
+        let mut world = PhysicsWorld::new();
+        settle_tesseract(&mut world);
+        for body in world.bodies.values_mut() {
+            body.wake();
+        }
+
+        let ids = |world: &mut PhysicsWorld| {
+            world
+                .find_collisions()
+                .into_iter()
+                .flat_map(|(_, _, manifold)| manifold.contacts)
+                .map(|contact| contact.id)
+                .collect::<HashSet<_>>()
+        };
+
+        let before = ids(&mut world);
+        world.step(1.0 / 60.0);
+        assert!(!before.is_empty());
+        assert_eq!(before, ids(&mut world));
+    }
+
+    #[test]
+    fn primitives_rest_on_floor() {
+        // each collider along with the height of its centre when resting
+        let primitives = [
+            (
+                Collider::Hyperbox {
+                    half_extents: Vector4::new(0.5, 0.25, 0.5, 0.5),
+                },
+                0.25,
+            ),
+            (
+                Collider::Spherinder {
+                    radius: 0.5,
+                    half_height: 0.25,
+                },
+                0.25,
+            ),
+            (
+                Collider::Cubinder {
+                    radius: 0.5,
+                    half_height: 0.25,
+                },
+                0.25,
+            ),
+            (
+                Collider::Capsule {
+                    radius: 0.5,
+                    half_height: 0.25,
+                },
+                0.75,
+            ),
+            (
+                Collider::Duocylinder {
+                    radius_xy: 0.5,
+                    radius_zw: 0.5,
+                },
+                0.5,
+            ),
+        ];
+        for (collider, height) in primitives.iter() {
+            let mut world = PhysicsWorld::new();
+            world.settings.sleeping = false;
+            world.bodies.insert(floor());
+            let key = world.bodies.insert(dynamic_body(
+                collider.clone(),
+                Vector4::unit_y() * (height + 0.1),
+            ));
+            for _ in 0..120 {
+                world.step(1.0 / 60.0);
+            }
+
+            let pos = world.bodies[key].pos;
+            assert!((pos.y - height).abs() < 0.02, "pos = {:?}", pos);
+            let drift = Vector4::new(pos.x, 0.0, pos.z, pos.w).magnitude();
+            assert!(drift < 0.01, "pos = {:?}", pos);
+        }
+    }
+
+    #[test]
+    fn hyperbox_rests_on_hyperbox() {
+        let mut world = PhysicsWorld::new();
+        world.settings.sleeping = false;
+        world.bodies.insert(floor());
+        let collider = Collider::Hyperbox {
+            half_extents: Vector4::new(0.5, 0.5, 0.5, 0.5),
+        };
+        world
+            .bodies
+            .insert(dynamic_body(collider.clone(), Vector4::unit_y() * 0.5));
+        let top = world
+            .bodies
+            .insert(dynamic_body(collider, Vector4::unit_y() * 1.6));
+        for _ in 0..180 {
+            world.step(1.0 / 60.0);
+        }
+
+        let pos = world.bodies[top].pos;
+        assert!((pos.y - 1.5).abs() < 0.02, "pos = {:?}", pos);
+        let drift = Vector4::new(pos.x, 0.0, pos.z, pos.w).magnitude();
+        assert!(drift < 0.05, "pos = {:?}", pos);
+    }
+
+    // Flings a body at a thin slab from 1 unit away, and returns where it
+    // ends up.
+    fn fling_at_slab(mut body: Body, settings: WorldSettings) -> f32 {
+        let mut world = PhysicsWorld::new();
+        world.settings = settings;
+        let mut slab = floor();
+        slab.collider = Collider::Hyperbox {
+            half_extents: Vector4::new(0.05, 1.0, 1.0, 1.0),
+        };
+        world.bodies.insert(slab);
+
+        body.pos = -Vector4::unit_x();
+        body.vel.linear = Vector4::unit_x() * 200.0;
+        let key = world.bodies.insert(body);
+        for _ in 0..30 {
+            world.step(1.0 / 60.0);
+        }
+        world.bodies[key].pos.x
+    }
+
+    #[test]
+    fn fast_bodies_dont_tunnel() {
+        let settings = WorldSettings {
+            gravity: Vector4::zero(),
+            ..WorldSettings::default()
+        };
+        let sphere =
+            dynamic_body(Collider::Sphere { radius: 0.1 }, Vector4::zero());
+        let mut bullet = dynamic_body(
+            Collider::Hyperbox {
+                half_extents: Vector4::new(0.1, 0.1, 0.1, 0.1),
+            },
+            Vector4::zero(),
+        );
+        bullet.bullet = true;
+
+        for body in [sphere.clone(), bullet.clone()].iter() {
+            let x = fling_at_slab(body.clone(), settings.clone());
+            assert!(x < 0.0, "x = {}", x);
+        }
+
+        // without the sweep, they go straight through
+        let discrete = WorldSettings {
+            continuous_collision: false,
+            ..settings
+        };
+        assert!(fling_at_slab(sphere, discrete.clone()) > 1.0);
+        bullet.bullet = false;
+        assert!(fling_at_slab(bullet, WorldSettings::default()) > 1.0);
+    }
+
+    #[test]
+    fn tiny_fast_sphere_doesnt_tunnel() {
+        let mut world = PhysicsWorld::new();
+        world.settings.gravity = Vector4::zero();
+        let mut slab = floor();
+        slab.collider = Collider::Hyperbox {
+            half_extents: Vector4::new(0.002, 1.0, 1.0, 1.0),
+        };
+        world.bodies.insert(slab);
+
+        // It moves 3.1 units in the first step, which is over 300 of its own
+        // radii, at a slab much thinner than it is. Checking 64 evenly spaced
+        // points along the way would put it either side of the slab.
+        let mut sphere =
+            dynamic_body(Collider::Sphere { radius: 0.01 }, -Vector4::unit_x());
+        sphere.vel.linear = Vector4::unit_x() * 64.0 / 20.5 * 60.0;
+        let key = world.bodies.insert(sphere);
+        for _ in 0..30 {
+            world.step(1.0 / 60.0);
+        }
+        let x = world.bodies[key].pos.x;
+        assert!(x < 0.0, "x = {}", x);
+    }
+
+    #[test]
+    fn fast_sphere_stops_at_wall() {
+        let mut world = PhysicsWorld::new();
+        world.settings.gravity = Vector4::zero();
+        let mut wall = floor();
+        wall.pos = Vector4::unit_x() * 2.0;
+        wall.collider = Collider::HalfSpace {
+            normal: -Vector4::unit_x(),
+        };
+        world.bodies.insert(wall);
+
+        let mut sphere =
+            dynamic_body(Collider::Sphere { radius: 0.25 }, Vector4::zero());
+        sphere.vel.linear = Vector4::unit_x() * 200.0;
+        let key = world.bodies.insert(sphere);
+
+        world.step(1.0 / 60.0);
+        let x = world.bodies[key].pos.x;
+        assert!((x - 1.75).abs() < 0.02, "x = {}", x);
+        for _ in 0..10 {
+            world.step(1.0 / 60.0);
+        }
+        let sphere = &world.bodies[key];
+        assert!(sphere.pos.x < 1.75, "x = {}", sphere.pos.x);
+        assert!(sphere.vel.linear.x < 0.0);
+    }
+
+    #[test]
+    fn compound_rests_on_floor() {
+        // an L-shaped block made of three boxes, which only touches the floor
+        // with two of them
+        let cube = Collider::Hyperbox {
+            half_extents: Vector4::new(0.25, 0.25, 0.25, 0.25),
+        };
+        let child = |x: f32, y: f32| ChildCollider {
+            offset: Vector4::new(x, y, 0.0, 0.0),
+            rotation: Rotor4::identity(),
+            collider: cube.clone(),
+        };
+        let collider = Collider::compound(vec![
+            child(-0.5, 0.0),
+            child(0.0, 0.0),
+            child(-0.5, 0.5),
+        ])
+        .unwrap();
+
+        let mut world = PhysicsWorld::new();
+        world.settings.sleeping = false;
+        world.bodies.insert(floor());
+        let mut body = dynamic_body(collider, Vector4::unit_y() * 0.6);
+        // The body rotates about its position, so that has to be the centroid,
+        // which is a third of a box above the middle of the bottom row.
+        body.inertia = body.collider.recentre(1.0).unwrap().inertia;
+        let key = world.bodies.insert(body);
+        for _ in 0..180 {
+            world.step(1.0 / 60.0);
+        }
+
+        let pos = world.bodies[key].pos;
+        let height = 0.25 + 0.5 / 3.0;
+        assert!((pos.y - height).abs() < 0.02, "pos = {:?}", pos);
+        let drift = Vector4::new(pos.x, 0.0, pos.z, pos.w).magnitude();
+        assert!(drift < 0.05, "pos = {:?}", pos);
+
+        let children: HashSet<_> = world
+            .find_collisions()
+            .into_iter()
+            .flat_map(|(_, _, manifold)| manifold.contacts)
+            .map(|contact| contact.children)
+            .collect();
+        assert_eq!(children, [(0, 0), (0, 1)].iter().copied().collect());
+    }
+}
@@ -0,0 +1,284 @@
+// Scene descriptions, which can be loaded from and saved to RON files so that
+// scenes don't have to be hard-coded.
+
+use std::error::Error;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use cgmath::{Point3, Vector4};
+use serde::{Deserialize, Serialize};
+
+use crate::graphics::SlicePlane;
+use crate::physics::{Material, WorldSettings};
+use crate::shapes::{self, ShapeBuilder, ShapeError};
+use crate::world::Object;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Light {
+    pub position: Point3<f32>,
+    pub fovy: f32,
+    pub color: Vector4<f32>,
+}
+
+impl Default for Light {
+    fn default() -> Self {
+        Self {
+            position: Point3::new(-4.0, 10.0, -6.0),
+            fovy: 60.0,
+            color: Vector4::new(1.0, 1.0, 1.0, 1.0),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum ObjectDesc {
+    Floor {
+        size: f32,
+        material: Material,
+    },
+    Wall {
+        position: Vector4<f32>,
+        normal: Vector4<f32>,
+        material: Material,
+    },
+    // Note that shapes with a random colour get a new one every time they are
+    // built.
+    Shape(ShapeBuilder),
+}
+
+impl ObjectDesc {
+    pub fn build(&self) -> Result<Object, ShapeError> {
+        Ok(match self {
+            ObjectDesc::Floor { size, material } => {
+                shapes::create_floor(*size, material.clone())
+            }
+            ObjectDesc::Wall {
+                position,
+                normal,
+                material,
+            } => shapes::create_wall(*position, *normal, material.clone()),
+            ObjectDesc::Shape(builder) => builder.clone().build()?,
+        })
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Scene {
+    pub settings: WorldSettings,
+    pub slice_plane: SlicePlane,
+    pub light: Light,
+    pub objects: Vec<ObjectDesc>,
+}
+
+#[derive(Debug)]
+pub enum SceneError {
+    Io(io::Error),
+    Ron(ron::Error),
+    Shape(ShapeError),
+}
+
+impl fmt::Display for SceneError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SceneError::Io(e) => {
+                write!(f, "could not access scene file: {}", e)
+            }
+            SceneError::Ron(e) => write!(f, "invalid scene: {}", e),
+            SceneError::Shape(e) => write!(f, "invalid shape: {}", e),
+        }
+    }
+}
+
+impl Error for SceneError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            SceneError::Io(e) => Some(e),
+            SceneError::Ron(e) => Some(e),
+            SceneError::Shape(e) => Some(e),
+        }
+    }
+}
+
+impl From<io::Error> for SceneError {
+    fn from(e: io::Error) -> Self {
+        SceneError::Io(e)
+    }
+}
+
+impl From<ron::Error> for SceneError {
+    fn from(e: ron::Error) -> Self {
+        SceneError::Ron(e)
+    }
+}
+
+impl From<ShapeError> for SceneError {
+    fn from(e: ShapeError) -> Self {
+        SceneError::Shape(e)
+    }
+}
+
+impl Scene {
+    pub fn from_ron(s: &str) -> Result<Self, SceneError> {
+        Ok(ron::de::from_str(s)?)
+    }
+
+    pub fn to_ron(&self) -> Result<String, SceneError> {
+        Ok(ron::ser::to_string_pretty(
+            self,
+            ron::ser::PrettyConfig::default(),
+        )?)
+    }
+
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, SceneError> {
+        Self::from_ron(&fs::read_to_string(path)?)
+    }
+
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<(), SceneError> {
+        fs::write(path, self.to_ron()?)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::physics::Collider;
+    use crate::world::World;
+
+    #[test]
+    fn arena_scene_loads() {
+        let scene = Scene::from_ron(include_str!("../scenes/arena.ron"))
+            .expect("arena scene should parse");
+
+        let mut world = World::new();
+        world.load_scene(&scene).unwrap();
+
+        // a floor and six walls
+        assert_eq!(world.physics.bodies.len(), 7);
+        assert_eq!(world.meshes.len(), 1);
+        assert!(world.physics.bodies.values().all(|body| {
+            match body.collider {
+                Collider::HalfSpace { .. } => body.stationary,
+                _ => false,
+            }
+        }));
+    }
+
+    #[test]
+    fn missing_fields_are_defaulted() {
+        let scene = Scene::from_ron(
+            "(objects: [Shape((spec: Sphere(radius: 0.5), mass: 2.0))])",
+        )
+        .unwrap();
+
+        let mut world = World::new();
+        world.load_scene(&scene).unwrap();
+
+        let body = world.physics.bodies.values().next().unwrap();
+        assert_eq!(body.mass, 2.0);
+        assert_eq!(body.pos, Vector4::new(0.0, 5.0, 0.0, 0.0));
+        assert!(
+            matches!(body.collider, Collider::Sphere { radius } if radius == 0.5)
+        );
+        assert_eq!(scene.slice_plane.normal, Vector4::unit_w());
+    }
+
+    #[test]
+    fn save_captures_current_state() {
+        let mut world = World::new();
+        world.insert(shapes::create_floor(
+            8.0,
+            Material {
+                restitution: 0.4,
+                ..Material::default()
+            },
+        ));
+        let key = world.insert(
+            ShapeBuilder::new()
+                .sphere(0.5)
+                .velocity(Vector4::new(1.0, 0.0, 0.0, 0.0))
+                .build()
+                .unwrap(),
+        );
+        world.physics.bodies[key].pos = Vector4::new(1.0, 2.0, 3.0, 4.0);
+
+        let saved = world
+            .save_scene(&SlicePlane::default(), &Light::default())
+            .to_ron()
+            .unwrap();
+
+        let mut reloaded = World::new();
+        reloaded
+            .load_scene(&Scene::from_ron(&saved).unwrap())
+            .unwrap();
+        assert_eq!(reloaded.physics.bodies.len(), 2);
+        assert_eq!(reloaded.meshes.len(), 2);
+
+        let sphere = reloaded
+            .physics
+            .bodies
+            .values()
+            .find(|body| !body.stationary)
+            .unwrap();
+        assert_eq!(sphere.pos, Vector4::new(1.0, 2.0, 3.0, 4.0));
+        assert_eq!(sphere.vel.linear, Vector4::new(1.0, 0.0, 0.0, 0.0));
+    }
+
+    #[test]
+    fn loaded_polytope_is_saved() {
+        let mesh = crate::mesh::Mesh::from_schlafli_symbol(&[3, 4, 3]);
+        let mesh = crate::mesh::Mesh::from_4off(&mesh.to_4off()).unwrap();
+        let mut world = World::new();
+        world.insert(ShapeBuilder::new().polytope(&mesh).build().unwrap());
+
+        let saved = world
+            .save_scene(&SlicePlane::default(), &Light::default())
+            .to_ron()
+            .unwrap();
+        let mut reloaded = World::new();
+        reloaded
+            .load_scene(&Scene::from_ron(&saved).unwrap())
+            .unwrap();
+
+        let body = reloaded.physics.bodies.values().next().unwrap();
+        assert!(matches!(
+            &body.collider,
+            Collider::Mesh { mesh } if mesh.cells.len() == 24
+        ));
+    }
+
+    #[test]
+    fn invalid_scene_is_an_error() {
+        assert!(matches!(
+            Scene::from_ron("(objects: [Teapot])"),
+            Err(SceneError::Ron(_))
+        ));
+        assert!(matches!(
+            Scene::load("does/not/exist.ron"),
+            Err(SceneError::Io(_))
+        ));
+    }
+
+    #[test]
+    fn unbuildable_shape_is_an_error() {
+        let scene = Scene::from_ron(
+            "(objects: [Shape((spec: UniformSolid(
+                ty: EightCell,
+                rings: (false, false, false, false),
+            )))])",
+        )
+        .unwrap();
+
+        let mut world = World::new();
+        world.insert(shapes::create_floor(8.0, Material::default()));
+        assert!(matches!(
+            world.load_scene(&scene),
+            Err(SceneError::Shape(ShapeError::NoRings))
+        ));
+        // the world is left as it was
+        assert_eq!(world.physics.bodies.len(), 1);
+    }
+}
@@ -1,11 +1,17 @@
-use cgmath::{InnerSpace, Vector4, Zero};
+use cgmath::{
+    ElementWise, InnerSpace, Matrix4, SquareMatrix, Vector3, Vector4, Zero,
+};
+use serde::{Deserialize, Serialize};
+use std::error::Error;
+use std::fmt;
 
 use crate::alg::{Bivec4, Rotor4};
-use crate::context::{graphics::SlicePipeline, GraphicsContext};
-use crate::mesh::{Mesh, TetrahedronMesh};
-use crate::physics::{Body, Collider, Material, Velocity};
+use crate::mesh::{Mesh, MeshError, PrismBase, TetrahedronMesh};
+use crate::physics::{Body, Collider, Inertia, Material, Velocity};
+use crate::scene::ObjectDesc;
 use crate::world::Object;
 
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
 pub enum RegularSolid {
     FiveCell,
     EightCell,
@@ -15,37 +21,220 @@ pub enum RegularSolid {
     SixHundredCell,
 }
 
+impl RegularSolid {
+    pub fn schlafli_symbol(self) -> [usize; 3] {
+        match self {
+            RegularSolid::FiveCell => [3, 3, 3],
+            RegularSolid::EightCell => [4, 3, 3],
+            RegularSolid::SixteenCell => [3, 3, 4],
+            RegularSolid::TwentyFourCell => [3, 4, 3],
+            RegularSolid::OneTwentyCell => [5, 3, 3],
+            RegularSolid::SixHundredCell => [3, 3, 5],
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 enum ShapeSpec {
-    RegularSolid { ty: RegularSolid },
-    Sphere { radius: f32 },
+    RegularSolid {
+        ty: RegularSolid,
+    },
+    // The uniform polytope from the Coxeter-Dynkin diagram of a regular one,
+    // with the given nodes ringed.
+    UniformSolid {
+        ty: RegularSolid,
+        rings: [bool; 4],
+    },
+    Sphere {
+        radius: f32,
+    },
+    Hyperbox {
+        half_extents: Vector4<f32>,
+    },
+    Spherinder {
+        radius: f32,
+        half_height: f32,
+    },
+    Cubinder {
+        radius: f32,
+        half_height: f32,
+    },
+    Capsule {
+        radius: f32,
+        half_height: f32,
+    },
+    Duocylinder {
+        radius_xy: f32,
+        radius_zw: f32,
+    },
+    Prism {
+        base: PrismBase,
+        half_height: f32,
+    },
+    // A prism over the convex hull of the given points.
+    CustomPrism {
+        vertices: Vec<Vector3<f32>>,
+        half_height: f32,
+    },
+    Duoprism {
+        p: usize,
+        q: usize,
+    },
+    // Any convex polytope, given in the same way as Mesh::from_cells.
+    Polytope {
+        vertices: Vec<Vector4<f32>>,
+        cells: Vec<Vec<Vec<usize>>>,
+    },
 }
 
-pub fn create_floor(
-    ctx: &GraphicsContext,
-    slice_pipeline: &SlicePipeline,
-    size: f32,
-    material: Material,
-) -> Object {
-    let floor_mesh = crate::mesh4::floor(size);
-    let floor_mesh_binding = slice_pipeline.create_mesh_binding(
-        &ctx,
-        &floor_mesh.vertices,
-        &floor_mesh.indices,
-    );
+impl ShapeSpec {
+    // The same shape stretched along each axis, if it's still the same kind
+    // of shape.
+    fn scaled(&self, scale: Vector4<f32>) -> Option<Self> {
+        let Vector4 { x, y, z, w } = scale;
+        let spec = match *self {
+            ShapeSpec::Hyperbox { half_extents } => ShapeSpec::Hyperbox {
+                half_extents: half_extents.mul_element_wise(scale),
+            },
+            ShapeSpec::Sphere { radius } if x == y && x == z && x == w => {
+                ShapeSpec::Sphere { radius: radius * x }
+            }
+            ShapeSpec::Spherinder {
+                radius,
+                half_height,
+            } if x == z && x == w => ShapeSpec::Spherinder {
+                radius: radius * x,
+                half_height: half_height * y,
+            },
+            ShapeSpec::Cubinder {
+                radius,
+                half_height,
+            } if x == z && y == w => ShapeSpec::Cubinder {
+                radius: radius * x,
+                half_height: half_height * y,
+            },
+            ShapeSpec::Capsule {
+                radius,
+                half_height,
+            } if x == y && x == z && x == w => ShapeSpec::Capsule {
+                radius: radius * x,
+                half_height: half_height * x,
+            },
+            ShapeSpec::Duocylinder {
+                radius_xy,
+                radius_zw,
+            } if x == y && z == w => ShapeSpec::Duocylinder {
+                radius_xy: radius_xy * x,
+                radius_zw: radius_zw * z,
+            },
+            _ => return None,
+        };
+        Some(spec)
+    }
+}
+
+// Why a shape couldn't be built from its description.
+#[derive(Debug)]
+pub enum ShapeError {
+    // A uniform polytope needs at least one ringed node, or it's just a point.
+    NoRings,
+    // Polygons need at least three sides, for prisms, antiprisms and
+    // duoprisms.
+    TooFewSides(usize),
+    // The base of a prism has to be three-dimensional and its height has to
+    // be more than zero, or there's nothing to fill.
+    FlatPrism,
+    // Every axis has to be stretched by a finite amount more than zero.
+    InvalidScale(Vector4<f32>),
+    // A vertex of a custom prism's base with a NaN or infinite coordinate.
+    NonFiniteVertex(usize),
+    // The polytope isn't a valid convex mesh.
+    InvalidMesh(Vec<MeshError>),
+}
+
+impl fmt::Display for ShapeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ShapeError::NoRings => {
+                write!(f, "uniform solids need at least one ringed node")
+            }
+            ShapeError::TooFewSides(sides) => write!(
+                f,
+                "polygons need at least three sides, but this has {}",
+                sides
+            ),
+            ShapeError::FlatPrism => write!(f, "prism has no hypervolume"),
+            ShapeError::InvalidScale(scale) => {
+                write!(f, "scale must be finite and positive, not {:?}", scale)
+            }
+            ShapeError::NonFiniteVertex(vertex) => {
+                write!(f, "vertex {} of the prism's base isn't finite", vertex)
+            }
+            ShapeError::InvalidMesh(errors) => {
+                write!(f, "invalid polytope: ")?;
+                for (i, error) in errors.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, "; ")?;
+                    }
+                    write!(f, "{}", error)?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+impl Error for ShapeError {}
+
+fn check_sides(sides: usize) -> Result<(), ShapeError> {
+    if sides < 3 {
+        Err(ShapeError::TooFewSides(sides))
+    } else {
+        Ok(())
+    }
+}
+
+// A random colour for a cell of a mesh, which varies a bit with the cell's
+// normal, unless a colour has been given.
+fn cell_color(
+    color: Option<Vector4<f32>>,
+    normal: Vector4<f32>,
+) -> Vector4<f32> {
+    use hsl::HSL;
+
+    color.unwrap_or_else(|| {
+        let (r, g, b) = HSL {
+            h: 180.0 * (normal.z as f64 + rand::random::<f64>() * 5.0 - 2.5)
+                % 360.0
+                + 360.0,
+            s: 0.8,
+            l: 0.5 + rand::random::<f64>() * 0.1,
+        }
+        .to_rgb();
+        Vector4::new(r as f32 / 255.0, g as f32 / 255.0, b as f32 / 255.0, 1.0)
+    })
+}
+
+pub fn create_floor(size: f32, material: Material) -> Object {
+    let desc = ObjectDesc::Floor {
+        size,
+        material: material.clone(),
+    };
     Object {
         body: Body {
-            mass: 0.0,
-            moment_inertia_scalar: 0.0,
             material,
             stationary: true,
-            pos: Vector4::zero(),
-            rotation: Rotor4::identity(),
-            vel: Velocity::zero(),
-            collider: Collider::HalfSpace {
-                normal: Vector4::unit_y(),
-            },
+            ..Body::new(
+                Collider::HalfSpace {
+                    normal: Vector4::unit_y(),
+                },
+                0.0,
+                Inertia::zero(),
+                Vector4::zero(),
+            )
         },
-        mesh_binding: Some(floor_mesh_binding),
+        mesh: Some(crate::mesh4::floor(size)),
+        desc: Some(desc),
     }
 }
 
@@ -54,31 +243,46 @@ pub fn create_wall(
     normal: Vector4<f32>,
     material: Material,
 ) -> Object {
+    let desc = ObjectDesc::Wall {
+        position,
+        normal,
+        material: material.clone(),
+    };
     Object {
         body: Body {
-            mass: 0.0,
-            moment_inertia_scalar: 0.0,
             material,
             stationary: true,
-            pos: position,
-            rotation: Rotor4::identity(),
-            vel: Velocity::zero(),
-            collider: Collider::HalfSpace {
-                normal: normal.normalize(),
-            },
+            ..Body::new(
+                Collider::HalfSpace {
+                    normal: normal.normalize(),
+                },
+                0.0,
+                Inertia::zero(),
+                position,
+            )
         },
-        mesh_binding: None,
+        mesh: None,
+        desc: Some(desc),
     }
 }
 
+// Builders can be written to and read from scene files. Any field missing
+// from the file takes its default value.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
 pub struct ShapeBuilder {
     spec: ShapeSpec,
+    // Stretches the shape along each of its axes.
+    scale: Vector4<f32>,
     position: Vector4<f32>,
     rotation: Rotor4,
     velocity: Velocity,
     mass: f32,
+    // If set, the mass is worked out from the volume of the shape instead.
+    density: Option<f32>,
     material: Material,
     color: Option<Vector4<f32>>,
+    bullet: bool,
 }
 
 impl ShapeBuilder {
@@ -91,11 +295,94 @@ impl ShapeBuilder {
         self
     }
 
+    pub fn uniform_solid(mut self, ty: RegularSolid, rings: [bool; 4]) -> Self {
+        self.spec = ShapeSpec::UniformSolid { ty, rings };
+        self
+    }
+
     pub fn sphere(mut self, radius: f32) -> Self {
         self.spec = ShapeSpec::Sphere { radius };
         self
     }
 
+    pub fn hyperbox(mut self, half_extents: Vector4<f32>) -> Self {
+        self.spec = ShapeSpec::Hyperbox { half_extents };
+        self
+    }
+
+    pub fn spherinder(mut self, radius: f32, half_height: f32) -> Self {
+        self.spec = ShapeSpec::Spherinder {
+            radius,
+            half_height,
+        };
+        self
+    }
+
+    pub fn cubinder(mut self, radius: f32, half_height: f32) -> Self {
+        self.spec = ShapeSpec::Cubinder {
+            radius,
+            half_height,
+        };
+        self
+    }
+
+    pub fn capsule(mut self, radius: f32, half_height: f32) -> Self {
+        self.spec = ShapeSpec::Capsule {
+            radius,
+            half_height,
+        };
+        self
+    }
+
+    pub fn duocylinder(mut self, radius_xy: f32, radius_zw: f32) -> Self {
+        self.spec = ShapeSpec::Duocylinder {
+            radius_xy,
+            radius_zw,
+        };
+        self
+    }
+
+    // Extrudes the base along w, from -half_height to half_height.
+    pub fn prism(mut self, base: PrismBase, half_height: f32) -> Self {
+        self.spec = ShapeSpec::Prism { base, half_height };
+        self
+    }
+
+    // Like prism, but over the convex hull of any set of points. The shape is
+    // moved so that its centroid is at the body's position.
+    pub fn custom_prism(
+        mut self,
+        vertices: Vec<Vector3<f32>>,
+        half_height: f32,
+    ) -> Self {
+        self.spec = ShapeSpec::CustomPrism {
+            vertices,
+            half_height,
+        };
+        self
+    }
+
+    pub fn duoprism(mut self, p: usize, q: usize) -> Self {
+        self.spec = ShapeSpec::Duoprism { p, q };
+        self
+    }
+
+    // A copy of an existing mesh, e.g. one loaded from a file.
+    pub fn polytope(mut self, mesh: &Mesh) -> Self {
+        self.spec = ShapeSpec::Polytope {
+            vertices: mesh.vertices.clone(),
+            cells: mesh.to_cells(),
+        };
+        self
+    }
+
+    // Stretches the shape along its own axes. Round shapes which can't be
+    // stretched this way and stay round get a polytope collider instead.
+    pub fn scale(mut self, scale: Vector4<f32>) -> Self {
+        self.scale = scale;
+        self
+    }
+
     pub fn position(mut self, position: Vector4<f32>) -> Self {
         self.position = position;
         self
@@ -118,6 +405,12 @@ impl ShapeBuilder {
 
     pub fn mass(mut self, mass: f32) -> Self {
         self.mass = mass;
+        self.density = None;
+        self
+    }
+
+    pub fn density(mut self, density: f32) -> Self {
+        self.density = Some(density);
         self
     }
 
@@ -136,56 +429,139 @@ impl ShapeBuilder {
         self
     }
 
-    pub fn build(
-        self,
-        ctx: &GraphicsContext,
-        slice_pipeline: &SlicePipeline,
-    ) -> Object {
+    // Stops the shape from tunnelling through things when it moves fast,
+    // at some extra cost.
+    pub fn bullet(mut self, bullet: bool) -> Self {
+        self.bullet = bullet;
+        self
+    }
+
+    pub fn build(self) -> Result<Object, ShapeError> {
         use hsl::HSL;
 
-        let (mesh_binding, collider) = match self.spec {
+        let desc = ObjectDesc::Shape(self.clone());
+
+        let density = self.density;
+        let mass_from_volume = |volume: f32| match density {
+            Some(density) => density * volume,
+            None => self.mass,
+        };
+
+        let positive = |x: f32| x.is_finite() && x > 0.0;
+        if !positive(self.scale.x)
+            || !positive(self.scale.y)
+            || !positive(self.scale.z)
+            || !positive(self.scale.w)
+        {
+            return Err(ShapeError::InvalidScale(self.scale));
+        }
+
+        // Where possible the scale is applied to the shape's own dimensions,
+        // and otherwise to its mesh.
+        let unscaled = Vector4::new(1.0, 1.0, 1.0, 1.0);
+        let (spec, scale) = match self.spec.scaled(self.scale) {
+            Some(spec) => (spec, unscaled),
+            None => (self.spec.clone(), self.scale),
+        };
+        let stretched = scale != unscaled;
+
+        let color = self.color;
+        let polytope = |mesh: Mesh| {
+            let mut mesh = if stretched {
+                mesh.transformed(Matrix4::from_diagonal(scale))
+            } else {
+                mesh
+            };
+            // Polytopes can come from scene files, so this has to be checked
+            // even in release builds.
+            mesh.validate().map_err(ShapeError::InvalidMesh)?;
+            // Bodies rotate about their position, so that needs to be where
+            // the centroid is.
+            let props = mesh.recentre(1.0);
+            let tetrahedralized_mesh =
+                TetrahedronMesh::from_mesh(&mesh, |normal| {
+                    cell_color(color, normal)
+                });
+            let mass = mass_from_volume(props.hypervolume);
+            let inertia = props.inertia_with_mass(mass);
+            Ok((tetrahedralized_mesh, Collider::Mesh { mesh }, mass, inertia))
+        };
+
+        // The round shapes are rendered with polytopes, but collide and
+        // move like the real thing.
+        let primitive = |mesh: Mesh, collider: Collider| {
+            if stretched {
+                return polytope(mesh);
+            }
+            let tetrahedralized_mesh =
+                TetrahedronMesh::from_mesh(&mesh, |normal| {
+                    cell_color(color, normal)
+                });
+            let props = collider.mass_properties(1.0).unwrap();
+            let mass = mass_from_volume(props.hypervolume);
+            let inertia = props.inertia_with_mass(mass);
+            Ok((tetrahedralized_mesh, collider, mass, inertia))
+        };
+
+        let (tetrahedralized_mesh, collider, mass, inertia) = match spec {
             ShapeSpec::RegularSolid { ty } => {
-                let schlafli_symbol = match ty {
-                    RegularSolid::FiveCell => &[3, 3, 3],
-                    RegularSolid::EightCell => &[4, 3, 3],
-                    RegularSolid::SixteenCell => &[3, 3, 4],
-                    RegularSolid::TwentyFourCell => &[3, 4, 3],
-                    RegularSolid::OneTwentyCell => &[5, 3, 3],
-                    RegularSolid::SixHundredCell => &[3, 3, 5],
+                polytope(Mesh::from_schlafli_symbol(&ty.schlafli_symbol()))
+            }
+            ShapeSpec::UniformSolid { ty, rings } => {
+                if !rings.iter().any(|&ring| ring) {
+                    return Err(ShapeError::NoRings);
+                }
+                polytope(Mesh::from_coxeter_diagram(
+                    &ty.schlafli_symbol(),
+                    rings,
+                ))
+            }
+            ShapeSpec::Prism { base, half_height } => {
+                if let PrismBase::Prism { sides }
+                | PrismBase::Antiprism { sides } = base
+                {
+                    check_sides(sides)?;
+                }
+                polytope(
+                    Mesh::polyhedral_prism(&base.vertices(), half_height)
+                        .ok_or(ShapeError::FlatPrism)?,
+                )
+            }
+            ShapeSpec::CustomPrism {
+                ref vertices,
+                half_height,
+            } => {
+                let finite = |v: &Vector3<f32>| {
+                    v.x.is_finite() && v.y.is_finite() && v.z.is_finite()
                 };
-
-                let mesh = Mesh::from_schlafli_symbol(schlafli_symbol);
-                let color = self.color;
-                let tetrahedralized_mesh =
-                    TetrahedronMesh::from_mesh(&mesh, |normal| {
-                        color.unwrap_or_else(|| {
-                            let (r, g, b) = HSL {
-                                h: 180.0
-                                    * (normal.z as f64
-                                        + rand::random::<f64>() * 5.0
-                                        - 2.5)
-                                    % 360.0
-                                    + 360.0,
-                                s: 0.8,
-                                l: 0.5 + rand::random::<f64>() * 0.1,
-                            }
-                            .to_rgb();
-                            Vector4::new(
-                                r as f32 / 255.0,
-                                g as f32 / 255.0,
-                                b as f32 / 255.0,
-                                1.0,
-                            )
-                        })
-                    });
-                let mesh_binding = slice_pipeline.create_mesh_binding(
-                    &ctx,
-                    &tetrahedralized_mesh.vertices,
-                    &tetrahedralized_mesh.indices,
-                );
-                (mesh_binding, Collider::Mesh { mesh })
+                if let Some(vertex) = vertices.iter().position(|v| !finite(v)) {
+                    return Err(ShapeError::NonFiniteVertex(vertex));
+                }
+                polytope(
+                    Mesh::polyhedral_prism(vertices, half_height)
+                        .ok_or(ShapeError::FlatPrism)?,
+                )
+            }
+            ShapeSpec::Duoprism { p, q } => {
+                check_sides(p)?;
+                check_sides(q)?;
+                polytope(Mesh::duoprism(p, q))
             }
+            ShapeSpec::Polytope {
+                ref vertices,
+                ref cells,
+            } => polytope(
+                Mesh::try_from_cells(vertices.clone(), cells)
+                    .map_err(ShapeError::InvalidMesh)?,
+            ),
+            ShapeSpec::Sphere { radius } if stretched => polytope(
+                Mesh::from_schlafli_symbol(&[3, 3, 5])
+                    .transformed(Matrix4::from_scale(radius)),
+            ),
             ShapeSpec::Sphere { radius } => {
+                let volume =
+                    std::f32::consts::PI.powi(2) * radius.powi(4) / 2.0;
+                let mass = mass_from_volume(volume);
                 let mesh = Mesh::from_schlafli_symbol(&[3, 3, 5]);
                 let color = self.color.unwrap_or_else(|| {
                     let (r, g, b) = HSL {
@@ -204,28 +580,70 @@ impl ShapeBuilder {
                 let tetrahedralized_mesh =
                     TetrahedronMesh::from_mesh(&mesh, |_| color)
                         .make_geodesic(4, radius);
-                let mesh_binding = slice_pipeline.create_mesh_binding(
-                    &ctx,
-                    &tetrahedralized_mesh.vertices,
-                    &tetrahedralized_mesh.indices,
-                );
-                (mesh_binding, Collider::Sphere { radius })
+                Ok((
+                    tetrahedralized_mesh,
+                    Collider::Sphere { radius },
+                    mass,
+                    Inertia::sphere(mass, radius),
+                ))
             }
-        };
+            ShapeSpec::Hyperbox { half_extents } => primitive(
+                Mesh::hyperbox(half_extents),
+                Collider::Hyperbox { half_extents },
+            ),
+            ShapeSpec::Spherinder {
+                radius,
+                half_height,
+            } => primitive(
+                Mesh::spherinder(radius, half_height),
+                Collider::Spherinder {
+                    radius,
+                    half_height,
+                },
+            ),
+            ShapeSpec::Cubinder {
+                radius,
+                half_height,
+            } => primitive(
+                Mesh::cubinder(radius, half_height),
+                Collider::Cubinder {
+                    radius,
+                    half_height,
+                },
+            ),
+            ShapeSpec::Capsule {
+                radius,
+                half_height,
+            } => primitive(
+                Mesh::capsule(radius, half_height),
+                Collider::Capsule {
+                    radius,
+                    half_height,
+                },
+            ),
+            ShapeSpec::Duocylinder {
+                radius_xy,
+                radius_zw,
+            } => primitive(
+                Mesh::duocylinder(radius_xy, radius_zw),
+                Collider::Duocylinder {
+                    radius_xy,
+                    radius_zw,
+                },
+            ),
+        }?;
 
-        Object {
+        Ok(Object {
             body: Body {
-                mass: self.mass,
-                moment_inertia_scalar: self.mass / 6.0, // TODO!
                 material: self.material,
-                stationary: false,
-                pos: self.position,
+                bullet: self.bullet,
                 rotation: self.rotation,
                 vel: self.velocity,
-                collider,
+                ..Body::new(collider, mass, inertia, self.position)
             },
-            mesh_binding: Some(mesh_binding),
-        }
+            mesh: Some(tetrahedralized_mesh),
+            desc: Some(desc),
+        })
     }
 }
 
@@ -239,8 +657,151 @@ impl Default for ShapeBuilder {
             rotation: Rotor4::identity(),
             velocity: Velocity::zero(),
             mass: 1.0,
-            material: Material { restitution: 0.2 },
+            density: None,
+            material: Material::default(),
             color: None,
+            bullet: false,
+            scale: Vector4::new(1.0, 1.0, 1.0, 1.0),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn invalid_prisms_are_errors() {
+        let build = |builder: ShapeBuilder| builder.build().map(|_| ());
+        for &sides in [0, 1, 2].iter() {
+            assert!(matches!(
+                build(
+                    ShapeBuilder::new().prism(PrismBase::Prism { sides }, 0.5)
+                ),
+                Err(ShapeError::TooFewSides(s)) if s == sides
+            ));
+            assert!(matches!(
+                build(
+                    ShapeBuilder::new()
+                        .prism(PrismBase::Antiprism { sides }, 0.5)
+                ),
+                Err(ShapeError::TooFewSides(s)) if s == sides
+            ));
+            assert!(matches!(
+                build(ShapeBuilder::new().duoprism(sides, 4)),
+                Err(ShapeError::TooFewSides(s)) if s == sides
+            ));
+            assert!(matches!(
+                build(ShapeBuilder::new().duoprism(4, sides)),
+                Err(ShapeError::TooFewSides(s)) if s == sides
+            ));
+        }
+
+        // a square, which has no thickness in z
+        let square = vec![
+            Vector3::new(0.0, 0.0, 0.0),
+            Vector3::new(1.0, 0.0, 0.0),
+            Vector3::new(1.0, 1.0, 0.0),
+            Vector3::new(0.0, 1.0, 0.0),
+        ];
+        assert!(matches!(
+            build(ShapeBuilder::new().custom_prism(square, 0.5)),
+            Err(ShapeError::FlatPrism)
+        ));
+        assert!(matches!(
+            build(ShapeBuilder::new().prism(PrismBase::Cube, 0.0)),
+            Err(ShapeError::FlatPrism)
+        ));
+
+        assert!(build(ShapeBuilder::new().prism(PrismBase::Cube, 0.5)).is_ok());
+        assert!(build(ShapeBuilder::new().duoprism(3, 5)).is_ok());
+    }
+
+    #[test]
+    fn invalid_polytopes_are_errors() {
+        // bending the cells around a vertex of a tesseract
+        let mut mesh = Mesh::from_schlafli_symbol(&[4, 3, 3]);
+        mesh.vertices[0] *= 1.1;
+        assert!(matches!(
+            ShapeBuilder::new().polytope(&mesh).build(),
+            Err(ShapeError::InvalidMesh(errors)) if !errors.is_empty()
+        ));
+
+        let mut vertices = PrismBase::Cube.vertices();
+        vertices[3].y = f32::NAN;
+        assert!(matches!(
+            ShapeBuilder::new().custom_prism(vertices, 0.5).build(),
+            Err(ShapeError::NonFiniteVertex(3))
+        ));
+    }
+
+    #[test]
+    fn broken_polytope_specs_are_errors() {
+        let build = |spec: &str| {
+            let spec = format!("(spec: Polytope({}))", spec);
+            ron::de::from_str::<ShapeBuilder>(&spec).unwrap().build()
+        };
+        let point = "vertices: [(x: 0, y: 0, z: 0, w: 0)]";
+        assert!(matches!(
+            build(&format!("{}, cells: [[[0, 0, 0]]]", point)),
+            Err(ShapeError::InvalidMesh(errors)) if !errors.is_empty()
+        ));
+        assert!(matches!(
+            build(&format!("{}, cells: [[[0, 1, 2]]]", point)),
+            Err(ShapeError::InvalidMesh(errors)) if errors == vec![
+                MeshError::CellVertexOutOfRange { cell: 0, vertex: 1 },
+                MeshError::CellVertexOutOfRange { cell: 0, vertex: 2 },
+            ]
+        ));
+        assert!(matches!(
+            build(&format!("{}, cells: [[]]", point)),
+            Err(ShapeError::InvalidMesh(errors)) if !errors.is_empty()
+        ));
+    }
+
+    #[test]
+    fn scaled_domino() {
+        // a 4x1x1x0.2 domino, both as a box and as a stretched tesseract
+        let scale = Vector4::new(4.0, 1.0, 1.0, 0.2);
+        let half = Vector4::new(2.0, 0.5, 0.5, 0.1);
+        let builders = [
+            ShapeBuilder::new().hyperbox(Vector4::new(0.5, 0.5, 0.5, 0.5)),
+            ShapeBuilder::new().regular_solid(RegularSolid::EightCell),
+        ];
+        for builder in builders.iter() {
+            let body = builder.clone().scale(scale).density(2.0).build();
+            let body = body.unwrap().body;
+            let mass = 2.0 * 16.0 * half.x * half.y * half.z * half.w;
+            assert!((body.mass - mass).abs() < 1e-4, "{}", body.mass);
+
+            // the moment in each plane is the mass times the sum of the mean
+            // squares along its two axes, which are h^2 / 3 for a box
+            let h2 = half.mul_element_wise(half) / 3.0;
+            let plane = |xy, xw, zw| {
+                body.inertia.apply(&Bivec4::new(xy, 0.0, xw, 0.0, 0.0, zw))
+            };
+            let moments = [
+                (plane(1.0, 0.0, 0.0).xy, h2.x + h2.y),
+                (plane(0.0, 1.0, 0.0).xw, h2.x + h2.w),
+                (plane(0.0, 0.0, 1.0).zw, h2.z + h2.w),
+            ];
+            for &(moment, expected) in moments.iter() {
+                let expected = mass * expected;
+                assert!((moment - expected).abs() < 1e-3, "{}", moment);
+            }
+        }
+
+        for &scale in [
+            Vector4::new(1.0, 0.0, 1.0, 1.0),
+            Vector4::new(1.0, 1.0, -1.0, 1.0),
+            Vector4::new(1.0, 1.0, 1.0, f32::NAN),
+        ]
+        .iter()
+        {
+            assert!(matches!(
+                ShapeBuilder::new().sphere(1.0).scale(scale).build(),
+                Err(ShapeError::InvalidScale(_))
+            ));
         }
     }
 }
@@ -5,7 +5,7 @@ use std::hint::unreachable_unchecked;
 pub const EPSILON: f32 = 1e-6;
 
 // Wrapper around a float that implements Ord.
-#[derive(PartialOrd, PartialEq, Debug, Default, Clone, Copy)]
+#[derive(PartialEq, Debug, Default, Clone, Copy)]
 pub struct NotNaN(f32);
 
 impl NotNaN {
@@ -16,17 +16,19 @@ impl NotNaN {
             Some(NotNaN(f))
         }
     }
-
-    pub fn into_inner(self) -> f32 {
-        self.0
-    }
 }
 
 impl Eq for NotNaN {}
 
+impl PartialOrd for NotNaN {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
 impl Ord for NotNaN {
     fn cmp(&self, other: &Self) -> Ordering {
-        match self.partial_cmp(other) {
+        match self.0.partial_cmp(&other.0) {
             Some(ord) => ord,
             None => unsafe { unreachable_unchecked() },
         }
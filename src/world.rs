@@ -1,180 +1,275 @@
-use slotmap::{new_key_type, DenseSlotMap};
-use std::collections::HashMap;
-
-use crate::context::{
-    graphics::{
-        MeshBinding, ShadowPipeline, SlicePipeline, SlicePlane, Transform4,
-        TriangleListPipeline,
-    },
-    GraphicsContext,
+use std::io::{self, Write};
+
+use cgmath::Vector4;
+use slotmap::SecondaryMap;
+
+use crate::alg::Rotor4;
+use crate::export::{self, SliceFormat};
+use crate::graphics::{SlicePlane, Transform4, Vertex3};
+use crate::mesh::TetrahedronMesh;
+use crate::physics::{
+    Aabb, Body, BodyKey, Collider, PhysicsWorld, Proximity, QueryHit,
 };
-use crate::physics::{Body, CollisionConstraint, CollisionDetection};
+use crate::scene::{Light, ObjectDesc, Scene, SceneError};
+
+// Every object in the world is backed by a body, so they share keys.
+pub type ObjectKey = BodyKey;
 
 pub struct Object {
     pub body: Body,
-    pub mesh_binding: Option<MeshBinding>,
+    // The tetrahedral mesh used for rendering, if this object is visible at
+    // all. Uploading this to the GPU is left up to the renderer.
+    pub mesh: Option<TetrahedronMesh>,
+    // How to recreate this object when loading a scene. Objects without a
+    // description are left out of saved scenes.
+    pub desc: Option<ObjectDesc>,
 }
 
-impl Object {
-    pub fn compute(
-        &self,
-        graphics_ctx: &GraphicsContext,
-        pipeline: &SlicePipeline,
-        encoder: &mut wgpu::CommandEncoder,
-        slice_plane: &SlicePlane,
-    ) {
-        if let Some(mesh_binding) = &self.mesh_binding {
-            let transform = Transform4 {
-                displacement: self.body.pos,
-                transform: self.body.rotation.to_matrix(),
-            };
-            pipeline.render_mesh(
-                graphics_ctx,
-                encoder,
-                slice_plane,
-                &transform,
-                mesh_binding,
-            );
-        }
+pub struct World {
+    pub physics: PhysicsWorld,
+    pub meshes: SecondaryMap<ObjectKey, TetrahedronMesh>,
+    pub descs: SecondaryMap<ObjectKey, ObjectDesc>,
+}
+
+impl Default for World {
+    fn default() -> Self {
+        Self::new()
     }
+}
 
-    pub fn render<'a: 'c, 'b, 'c>(
-        &'a self,
-        pipeline: &'a TriangleListPipeline,
-        render_pass: &'b mut wgpu::RenderPass<'c>,
-    ) {
-        if let Some(mesh_binding) = &self.mesh_binding {
-            pipeline.render(render_pass, mesh_binding);
+impl World {
+    pub fn new() -> Self {
+        Self {
+            physics: PhysicsWorld::new(),
+            meshes: SecondaryMap::new(),
+            descs: SecondaryMap::new(),
         }
     }
 
-    pub fn shadow_pass<'a: 'c, 'b, 'c>(
-        &'a self,
-        pipeline: &'a ShadowPipeline,
-        render_pass: &'b mut wgpu::RenderPass<'c>,
-    ) {
-        if let Some(mesh_binding) = &self.mesh_binding {
-            pipeline.render(render_pass, mesh_binding);
+    pub fn insert(&mut self, object: Object) -> ObjectKey {
+        let key = self.physics.bodies.insert(object.body);
+        if let Some(mesh) = object.mesh {
+            self.meshes.insert(key, mesh);
+        }
+        if let Some(desc) = object.desc {
+            self.descs.insert(key, desc);
         }
+        key
     }
-}
 
-new_key_type! { pub struct ObjectKey; }
+    pub fn remove(&mut self, key: ObjectKey) -> Option<Object> {
+        let body = self.physics.bodies.remove(key)?;
+        self.physics
+            .joints
+            .retain(|_, joint| joint.bodies.0 != key && joint.bodies.1 != key);
+        let mesh = self.meshes.remove(key);
+        let desc = self.descs.remove(key);
+        Some(Object { body, mesh, desc })
+    }
 
-pub struct World {
-    pub objects: DenseSlotMap<ObjectKey, Object>,
-    pub collision: CollisionDetection,
-}
+    pub fn clear(&mut self) {
+        self.physics.bodies.clear();
+        self.physics.joints.clear();
+        self.meshes.clear();
+        self.descs.clear();
+    }
 
-fn slotmap_get_mut2<K, V>(
-    map: &mut DenseSlotMap<K, V>,
-    i: K,
-    j: K,
-) -> (&mut V, &mut V)
-where
-    K: slotmap::Key + std::cmp::Eq,
-{
-    assert!(i != j);
+    // Replaces the contents of the world with the objects in the scene. The
+    // slice plane and light are left for the caller to apply. If any object
+    // can't be built, the world is left as it was.
+    pub fn load_scene(&mut self, scene: &Scene) -> Result<(), SceneError> {
+        let objects = scene
+            .objects
+            .iter()
+            .map(ObjectDesc::build)
+            .collect::<Result<Vec<_>, _>>()?;
+        self.clear();
+        self.physics.settings = scene.settings.clone();
+        for object in objects {
+            self.insert(object);
+        }
+        Ok(())
+    }
 
-    unsafe {
-        let a = std::mem::transmute::<_, _>(map.get_mut(i).unwrap());
-        let b = std::mem::transmute::<_, _>(map.get_mut(j).unwrap());
+    // Captures the current state of every object in the world that has a
+    // description, so that shapes are saved where they are now rather than
+    // where they were spawned.
+    pub fn save_scene(&self, slice_plane: &SlicePlane, light: &Light) -> Scene {
+        let objects = self
+            .physics
+            .bodies
+            .iter()
+            .filter_map(|(key, body)| {
+                let desc = match self.descs.get(key)? {
+                    ObjectDesc::Shape(builder) => ObjectDesc::Shape(
+                        builder
+                            .clone()
+                            .position(body.pos)
+                            .rotation(body.rotation)
+                            .velocity(body.vel.linear)
+                            .angular_velocity(body.vel.angular),
+                    ),
+                    desc => desc.clone(),
+                };
+                Some(desc)
+            })
+            .collect();
 
-        return (a, b);
+        Scene {
+            settings: self.physics.settings.clone(),
+            slice_plane: *slice_plane,
+            light: light.clone(),
+            objects,
+        }
     }
-}
 
-impl World {
-    pub fn new() -> Self {
-        Self {
-            objects: DenseSlotMap::with_key(),
-            collision: CollisionDetection::new(),
-        }
+    pub fn transform(&self, key: ObjectKey) -> Option<Transform4> {
+        self.physics.bodies.get(key).map(|body| Transform4 {
+            displacement: body.pos,
+            transform: body.rotation.to_matrix(),
+        })
     }
 
     pub fn update(&mut self, dt: f32) {
-        let mut collisions = Vec::new();
-        let mut mass_adjustments = HashMap::new();
-
-        let object_keys: Vec<_> = self.objects.keys().collect();
-
-        for i in 0..object_keys.len() {
-            for j in i + 1..object_keys.len() {
-                let ka = object_keys[i];
-                let kb = object_keys[j];
-                let a = &self.objects[ka];
-                let b = &self.objects[kb];
-
-                if let Some(manifold) =
-                    self.collision.detect_collisions((ka, kb), &a.body, &b.body)
-                {
-                    if manifold.contacts.len() == 0 {
-                        continue;
-                    }
-                    *mass_adjustments.entry(ka).or_insert(0) += 1;
-                    *mass_adjustments.entry(kb).or_insert(0) += 1;
-                    collisions.push((ka, kb, manifold));
-                }
-            }
-        }
+        self.physics.step(dt);
+    }
 
-        let mut constraints = Vec::new();
-        for (i, j, manifold) in collisions {
-            constraints.push((
-                i,
-                j,
-                CollisionConstraint::new(
-                    manifold,
-                    &self.objects[i].body,
-                    mass_adjustments[&i] as f32,
-                    &self.objects[j].body,
-                    mass_adjustments[&j] as f32,
-                ),
-            ));
-        }
+    // Scene queries, which are described in more detail on PhysicsWorld.
+    // Only objects for which `filter` returns true are considered.
 
-        const SOLVER_ITERS: usize = 20;
-        for _ in 0..SOLVER_ITERS {
-            for (i, j, constraint) in constraints.iter_mut() {
-                let (a, b) = slotmap_get_mut2(&mut self.objects, *i, *j);
-                constraint.solve(&mut a.body, &mut b.body);
-            }
-        }
+    pub fn raycast<F>(
+        &self,
+        origin: Vector4<f32>,
+        dir: Vector4<f32>,
+        max_distance: f32,
+        filter: F,
+    ) -> Option<QueryHit>
+    where
+        F: FnMut(ObjectKey, &Body) -> bool,
+    {
+        self.physics.raycast(origin, dir, max_distance, filter)
+    }
 
-        for object in self.objects.values_mut() {
-            object.body.step(dt);
-        }
+    pub fn raycast_all<F>(
+        &self,
+        origin: Vector4<f32>,
+        dir: Vector4<f32>,
+        max_distance: f32,
+        filter: F,
+    ) -> Vec<QueryHit>
+    where
+        F: FnMut(ObjectKey, &Body) -> bool,
+    {
+        self.physics.raycast_all(origin, dir, max_distance, filter)
     }
 
-    pub fn compute(
+    pub fn sphere_cast<F>(
         &self,
-        graphics_ctx: &GraphicsContext,
-        pipeline: &SlicePipeline,
-        encoder: &mut wgpu::CommandEncoder,
-        slice_plane: &SlicePlane,
-    ) {
-        for i in self.objects.values() {
-            i.compute(graphics_ctx, pipeline, encoder, slice_plane);
-        }
+        origin: Vector4<f32>,
+        radius: f32,
+        dir: Vector4<f32>,
+        max_distance: f32,
+        filter: F,
+    ) -> Option<QueryHit>
+    where
+        F: FnMut(ObjectKey, &Body) -> bool,
+    {
+        self.physics
+            .sphere_cast(origin, radius, dir, max_distance, filter)
     }
 
-    pub fn render<'a: 'c, 'b, 'c>(
-        &'a self,
-        pipeline: &'a TriangleListPipeline,
-        render_pass: &'b mut wgpu::RenderPass<'c>,
-    ) {
-        for i in self.objects.values() {
-            i.render(pipeline, render_pass);
-        }
+    pub fn shape_cast<F>(
+        &self,
+        collider: &Collider,
+        origin: Vector4<f32>,
+        rotation: Rotor4,
+        dir: Vector4<f32>,
+        max_distance: f32,
+        filter: F,
+    ) -> Option<QueryHit>
+    where
+        F: FnMut(ObjectKey, &Body) -> bool,
+    {
+        self.physics.shape_cast(
+            collider,
+            origin,
+            rotation,
+            dir,
+            max_distance,
+            filter,
+        )
     }
 
-    pub fn shadow_pass<'a: 'c, 'b, 'c>(
-        &'a self,
-        pipeline: &'a ShadowPipeline,
-        render_pass: &'b mut wgpu::RenderPass<'c>,
-    ) {
-        for i in self.objects.values() {
-            i.shadow_pass(pipeline, render_pass);
-        }
+    pub fn overlap_shape<F>(
+        &self,
+        collider: &Collider,
+        pos: Vector4<f32>,
+        rotation: Rotor4,
+        filter: F,
+    ) -> Vec<ObjectKey>
+    where
+        F: FnMut(ObjectKey, &Body) -> bool,
+    {
+        self.physics.overlap_shape(collider, pos, rotation, filter)
+    }
+
+    pub fn overlap_sphere<F>(
+        &self,
+        center: Vector4<f32>,
+        radius: f32,
+        filter: F,
+    ) -> Vec<ObjectKey>
+    where
+        F: FnMut(ObjectKey, &Body) -> bool,
+    {
+        self.physics.overlap_sphere(center, radius, filter)
+    }
+
+    pub fn overlap_aabb<F>(&self, aabb: &Aabb, filter: F) -> Vec<ObjectKey>
+    where
+        F: FnMut(ObjectKey, &Body) -> bool,
+    {
+        self.physics.overlap_aabb(aabb, filter)
+    }
+
+    pub fn closest_points(&self, a: ObjectKey, b: ObjectKey) -> Proximity {
+        self.physics.closest_points(a, b)
+    }
+
+    pub fn distance(&self, a: ObjectKey, b: ObjectKey) -> Option<f32> {
+        self.physics.distance(a, b)
+    }
+
+    // Slices every visible object on the CPU. Objects which don't intersect
+    // the slice plane are left out.
+    pub fn slice(
+        &self,
+        slice_plane: &SlicePlane,
+    ) -> Vec<(ObjectKey, Vec<Vertex3>)> {
+        self.meshes
+            .iter()
+            .filter_map(|(key, mesh)| {
+                let triangles = mesh.slice(&self.transform(key)?, slice_plane);
+                if triangles.is_empty() {
+                    None
+                } else {
+                    Some((key, triangles))
+                }
+            })
+            .collect()
+    }
+
+    pub fn export_slice<W: Write>(
+        &self,
+        writer: W,
+        format: SliceFormat,
+        slice_plane: &SlicePlane,
+    ) -> io::Result<()> {
+        let objects: Vec<Vec<Vertex3>> = self
+            .slice(slice_plane)
+            .into_iter()
+            .map(|(_, triangles)| triangles)
+            .collect();
+        export::write_slice(writer, format, &objects)
     }
 }
@@ -3,6 +3,7 @@ use winit::window::Window;
 
 pub struct GraphicsContext {
     pub surface: wgpu::Surface,
+    #[allow(dead_code)]
     pub adapter: wgpu::Adapter,
     pub device: wgpu::Device,
     pub queue: wgpu::Queue,
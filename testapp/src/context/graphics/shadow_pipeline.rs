@@ -1,5 +1,6 @@
 use super::{
-    GraphicsContext, Light, MeshBinding, Vertex3, SHADOW_FORMAT, SHADOW_SIZE,
+    GraphicsContext, Light, MeshBinding, Vertex3, VertexDesc, SHADOW_FORMAT,
+    SHADOW_SIZE,
 };
 
 use anyhow::{anyhow, Context, Result};
@@ -45,10 +45,6 @@ pub struct MeshBinding {
     pub(super) dst_vertex_buffer: wgpu::Buffer,
 }
 
-fn ceil_div(x: u32, y: u32) -> u32 {
-    x / y + if x % y != 0 { 1 } else { 0 }
-}
-
 impl SlicePipeline {
     pub fn new(ctx: &GraphicsContext) -> Result<Self> {
         let shader_src = include_str!("shaders/slice.comp");
@@ -164,15 +160,14 @@ impl SlicePipeline {
     pub fn create_mesh_binding(
         &self,
         ctx: &GraphicsContext,
-        vertices: &Vec<Vertex4>,
-        indices: &Vec<u32>,
+        vertices: &[Vertex4],
+        indices: &[u32],
     ) -> MeshBinding {
         let simplex_count = (indices.len() / 4) as u32;
-        let vertex_buffer_size = (vertices.len()
-            * std::mem::size_of::<Vertex4>())
-            as wgpu::BufferAddress;
+        let vertex_buffer_size =
+            std::mem::size_of_val(vertices) as wgpu::BufferAddress;
         let index_buffer_size =
-            (indices.len() * std::mem::size_of::<u32>()) as wgpu::BufferAddress;
+            std::mem::size_of_val(indices) as wgpu::BufferAddress;
 
         // overestimate of how many triangles can be generated
         let dst_vertex_buffer_size =
@@ -209,12 +204,12 @@ impl SlicePipeline {
         );
 
         let vertex_buffer = ctx.device.create_buffer_with_data(
-            bytemuck::cast_slice(&vertices),
+            bytemuck::cast_slice(vertices),
             wgpu::BufferUsage::COPY_DST | wgpu::BufferUsage::STORAGE_READ,
         );
 
         let index_buffer = ctx.device.create_buffer_with_data(
-            bytemuck::cast_slice(&indices),
+            bytemuck::cast_slice(indices),
             wgpu::BufferUsage::COPY_DST | wgpu::BufferUsage::STORAGE_READ,
         );
 
@@ -361,7 +356,7 @@ impl SlicePipeline {
         compute_pass.set_bind_group(1, &mesh.src_bind_group, &[]);
         compute_pass.set_bind_group(2, &mesh.dst_bind_group, &[]);
         compute_pass.dispatch(
-            ceil_div(mesh.simplex_count, WORK_GROUP_SIZE),
+            mesh.simplex_count.div_ceil(WORK_GROUP_SIZE),
             1,
             1,
         );
@@ -1,5 +1,6 @@
 use super::{
-    GraphicsContext, Light, MeshBinding, Vertex3, ViewProjection, DEPTH_FORMAT,
+    GraphicsContext, Light, MeshBinding, Vertex3, VertexDesc, ViewProjection,
+    DEPTH_FORMAT,
 };
 
 use anyhow::{anyhow, Context, Result};
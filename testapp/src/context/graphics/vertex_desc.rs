@@ -0,0 +1,61 @@
+use super::{Vertex3, Vertex4};
+use cgmath::Vector4;
+
+// The vertex types themselves live in the hypervis crate, which knows nothing
+// about wgpu, so their buffer layouts are described here instead.
+pub trait VertexDesc {
+    fn desc<'a>() -> wgpu::VertexBufferDescriptor<'a>;
+}
+
+impl VertexDesc for Vertex4 {
+    fn desc<'a>() -> wgpu::VertexBufferDescriptor<'a> {
+        use std::mem;
+        wgpu::VertexBufferDescriptor {
+            stride: mem::size_of::<Vertex4>() as wgpu::BufferAddress,
+            step_mode: wgpu::InputStepMode::Vertex,
+            attributes: &[
+                wgpu::VertexAttributeDescriptor {
+                    offset: 0,
+                    shader_location: 0,
+                    format: wgpu::VertexFormat::Float4,
+                },
+                wgpu::VertexAttributeDescriptor {
+                    offset: mem::size_of::<Vector4<f32>>()
+                        as wgpu::BufferAddress,
+                    shader_location: 1,
+                    format: wgpu::VertexFormat::Float4,
+                },
+            ],
+        }
+    }
+}
+
+impl VertexDesc for Vertex3 {
+    fn desc<'a>() -> wgpu::VertexBufferDescriptor<'a> {
+        use std::mem;
+        wgpu::VertexBufferDescriptor {
+            stride: mem::size_of::<Vertex3>() as wgpu::BufferAddress,
+            step_mode: wgpu::InputStepMode::Vertex,
+            attributes: &[
+                wgpu::VertexAttributeDescriptor {
+                    offset: 0,
+                    shader_location: 0,
+                    format: wgpu::VertexFormat::Float4,
+                },
+                wgpu::VertexAttributeDescriptor {
+                    offset: mem::size_of::<Vector4<f32>>()
+                        as wgpu::BufferAddress,
+                    shader_location: 1,
+                    format: wgpu::VertexFormat::Float4,
+                },
+                wgpu::VertexAttributeDescriptor {
+                    offset: mem::size_of::<Vector4<f32>>()
+                        as wgpu::BufferAddress
+                        * 2,
+                    shader_location: 2,
+                    format: wgpu::VertexFormat::Float4,
+                },
+            ],
+        }
+    }
+}
@@ -26,6 +26,7 @@ impl ViewProjection {
         }
     }
 
+    #[allow(dead_code)]
     pub fn world_to_screen(&self, world: Vector4<f32>) -> Vector4<f32> {
         self.view_proj * world
     }
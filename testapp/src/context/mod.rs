@@ -151,7 +151,7 @@ pub async fn run<App: Application>(
                 ctx.imgui_renderer
                     .render(
                         ui.render(),
-                        &mut ctx.graphics_ctx.device,
+                        &ctx.graphics_ctx.device,
                         &mut encoder,
                         &frame.view,
                     )
@@ -1,24 +1,24 @@
-mod alg;
 mod context;
-mod mesh;
-mod mesh4;
-mod physics;
-mod shapes;
-mod util;
-mod world;
+mod world_renderer;
+
+use std::fs::File;
+use std::io::BufWriter;
 
 use anyhow::Result;
-use cgmath::{InnerSpace, Matrix4, Point3, SquareMatrix, Vector4, Zero};
-use winit::event::{WindowEvent, VirtualKeyCode};
+use cgmath::{InnerSpace, Point3, SquareMatrix, Vector4, Zero};
+use winit::event::{VirtualKeyCode, WindowEvent};
 
 use context::graphics::{
     Light, ShadowPipeline, SlicePipeline, SlicePlane, TriangleListPipeline,
     ViewProjection,
 };
 use context::{Application, Ctx, GraphicsContext};
-use physics::Material;
-use shapes::RegularSolid;
-use world::{ObjectKey, World};
+use hypervis::export::SliceFormat;
+use hypervis::physics::Collider;
+use hypervis::scene::{self, Scene};
+use hypervis::shapes::{self, RegularSolid};
+use hypervis::world::{ObjectKey, World};
+use world_renderer::WorldRenderer;
 
 #[derive(Debug)]
 struct DragSelection {
@@ -40,11 +40,13 @@ struct TestApp {
     slice_pipeline: SlicePipeline,
     shadow_pipeline: ShadowPipeline,
     slice_plane: SlicePlane,
+    light: scene::Light,
     shadow_texture: wgpu::TextureView,
     depth_texture: wgpu::TextureView,
     ms_framebuffer: wgpu::TextureView,
     view_proj: ViewProjection,
     world: World,
+    world_renderer: WorldRenderer,
     frames: usize,
     steps: usize,
     cursor_ray: (Vector4<f32>, Vector4<f32>),
@@ -55,26 +57,51 @@ struct TestApp {
 
 const ARENA_SIZE: f32 = 4.0;
 
+// Used when no scene file is given on the command line.
+const DEFAULT_SCENE: &str = include_str!("../../scenes/arena.ron");
+
+impl TestApp {
+    // Dumps the current cross-section into the working directory in every
+    // supported format.
+    fn export_slice(&self) {
+        for path in &["slice.obj", "slice.ply", "slice.glb"] {
+            let format = SliceFormat::from_path(path).unwrap();
+            let result = File::create(path).and_then(|file| {
+                self.world.export_slice(
+                    BufWriter::new(file),
+                    format,
+                    &self.slice_plane,
+                )
+            });
+            match result {
+                Ok(()) => println!("Wrote {}", path),
+                Err(e) => eprintln!("Could not write {}: {}", path, e),
+            }
+        }
+    }
+
+    fn save_scene(&self) {
+        let path = "scene.ron";
+        let scene = self.world.save_scene(&self.slice_plane, &self.light);
+        match scene.save(path) {
+            Ok(()) => println!("Wrote {}", path),
+            Err(e) => eprintln!("Could not write {}: {}", path, e),
+        }
+    }
+}
+
 impl Application for TestApp {
     fn init(ctx: &mut Ctx) -> Self {
-        let orthogonal = SlicePlane {
-            normal: Vector4::new(0.0, 0.0, 0.0, 1.0),
-            base_point: Vector4::new(0.0, 0.0, 0.0, 0.0),
-            #[rustfmt::skip]
-            proj_matrix: Matrix4::new(
-                1.0, 0.0, 0.0, 0.0,
-                0.0, 1.0, 0.0, 0.0,
-                0.0, 0.0, 1.0, 0.0,
-                0.0, 0.0, 0.0, 1.0,
-            ),
+        let scene = match std::env::args().nth(1) {
+            Some(path) => Scene::load(path).unwrap(),
+            None => Scene::from_ron(DEFAULT_SCENE).unwrap(),
         };
 
-        let slice_plane = orthogonal;
-
+        let slice_plane = scene.slice_plane;
         let light = Light::new(
-            Point3::new(-4.0, 10.0, -6.0),
-            60.0,
-            Vector4::new(1.0, 1.0, 1.0, 1.0),
+            scene.light.position,
+            scene.light.fovy,
+            scene.light.color,
         );
 
         let shadow_pipeline = ShadowPipeline::new(&ctx.graphics_ctx).unwrap();
@@ -93,45 +120,7 @@ impl Application for TestApp {
         let slice_pipeline = SlicePipeline::new(&ctx.graphics_ctx).unwrap();
 
         let mut world = World::new();
-
-        world.objects.insert(shapes::create_floor(
-            &ctx.graphics_ctx,
-            &slice_pipeline,
-            2.0 * ARENA_SIZE,
-            Material { restitution: 0.4 },
-        ));
-
-        // side walls
-        world.objects.insert(shapes::create_wall(
-            -ARENA_SIZE * Vector4::unit_x(),
-            Vector4::unit_x(),
-            Material { restitution: 0.4 },
-        ));
-        world.objects.insert(shapes::create_wall(
-            ARENA_SIZE * Vector4::unit_x(),
-            -Vector4::unit_x(),
-            Material { restitution: 0.4 },
-        ));
-        world.objects.insert(shapes::create_wall(
-            -ARENA_SIZE * Vector4::unit_z(),
-            Vector4::unit_z(),
-            Material { restitution: 0.4 },
-        ));
-        world.objects.insert(shapes::create_wall(
-            ARENA_SIZE * Vector4::unit_z(),
-            -Vector4::unit_z(),
-            Material { restitution: 0.4 },
-        ));
-        world.objects.insert(shapes::create_wall(
-            -ARENA_SIZE * Vector4::unit_w(),
-            Vector4::unit_w(),
-            Material { restitution: 0.4 },
-        ));
-        world.objects.insert(shapes::create_wall(
-            ARENA_SIZE * Vector4::unit_w(),
-            -Vector4::unit_w(),
-            Material { restitution: 0.4 },
-        ));
+        world.load_scene(&scene).unwrap();
 
         let view_proj = ViewProjection::new(
             ctx,
@@ -150,11 +139,13 @@ impl Application for TestApp {
             slice_pipeline,
             shadow_pipeline,
             slice_plane,
+            light: scene.light,
             shadow_texture,
             ms_framebuffer,
             depth_texture,
             view_proj,
             world,
+            world_renderer: WorldRenderer::new(),
             frames: 0,
             steps: 0,
             cursor_ray: (Vector4::zero(), Vector4::unit_z()),
@@ -218,41 +209,31 @@ impl Application for TestApp {
                 button: winit::event::MouseButton::Left,
                 ..
             } => {
-                let mut min_lambda = std::f32::INFINITY;
-                let mut selection = None;
-                for (key, object) in self.world.objects.iter() {
-                    match object
-                        .body
-                        .ray_intersect(self.cursor_ray.0, self.cursor_ray.1)
-                    {
-                        Some(lambda) => {
-                            if lambda < min_lambda {
-                                selection = Some(key);
-                                min_lambda = lambda;
-                            }
-                        }
-                        None => (),
-                    }
-                }
+                // The floor can't be dragged around, so isn't worth picking.
+                let hit = self.world.raycast(
+                    self.cursor_ray.0,
+                    self.cursor_ray.1,
+                    f32::INFINITY,
+                    |_, body| {
+                        !matches!(body.collider, Collider::HalfSpace { .. })
+                    },
+                );
 
-                match selection {
-                    Some(key) => {
-                        let object = &self.world.objects[key];
-                        let contact_point =
-                            self.cursor_ray.0 + self.cursor_ray.1 * min_lambda;
-                        let plane_normal = Vector4::unit_y();
-                        let plane_distance = contact_point.dot(plane_normal);
-                        let anchor_offset = contact_point - object.body.pos;
-
-                        self.selection = Some(key);
-                        self.drag_selection = Some(DragSelection {
-                            key,
-                            plane_normal,
-                            plane_distance,
-                            anchor_offset,
-                        });
-                    }
-                    _ => (),
+                if let Some(hit) = hit {
+                    let key = hit.key;
+                    let body = &self.world.physics.bodies[key];
+                    let contact_point = hit.point;
+                    let plane_normal = Vector4::unit_y();
+                    let plane_distance = contact_point.dot(plane_normal);
+                    let anchor_offset = contact_point - body.pos;
+
+                    self.selection = Some(key);
+                    self.drag_selection = Some(DragSelection {
+                        key,
+                        plane_normal,
+                        plane_distance,
+                        anchor_offset,
+                    });
                 }
             }
             WindowEvent::MouseInput {
@@ -294,7 +275,8 @@ impl Application for TestApp {
         let dt = 1f32 / 60f32;
 
         if let Some(selection) = &mut self.drag_selection {
-            if let Some(object) = self.world.objects.get_mut(selection.key) {
+            if let Some(body) = self.world.physics.bodies.get_mut(selection.key)
+            {
                 // intersect the current screen ray with the plane
                 let lambda = (selection.plane_distance
                     - self.cursor_ray.0.dot(selection.plane_normal))
@@ -315,15 +297,16 @@ impl Application for TestApp {
                     selection.anchor_offset += Vector4::unit_w() * 0.02;
                 }
 
+                body.wake();
                 let displacement =
-                    contact_point - selection.anchor_offset - object.body.pos;
+                    contact_point - selection.anchor_offset - body.pos;
                 let spring_constant = 0.5;
 
-                object.body.vel.linear += displacement * spring_constant;
+                body.vel.linear += displacement * spring_constant;
 
                 // damping
-                object.body.vel.linear *= 0.8;
-                object.body.vel.angular = 0.8 * object.body.vel.angular;
+                body.vel.linear *= 0.8;
+                body.vel.angular = 0.8 * body.vel.angular;
             }
         }
 
@@ -349,96 +332,114 @@ impl Application for TestApp {
 
         Window::new(im_str!("controls")).build(ui, || {
             if ui.button(im_str!("Spawn a tesseract"), [0.0, 0.0]) {
-                self.world.objects.insert(
+                self.world.insert(
                     shapes::ShapeBuilder::new()
                         .regular_solid(RegularSolid::EightCell)
-                        .build(graphics_ctx, &self.slice_pipeline),
+                        .build()
+                        .unwrap(),
                 );
             }
             if ui.button(im_str!("Spawn a sphere"), [0.0, 0.0]) {
-                self.world.objects.insert(
-                    shapes::ShapeBuilder::new()
-                        .sphere(0.5)
-                        .build(graphics_ctx, &self.slice_pipeline),
+                self.world.insert(
+                    shapes::ShapeBuilder::new().sphere(0.5).build().unwrap(),
                 );
             }
             if ui.button(im_str!("Spawn a 5-cell"), [0.0, 0.0]) {
-                self.world.objects.insert(
+                self.world.insert(
                     shapes::ShapeBuilder::new()
                         .regular_solid(RegularSolid::FiveCell)
-                        .build(graphics_ctx, &self.slice_pipeline),
+                        .build()
+                        .unwrap(),
                 );
             }
             if ui.button(im_str!("Spawn a 16-cell"), [0.0, 0.0]) {
-                self.world.objects.insert(
+                self.world.insert(
                     shapes::ShapeBuilder::new()
                         .regular_solid(RegularSolid::SixteenCell)
-                        .build(graphics_ctx, &self.slice_pipeline),
+                        .build()
+                        .unwrap(),
                 );
             }
             if ui.button(im_str!("Spawn a 24-cell"), [0.0, 0.0]) {
-                self.world.objects.insert(
+                self.world.insert(
                     shapes::ShapeBuilder::new()
                         .regular_solid(RegularSolid::TwentyFourCell)
-                        .build(graphics_ctx, &self.slice_pipeline),
+                        .build()
+                        .unwrap(),
                 );
             }
 
+            if ui.button(im_str!("Export slice"), [0.0, 0.0]) {
+                self.export_slice();
+            }
+            if ui.button(im_str!("Save scene"), [0.0, 0.0]) {
+                self.save_scene();
+            }
+
             ui.text("Left click to select and drag an object.");
             ui.text("Right click to deselect.");
             ui.text("While dragging:");
             ui.text("W/S: raise/lower");
             ui.text("A/D: move in 4th dimension");
 
-            if let Some(obj) = self
+            if let Some(body) = self
                 .selection
-                .and_then(|key| self.world.objects.get_mut(key))
+                .and_then(|key| self.world.physics.bodies.get_mut(key))
             {
+                let mut changed = false;
                 ui.text("Position:");
                 {
                     let token = ui.push_id("position");
-                    Slider::new(im_str!("x"), -ARENA_SIZE..=ARENA_SIZE)
-                        .build(ui, &mut obj.body.pos.x);
-                    Slider::new(im_str!("y"), -ARENA_SIZE..=ARENA_SIZE)
-                        .build(ui, &mut obj.body.pos.y);
-                    Slider::new(im_str!("z"), -ARENA_SIZE..=ARENA_SIZE)
-                        .build(ui, &mut obj.body.pos.z);
-                    Slider::new(im_str!("w"), -ARENA_SIZE..=ARENA_SIZE)
-                        .build(ui, &mut obj.body.pos.w);
+                    changed |=
+                        Slider::new(im_str!("x"), -ARENA_SIZE..=ARENA_SIZE)
+                            .build(ui, &mut body.pos.x);
+                    changed |=
+                        Slider::new(im_str!("y"), -ARENA_SIZE..=ARENA_SIZE)
+                            .build(ui, &mut body.pos.y);
+                    changed |=
+                        Slider::new(im_str!("z"), -ARENA_SIZE..=ARENA_SIZE)
+                            .build(ui, &mut body.pos.z);
+                    changed |=
+                        Slider::new(im_str!("w"), -ARENA_SIZE..=ARENA_SIZE)
+                            .build(ui, &mut body.pos.w);
                     token.pop(ui);
                 }
 
                 ui.text("Velocity:");
                 {
                     let token = ui.push_id("velocity");
-                    Slider::new(im_str!("x"), -10.0..=10.0)
-                        .build(ui, &mut obj.body.vel.linear.x);
-                    Slider::new(im_str!("y"), -10.0..=10.0)
-                        .build(ui, &mut obj.body.vel.linear.y);
-                    Slider::new(im_str!("z"), -10.0..=10.0)
-                        .build(ui, &mut obj.body.vel.linear.z);
-                    Slider::new(im_str!("w"), -10.0..=10.0)
-                        .build(ui, &mut obj.body.vel.linear.w);
+                    changed |= Slider::new(im_str!("x"), -10.0..=10.0)
+                        .build(ui, &mut body.vel.linear.x);
+                    changed |= Slider::new(im_str!("y"), -10.0..=10.0)
+                        .build(ui, &mut body.vel.linear.y);
+                    changed |= Slider::new(im_str!("z"), -10.0..=10.0)
+                        .build(ui, &mut body.vel.linear.z);
+                    changed |= Slider::new(im_str!("w"), -10.0..=10.0)
+                        .build(ui, &mut body.vel.linear.w);
                     token.pop(ui);
                 }
 
                 ui.text("Angular Velocity:");
                 {
                     let token = ui.push_id("angular_velocity");
-                    Slider::new(im_str!("xy"), -10.0..=10.0)
-                        .build(ui, &mut obj.body.vel.angular.xy);
-                    Slider::new(im_str!("xz"), -10.0..=10.0)
-                        .build(ui, &mut obj.body.vel.angular.xz);
-                    Slider::new(im_str!("xw"), -10.0..=10.0)
-                        .build(ui, &mut obj.body.vel.angular.xw);
-                    Slider::new(im_str!("yz"), -10.0..=10.0)
-                        .build(ui, &mut obj.body.vel.angular.yz);
-                    Slider::new(im_str!("yw"), -10.0..=10.0)
-                        .build(ui, &mut obj.body.vel.angular.yw);
-                    Slider::new(im_str!("zw"), -10.0..=10.0)
-                        .build(ui, &mut obj.body.vel.angular.zw);
+                    changed |= Slider::new(im_str!("xy"), -10.0..=10.0)
+                        .build(ui, &mut body.vel.angular.xy);
+                    changed |= Slider::new(im_str!("xz"), -10.0..=10.0)
+                        .build(ui, &mut body.vel.angular.xz);
+                    changed |= Slider::new(im_str!("xw"), -10.0..=10.0)
+                        .build(ui, &mut body.vel.angular.xw);
+                    changed |= Slider::new(im_str!("yz"), -10.0..=10.0)
+                        .build(ui, &mut body.vel.angular.yz);
+                    changed |= Slider::new(im_str!("yw"), -10.0..=10.0)
+                        .build(ui, &mut body.vel.angular.yw);
+                    changed |= Slider::new(im_str!("zw"), -10.0..=10.0)
+                        .build(ui, &mut body.vel.angular.zw);
                     token.pop(ui);
                 }
+
+                if changed {
+                    body.wake();
+                }
             }
         });
 
@@ -448,11 +449,17 @@ impl Application for TestApp {
             },
         );
 
-        self.world.compute(
+        self.world_renderer.sync(
+            graphics_ctx,
+            &self.slice_pipeline,
+            &self.world,
+        );
+        self.world_renderer.compute(
             graphics_ctx,
             &self.slice_pipeline,
             &mut encoder,
             &self.slice_plane,
+            &self.world,
         );
 
         // for some reason I need to do the compute and render passes in two
@@ -485,7 +492,7 @@ impl Application for TestApp {
                     ),
                 });
 
-            self.world
+            self.world_renderer
                 .shadow_pass(&self.shadow_pipeline, &mut shadow_pass);
         }
 
@@ -519,7 +526,8 @@ impl Application for TestApp {
                     ),
                 });
 
-            self.world.render(&self.render_pipeline, &mut render_pass);
+            self.world_renderer
+                .render(&self.render_pipeline, &mut render_pass);
         }
 
         graphics_ctx.queue.submit(&[encoder.finish()]);
@@ -0,0 +1,92 @@
+use slotmap::SecondaryMap;
+
+use crate::context::{
+    graphics::{
+        MeshBinding, ShadowPipeline, SlicePipeline, SlicePlane,
+        TriangleListPipeline,
+    },
+    GraphicsContext,
+};
+use hypervis::world::{ObjectKey, World};
+
+// Keeps track of the GPU resources for each mesh in a World. The World itself
+// only holds CPU-side tetrahedral meshes, which get uploaded here the first
+// time they're seen.
+pub struct WorldRenderer {
+    mesh_bindings: SecondaryMap<ObjectKey, MeshBinding>,
+}
+
+impl WorldRenderer {
+    pub fn new() -> Self {
+        Self {
+            mesh_bindings: SecondaryMap::new(),
+        }
+    }
+
+    pub fn sync(
+        &mut self,
+        graphics_ctx: &GraphicsContext,
+        pipeline: &SlicePipeline,
+        world: &World,
+    ) {
+        // drop bindings for objects that no longer exist
+        self.mesh_bindings
+            .retain(|key, _| world.meshes.contains_key(key));
+
+        for (key, mesh) in world.meshes.iter() {
+            if self.mesh_bindings.contains_key(key) {
+                continue;
+            }
+
+            let mesh_binding = pipeline.create_mesh_binding(
+                graphics_ctx,
+                &mesh.vertices,
+                &mesh.indices,
+            );
+            self.mesh_bindings.insert(key, mesh_binding);
+        }
+    }
+
+    pub fn compute(
+        &self,
+        graphics_ctx: &GraphicsContext,
+        pipeline: &SlicePipeline,
+        encoder: &mut wgpu::CommandEncoder,
+        slice_plane: &SlicePlane,
+        world: &World,
+    ) {
+        for (key, mesh_binding) in self.mesh_bindings.iter() {
+            let transform = match world.transform(key) {
+                Some(transform) => transform,
+                None => continue,
+            };
+            pipeline.render_mesh(
+                graphics_ctx,
+                encoder,
+                slice_plane,
+                &transform,
+                mesh_binding,
+            );
+        }
+    }
+
+    pub fn render<'a: 'c, 'b, 'c>(
+        &'a self,
+        pipeline: &'a TriangleListPipeline,
+        render_pass: &'b mut wgpu::RenderPass<'c>,
+    ) {
+        for mesh_binding in self.mesh_bindings.values() {
+            pipeline.render(render_pass, mesh_binding);
+        }
+    }
+
+    pub fn shadow_pass<'a: 'c, 'b, 'c>(
+        &'a self,
+        pipeline: &'a ShadowPipeline,
+        render_pass: &'b mut wgpu::RenderPass<'c>,
+    ) {
+        for mesh_binding in self.mesh_bindings.values() {
+            pipeline.render(render_pass, mesh_binding);
+        }
+    }
+}